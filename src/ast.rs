@@ -25,6 +25,12 @@ pub struct Entity {
     pub policies: Vec<Policy>,
     /// Whether this entity is marked as auth entity
     pub is_auth: bool,
+    /// Multi-column indexes, each listing its columns in order
+    #[serde(default)]
+    pub indexes: Vec<Vec<String>>,
+    /// Previous name from `@renamed_from("Old")`, for migrations
+    #[serde(default)]
+    pub renamed_from: Option<String>,
     pub location: SourceLocation,
 }
 
@@ -60,6 +66,25 @@ pub enum FieldType {
     Optional(Box<FieldType>),
 }
 
+impl std::fmt::Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::String => write!(f, "string"),
+            FieldType::Number => write!(f, "number"),
+            FieldType::Boolean => write!(f, "boolean"),
+            FieldType::DateTime => write!(f, "datetime"),
+            FieldType::Uuid => write!(f, "uuid"),
+            FieldType::Email => write!(f, "email"),
+            FieldType::Enum(values) => write!(f, "{}", values.join(" | ")),
+            FieldType::Reference(name) => write!(f, "{}", name),
+            FieldType::Ref(name) => write!(f, "ref<{}>", name),
+            FieldType::Array(inner) => write!(f, "[{}]", inner),
+            FieldType::List(inner) => write!(f, "list<{}>", inner),
+            FieldType::Optional(inner) => write!(f, "{}?", inner),
+        }
+    }
+}
+
 /// Field and action decorators
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Decorator {
@@ -73,6 +98,8 @@ pub enum Decorator {
     Auto,
     /// @index - creates database index
     Index,
+    /// @expose - allows a secret-looking field (e.g. password_hash) in action outputs
+    Expose,
     /// @default(value) - sets default value
     Default(String),
     /// @validate(min:, max:, pattern:) - validation constraints
@@ -85,6 +112,8 @@ pub enum Decorator {
     Map { target: String, transform: MapTransform },
     /// @policy(Name) - enforces a policy
     Policy(String),
+    /// @renamed_from("old_name") - the field's previous name, for migrations
+    RenamedFrom(String),
 }
 
 /// Validation constraints for @validate decorator
@@ -98,16 +127,13 @@ pub struct ValidationConstraints {
 
 /// Map transform types for @map decorator
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Default)]
 pub enum MapTransform {
+    #[default]
     None,
     Hash,
 }
 
-impl Default for MapTransform {
-    fn default() -> Self {
-        MapTransform::None
-    }
-}
 
 /// HTTP methods for API decorators
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -230,7 +256,7 @@ pub struct Predicate {
 }
 
 /// Field reference in predicates and expressions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum FieldReference {
     /// Reference to input field: input.email or just email
     InputField(String),
@@ -249,11 +275,24 @@ pub enum CompareOp {
     Greater,    // >
 }
 
+impl std::fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompareOp::Equal => write!(f, "=="),
+            CompareOp::NotEqual => write!(f, "!="),
+            CompareOp::Less => write!(f, "<"),
+            CompareOp::Greater => write!(f, ">"),
+        }
+    }
+}
+
 /// Output section for action
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputSection {
     pub entity: String,
     pub fields: Vec<String>,
+    /// Location of the `Entity(fields)` projection
+    pub location: SourceLocation,
 }
 
 /// Parameter for an action
@@ -350,7 +389,7 @@ impl std::fmt::Display for LogicalOperator {
 }
 
 /// Literal values in expressions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LiteralValue {
     String(String),
     Number(f64),
@@ -412,60 +451,6 @@ impl IntentFile {
     }
 }
 
-impl Action {
-    /// Infer which entity this action belongs to
-    pub fn infer_entity(&self, ast: &IntentFile) -> Option<String> {
-        // 1. Explicit output entity
-        if let Some(output) = &self.output {
-            return Some(output.entity.clone());
-        }
-
-        // 2. Look for mutations/deletions in process
-        if let Some(process) = &self.process {
-            for step in &process.steps {
-                match step {
-                    ProcessStep::Mutate(m) => return Some(m.entity.clone()),
-                    ProcessStep::Delete(d) => return Some(d.entity.clone()),
-                    ProcessStep::Derive(d) => {
-                        if let DeriveValue::Select { entity, .. } = &d.value {
-                            return Some(entity.clone());
-                        }
-                    }
-                }
-            }
-        }
-
-        // 3. Fallback to @api path matching
-        if let Some(Decorator::Api { path, .. }) = self.decorators.iter().find(|d| matches!(d, Decorator::Api { .. })) {
-            for entity in &ast.entities {
-                let entity_name_lower = entity.name.to_lowercase();
-                let prefix = format!("/{}s", entity_name_lower);
-                let prefix_single = format!("/{}", entity_name_lower);
-                if path.starts_with(&prefix) || path.starts_with(&prefix_single) {
-                    return Some(entity.name.clone());
-                }
-            }
-            
-            // Special case for /auth paths and auth entity
-            if path.starts_with("/auth") {
-                if let Some(auth_entity) = &ast.auth_entity {
-                    return Some(auth_entity.clone());
-                }
-            }
-        }
-
-        // 4. Heuristic by name for auth actions
-        if let Some(auth_entity) = &ast.auth_entity {
-            let auth_names = ["register", "login", "logout", "get_me", "get_my_auth", "forgot_password", "reset_password", "signup"];
-            if auth_names.contains(&self.name.as_str()) {
-                return Some(auth_entity.clone());
-            }
-        }
-
-        None
-    }
-}
-
 impl Default for IntentFile {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,366 @@
+// Intent Compiler - Security Audit
+// Flags risky intent definitions with a severity and a remediation hint
+
+use std::fmt;
+
+use clap::ValueEnum;
+use colored::Colorize;
+
+use crate::ast::*;
+use crate::error::{SourceMap, format_location};
+use crate::validator::is_secret_name;
+
+/// How serious an audit finding is
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
+/// A single audit finding
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    /// None for findings about generated code rather than the intent file
+    pub location: Option<SourceLocation>,
+    pub hint: String,
+}
+
+impl Finding {
+    fn new(
+        severity: Severity,
+        code: &'static str,
+        message: impl Into<String>,
+        location: Option<&SourceLocation>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            location: location.cloned(),
+            hint: hint.into(),
+        }
+    }
+
+    pub fn format_colored(&self, sources: &SourceMap) -> String {
+        let label = format!("{}[{}]", self.severity, self.code);
+        let label = match self.severity {
+            Severity::High => label.red().bold(),
+            Severity::Medium => label.yellow().bold(),
+            Severity::Low => label.blue().bold(),
+        };
+        let mut output = format!("{}: {}\n", label, self.message.white());
+        if let Some(location) = &self.location {
+            let (file, location) = sources.locate(location);
+            output.push_str(&format_location(file, &location));
+        }
+        output.push_str(&format!("  {} {}\n", "hint:".cyan().bold(), self.hint));
+        output
+    }
+}
+
+/// Field names that tie a record to its owner or tenant
+const OWNER_FIELDS: &[&str] = &["user_id", "owner_id", "author_id", "account_id", "tenant_id", "org_id", "organization_id"];
+
+/// Audit a parsed file, before auth actions are injected.
+/// Findings are sorted by severity (highest first), then by position.
+pub fn audit(file: &IntentFile) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for action in &file.actions {
+        check_unprotected_write(action, &mut findings);
+        check_secret_output(action, file, &mut findings);
+        check_plaintext_password(action, &mut findings);
+        check_unscoped_list(action, file, &mut findings);
+    }
+    check_auth_identifier(file, &mut findings);
+    check_default_secret_key(file, &mut findings);
+
+    findings.sort_by_key(|f| {
+        // Findings about generated code have no position and go last within their severity
+        let position = f.location.as_ref().map_or((usize::MAX, 0), |l| (l.line, l.column));
+        (std::cmp::Reverse(f.severity), position)
+    });
+    findings
+}
+
+fn has_auth(action: &Action) -> bool {
+    action
+        .decorators
+        .iter()
+        .any(|d| matches!(d, Decorator::Auth { .. } | Decorator::Policy(_)))
+}
+
+fn api_method(action: &Action) -> Option<(&HttpMethod, &str)> {
+    action.decorators.iter().find_map(|d| match d {
+        Decorator::Api { method, path } => Some((method, path.as_str())),
+        _ => None,
+    })
+}
+
+/// S0101: endpoints that change data without @auth or @policy
+fn check_unprotected_write(action: &Action, findings: &mut Vec<Finding>) {
+    if has_auth(action) {
+        return;
+    }
+    let writes_in_process = action.process.iter().flat_map(|p| &p.steps).find_map(|step| match step {
+        ProcessStep::Mutate(m) => Some(format!("mutates {}", m.entity)),
+        ProcessStep::Delete(d) => Some(format!("deletes {}", d.entity)),
+        ProcessStep::Derive(_) => None,
+    });
+    let writes = writes_in_process.or_else(|| match api_method(action) {
+        Some((method @ (HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch | HttpMethod::Delete), _)) => {
+            Some(format!("is a {} endpoint", method))
+        }
+        _ => None,
+    });
+    if let Some(what) = writes {
+        findings.push(Finding::new(
+            Severity::High,
+            "S0101",
+            format!("Action '{}' {} but requires no authentication", action.name, what),
+            Some(&action.location),
+            "Add @auth, or @policy(...) to restrict who may call it",
+        ));
+    }
+}
+
+/// S0102: credential fields returned to clients via @expose
+fn check_secret_output(action: &Action, file: &IntentFile, findings: &mut Vec<Finding>) {
+    let Some(output) = &action.output else { return };
+    let Some(entity) = file.find_entity(&output.entity) else { return };
+    for name in output.fields.iter().filter(|f| is_secret_name(f)) {
+        if entity.fields.iter().any(|f| &f.name == name) {
+            findings.push(Finding::new(
+                Severity::High,
+                "S0102",
+                format!("Action '{}' returns credential field '{}.{}'", action.name, entity.name, name),
+                Some(&action.location),
+                "Remove the field from the output projection",
+            ));
+        }
+    }
+}
+
+/// S0103: passwords stored without hashing
+fn check_plaintext_password(action: &Action, findings: &mut Vec<Finding>) {
+    for param in action.input.iter().flat_map(|i| &i.fields) {
+        let unhashed = param
+            .decorators
+            .iter()
+            .any(|d| matches!(d, Decorator::Map { transform: MapTransform::None, .. }));
+        if unhashed && is_secret_name(&param.name) {
+            findings.push(Finding::new(
+                Severity::High,
+                "S0103",
+                format!("Input '{}' of action '{}' is mapped without hashing", param.name, action.name),
+                Some(&param.location),
+                format!("Use @map(..., hash) on '{}'", param.name),
+            ));
+        }
+    }
+
+    for step in action.process.iter().flat_map(|p| &p.steps) {
+        let ProcessStep::Mutate(mutate) = step else { continue };
+        for setter in mutate.setters.iter().filter(|s| is_secret_name(&s.field)) {
+            let hashed = matches!(&setter.value, DeriveValue::Compute { function, .. } if function == "hash");
+            if !hashed {
+                findings.push(Finding::new(
+                    Severity::High,
+                    "S0103",
+                    format!("'{}.{}' is set without hashing in action '{}'", mutate.entity, setter.field, action.name),
+                    Some(&setter.location),
+                    format!("Store a hash instead: set {} = hash(...)", setter.field),
+                ));
+            }
+        }
+    }
+}
+
+/// S0104: auth entities that cannot identify a user uniquely
+fn check_auth_identifier(file: &IntentFile, findings: &mut Vec<Finding>) {
+    for entity in file.entities.iter().filter(|e| e.is_auth) {
+        let has_identifier = entity
+            .fields
+            .iter()
+            .any(|f| f.decorators.contains(&Decorator::Unique) && !f.decorators.contains(&Decorator::Primary));
+        if !has_identifier {
+            findings.push(Finding::new(
+                Severity::Medium,
+                "S0104",
+                format!("Auth entity '{}' has no @unique login identifier", entity.name),
+                Some(&entity.location),
+                "Mark the login field @unique, e.g. email: email @unique",
+            ));
+        }
+    }
+}
+
+/// S0105: list endpoints over owned records that return every owner's rows
+fn check_unscoped_list(action: &Action, file: &IntentFile, findings: &mut Vec<Finding>) {
+    let Some((HttpMethod::Get, path)) = api_method(action) else { return };
+    if path.contains('{') || action.decorators.iter().any(|d| matches!(d, Decorator::Policy(_))) {
+        return;
+    }
+    let Some(entity) = action.output.as_ref().and_then(|o| file.find_entity(&o.entity)) else { return };
+    let owners: Vec<&Field> = entity
+        .fields
+        .iter()
+        .filter(|f| {
+            OWNER_FIELDS.contains(&f.name.as_str())
+                || matches!(&f.field_type, FieldType::Reference(r) | FieldType::Ref(r) if file.auth_entity.as_ref() == Some(r))
+        })
+        .collect();
+    if owners.iter().any(|owner| selects_own(action, &entity.name, &owner.name)) {
+        return;
+    }
+    if let Some(owner) = owners.first() {
+        findings.push(Finding::new(
+            Severity::Medium,
+            "S0105",
+            format!(
+                "Action '{}' lists every {} regardless of '{}'",
+                action.name, entity.name, owner.name
+            ),
+            Some(&action.location),
+            format!("Filter by the caller, e.g. select {} where {} == current_user.id, or add a @policy", entity.name, owner.name),
+        ));
+    }
+}
+
+/// Whether the action selects the caller's `entity` records, as in
+/// `select Order where user_id == current_user.id`
+fn selects_own(action: &Action, entity: &str, owner: &str) -> bool {
+    let Some(process) = &action.process else { return false };
+    let caller = FieldReference::DerivedField { name: "current_user".to_string(), field: "id".to_string() };
+    process.steps.iter().any(|step| match step {
+        ProcessStep::Derive(DeriveStatement { value: DeriveValue::Select { entity: selected, predicate }, .. }) => {
+            selected == entity
+                && predicate.operator == CompareOp::Equal
+                && predicate.field == FieldReference::InputField(owner.to_string())
+                && predicate.value == caller
+        }
+        _ => false,
+    })
+}
+
+/// S0106: the generated config falls back to a secret key that is public
+fn check_default_secret_key(file: &IntentFile, findings: &mut Vec<Finding>) {
+    let uses_jwt = file.auth_entity.is_some()
+        || file
+            .actions
+            .iter()
+            .any(|a| a.decorators.iter().any(|d| matches!(d, Decorator::Auth { .. })));
+    if uses_jwt {
+        findings.push(Finding::new(
+            Severity::Medium,
+            "S0106",
+            "Generated config.py signs tokens with a hard-coded default secret_key",
+            None,
+            "Set SECRET_KEY in the environment or .env of every deployment",
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        audit(&parse_intent(source).unwrap()).iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn test_unprotected_write_and_plaintext_password() {
+        let found = codes(
+            r#"entity Account:
+    id: uuid @primary
+    password_hash: string
+
+@api POST /accounts
+action create_account:
+    input:
+        password: string
+    process:
+        mutate Account:
+            set password_hash = input.password
+    output: Account(id)
+"#,
+        );
+        assert_eq!(found, vec!["S0101", "S0103"]);
+    }
+
+    #[test]
+    fn test_auth_entity_findings_sorted_by_severity() {
+        let findings = audit(
+            &parse_intent(
+                r#"auth entity User:
+    id: uuid @primary
+    email: email
+    password_hash: string
+
+entity Note:
+    id: uuid @primary
+    user_id: uuid
+    body: string
+
+@api GET /notes
+@auth
+action list_notes:
+    output: Note(id, body)
+"#,
+            )
+            .unwrap(),
+        );
+        let found: Vec<_> = findings.iter().map(|f| (f.severity, f.code)).collect();
+        assert_eq!(
+            found,
+            vec![(Severity::Medium, "S0104"), (Severity::Medium, "S0105"), (Severity::Medium, "S0106")]
+        );
+    }
+
+    #[test]
+    fn test_protected_actions_are_clean() {
+        let found = codes(
+            r#"entity Post:
+    id: uuid @primary
+    title: string
+
+@api POST /posts
+@policy(Editor)
+action create_post:
+    output: Post(id, title)
+"#,
+        );
+        assert!(found.is_empty(), "{:?}", found);
+    }
+
+    #[test]
+    fn test_list_is_scoped_only_by_the_caller() {
+        let list = |predicate: &str| {
+            let source = format!(
+                "entity Note:\n    id: uuid @primary\n    user_id: uuid\n    archived: boolean\n\n\
+                 @api GET /notes\n@auth\naction list_notes:\n    process:\n        derive notes = select Note where {}\n    output: Note(id)\n",
+                predicate
+            );
+            codes(&source).contains(&"S0105")
+        };
+        assert!(list("archived == false"));
+        assert!(list("user_id == input.user_id"));
+        assert!(!list("user_id == current_user.id"));
+    }
+}
@@ -3,8 +3,9 @@
 
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
+use crate::audit::Severity;
 use crate::codegen::TargetLanguage;
 
 /// Intent Compiler - Transform IDL into production-ready backend code
@@ -26,28 +27,231 @@ pub struct Cli {
 pub enum Commands {
     /// Compile an intent file to target language
     Compile {
-        /// Input .intent file path
+        /// Input .intent file path (default: [project] inputs in intent.toml)
+        #[arg(short, long, conflicts_with = "from_ast")]
+        input: Option<PathBuf>,
+
+        /// Generate from a JSON AST written by `intentc ast` instead of an intent file
+        #[arg(long, value_name = "FILE")]
+        from_ast: Option<PathBuf>,
+
+        /// Output directory for generated code (default: [project] output, else ./output)
         #[arg(short, long)]
-        input: PathBuf,
+        output: Option<PathBuf>,
+
+        /// Target language (python) - optional, defaults to python
+        #[arg(short, long)]
+        target: Option<String>,
+
+        /// Generate tests (the default unless intent.toml disables them)
+        #[arg(long, overrides_with = "no_tests")]
+        tests: bool,
 
-        /// Output directory for generated code
-        #[arg(short, long, default_value = "./output")]
-        output: PathBuf,
+        /// Do not generate tests
+        #[arg(long, overrides_with = "tests")]
+        no_tests: bool,
+
+        /// Database URL written to the generated settings
+        #[arg(long, value_name = "URL")]
+        database_url: Option<String>,
+
+        /// Add the indexes suggested by the index advisor to the ORM and migrations
+        #[arg(long)]
+        auto_index: bool,
+
+        /// Overwrite or delete files even if they were edited by hand or are user-owned
+        #[arg(long)]
+        force: bool,
+
+        /// List the files that would be created, updated or deleted without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Generate migrations that drop tables or columns or narrow column types
+        #[arg(long)]
+        allow_destructive: bool,
+
+        /// Recompile whenever the input files, intent.toml or template overrides change
+        #[arg(long)]
+        watch: bool,
+
+        /// Suppress warnings with the given code (repeatable)
+        #[arg(long = "allow", value_name = "CODE")]
+        allow: Vec<String>,
+
+        /// Diagnostic output format
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+
+    /// Show what recompiling would change in the output directory
+    Diff {
+        /// Input .intent file path (default: [project] inputs in intent.toml)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Output directory to compare against (default: [project] output, else ./output)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
 
         /// Target language (python) - optional, defaults to python
         #[arg(short, long)]
         target: Option<String>,
 
-        /// Generate tests
-        #[arg(long, default_value = "true")]
+        /// Generate tests (the default unless intent.toml disables them)
+        #[arg(long, overrides_with = "no_tests")]
         tests: bool,
+
+        /// Do not generate tests
+        #[arg(long, overrides_with = "tests")]
+        no_tests: bool,
+
+        /// Database URL written to the generated settings
+        #[arg(long, value_name = "URL")]
+        database_url: Option<String>,
+
+        /// Add the indexes suggested by the index advisor to the ORM and migrations
+        #[arg(long)]
+        auto_index: bool,
+
+        /// Only print the per-file summary, not the diff itself
+        #[arg(long)]
+        stat: bool,
+
+        /// Exit with an error if recompiling would change anything
+        #[arg(long)]
+        exit_code: bool,
+
+        /// Suppress warnings with the given code (repeatable)
+        #[arg(long = "allow", value_name = "CODE")]
+        allow: Vec<String>,
     },
 
     /// Validate an intent file without generating code
     Check {
+        /// Input .intent file path (default: [project] inputs in intent.toml)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Check again whenever the input files or intent.toml change
+        #[arg(long)]
+        watch: bool,
+
+        /// Suppress warnings with the given code (repeatable)
+        #[arg(long = "allow", value_name = "CODE")]
+        allow: Vec<String>,
+
+        /// Diagnostic output format
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+
+    /// Rewrite intent files in canonical style, keeping comments
+    Fmt {
+        /// Input .intent file path (default: [project] inputs in intent.toml)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Write nothing; list the files that need formatting and fail if there are any
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Start a language server on stdin/stdout for editor integration
+    Lsp,
+
+    /// Run the configurable lints over an intent file
+    Lint {
+        /// Input .intent file path
+        #[arg(short, long, required_unless_present = "list")]
+        input: Option<PathBuf>,
+
+        /// List available lints and their default levels
+        #[arg(long)]
+        list: bool,
+
+        /// Suppress warnings with the given code (repeatable)
+        #[arg(long = "allow", value_name = "CODE")]
+        allow: Vec<String>,
+
+        /// Diagnostic output format
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+
+    /// Audit an intent file for security risks
+    Audit {
         /// Input .intent file path
         #[arg(short, long)]
         input: PathBuf,
+
+        /// Exit with an error if any finding is at least this severe
+        #[arg(long, value_enum, default_value_t = Severity::High)]
+        fail_on: Severity,
+
+        /// Suppress findings with the given code (repeatable)
+        #[arg(long = "allow", value_name = "CODE")]
+        allow: Vec<String>,
+
+        /// Diagnostic output format
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+
+    /// Export an OpenAPI 3.1 description of the API
+    Openapi {
+        /// Input .intent file path (default: [project] inputs in intent.toml)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// File to write (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Document format (default: json for a .json output, else yaml)
+        #[arg(long, value_enum)]
+        format: Option<DocumentFormat>,
+    },
+
+    /// Export JSON Schemas of entities, action inputs and output projections
+    Schema {
+        /// Input .intent file path (default: [project] inputs in intent.toml)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// File to write (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Print the parsed and preprocessed AST
+    Ast {
+        /// Input .intent file path (default: [project] inputs in intent.toml)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// File to write (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Document format
+        #[arg(long, value_enum, default_value_t = AstFormat::Json)]
+        format: AstFormat,
+    },
+
+    /// Report API changes between two versions of an intent file, failing on breaking ones
+    Compat {
+        /// The intent file clients were built against
+        old: PathBuf,
+
+        /// The changed intent file
+        new: PathBuf,
+    },
+
+    /// Explain a diagnostic code, e.g. `intentc explain E0203`
+    Explain {
+        /// Diagnostic code
+        code: String,
     },
 
     /// Initialize a new intent project
@@ -61,6 +265,30 @@ pub enum Commands {
     },
 }
 
+/// How errors and warnings are printed
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum MessageFormat {
+    /// Colored text on stderr
+    Human,
+    /// A single JSON document on stdout
+    Json,
+    /// A SARIF 2.1.0 log on stdout
+    Sarif,
+}
+
+/// Serialization of exported documents
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DocumentFormat {
+    Yaml,
+    Json,
+}
+
+/// Serialization of `intentc ast`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AstFormat {
+    Json,
+}
+
 impl Cli {
     /// Parse command line arguments
     pub fn parse_args() -> Self {
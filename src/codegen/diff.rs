@@ -0,0 +1,89 @@
+// Intent Compiler - Output Diff
+// Unified diff between a generated file tree and the current output directory
+
+use std::fs;
+use std::path::Path;
+
+use similar::{ChangeTag, TextDiff};
+
+use crate::codegen::GenerationResult;
+use crate::codegen::output::{self, Change};
+use crate::error::CompileResult;
+
+/// Lines of context around each hunk
+const CONTEXT_LINES: usize = 3;
+
+/// Differences of a single file
+#[derive(Debug)]
+pub struct FileDiff {
+    pub path: String,
+    pub change: Change,
+    /// Lines only in the generated file
+    pub added: usize,
+    /// Lines only in the file on disk
+    pub removed: usize,
+    /// Unified diff with `a/` and `b/` headers
+    pub patch: String,
+}
+
+/// Diff every file that committing `result` would create, update or delete
+pub fn diff_output(output_dir: &Path, result: &GenerationResult, orphans: &[&str]) -> CompileResult<Vec<FileDiff>> {
+    let mut diffs = Vec::new();
+    for (change, path) in output::plan(output_dir, result, orphans)? {
+        let old = match change {
+            Change::Create => String::new(),
+            Change::Update | Change::Delete => String::from_utf8_lossy(&fs::read(output_dir.join(path))?).into_owned(),
+        };
+        let new = result.files.get(path).map(String::as_str).unwrap_or_default();
+        diffs.push(file_diff(path, change, &old, new));
+    }
+    Ok(diffs)
+}
+
+/// Diff `old` against `new`, naming a missing side /dev/null as git does
+fn file_diff(path: &str, change: Change, old: &str, new: &str) -> FileDiff {
+    let diff = TextDiff::from_lines(old, new);
+    let (mut added, mut removed) = (0, 0);
+    for op in diff.iter_all_changes() {
+        match op.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    let old_header = if change == Change::Create { "/dev/null".to_string() } else { format!("a/{}", path) };
+    let new_header = if change == Change::Delete { "/dev/null".to_string() } else { format!("b/{}", path) };
+    let patch = diff
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&old_header, &new_header)
+        .to_string();
+    FileDiff { path: path.to_string(), change, added, removed, patch }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_counts_lines_and_names_sides() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.py"), "a = 1\nb = 2\n").unwrap();
+        fs::write(dir.path().join("old.py"), "x = 1\n").unwrap();
+        let mut result = GenerationResult::new();
+        result.add_file("main.py", "a = 1\nb = 3\nc = 4\n".to_string());
+        result.add_file("new.py", "y = 1\n".to_string());
+
+        let diffs = diff_output(dir.path(), &result, &["old.py"]).unwrap();
+        let summary: Vec<_> = diffs.iter().map(|d| (d.path.as_str(), d.change, d.added, d.removed)).collect();
+        assert_eq!(summary, vec![
+            ("main.py", Change::Update, 2, 1),
+            ("new.py", Change::Create, 1, 0),
+            ("old.py", Change::Delete, 0, 1),
+        ]);
+        assert!(diffs[0].patch.starts_with("--- a/main.py\n+++ b/main.py\n@@"));
+        assert!(diffs[0].patch.contains("-b = 2\n+b = 3\n+c = 4\n"));
+        assert!(diffs[1].patch.starts_with("--- /dev/null\n+++ b/new.py\n"));
+        assert!(diffs[2].patch.starts_with("--- a/old.py\n+++ /dev/null\n"));
+    }
+}
@@ -0,0 +1,228 @@
+// Intent Compiler - JSON Schema Export
+// Maps field types to JSON Schema (draft 2020-12) and bundles entity and payload schemas
+
+use serde_json::{Map, Value, json};
+
+use crate::ast::{Decorator, FieldType, OutputSection, ValidationConstraints};
+use crate::codegen::python::models::to_pascal_case;
+use crate::ir::{ActionIr, Cardinality, Program, VarType};
+
+/// Meta-schema of the exported bundle
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Schema of a reference to the named entity
+pub type References<'a> = &'a dyn Fn(&str) -> Value;
+
+/// One document with a `$defs` entry per entity, action input (`<Action>Input`)
+/// and output projection (`<Action>Output`)
+pub fn bundle(program: &Program, title: &str) -> Value {
+    let references = |entity: &str| primary_key(program, entity);
+    let mut defs = Map::new();
+    for entity in &program.entities {
+        let properties = entity
+            .fields
+            .iter()
+            .map(|f| property(&f.name, &f.field_type, &f.decorators, &references))
+            .collect();
+        defs.insert(entity.name.clone(), object(properties));
+    }
+    for action in &program.actions {
+        let name = to_pascal_case(&action.name);
+        if let Some(input) = action.def.input.as_ref().filter(|i| !i.fields.is_empty()) {
+            let properties = input
+                .fields
+                .iter()
+                .map(|p| property(&p.name, &p.param_type, &p.decorators, &references))
+                .collect();
+            defs.insert(format!("{}Input", name), object(properties));
+        }
+        if let Some(output) = action.def.output.as_ref().filter(|o| !o.fields.is_empty()) {
+            defs.insert(format!("{}Output", name), projection(action, output, program, &references));
+        }
+    }
+    json!({ "$schema": DIALECT, "title": title, "$defs": defs })
+}
+
+/// A reference holds the primary key of the referenced entity
+fn primary_key(program: &Program, entity: &str) -> Value {
+    let key = program
+        .find_entity(entity)
+        .and_then(|e| e.fields.iter().find(|f| f.decorators.contains(&Decorator::Primary)));
+    match key {
+        Some(key) => json!({ "$ref": format!("#/$defs/{}/properties/{}", entity, key.name) }),
+        None => json!({ "type": "string" }),
+    }
+}
+
+/// Output fields point at the entity's own property schemas
+fn projection(action: &ActionIr, output: &OutputSection, program: &Program, references: References) -> Value {
+    let entity = program.find_entity(&output.entity);
+    let properties = output
+        .fields
+        .iter()
+        .map(|name| {
+            let schema = match entity.filter(|e| e.fields.iter().any(|f| &f.name == name)) {
+                Some(entity) => json!({ "$ref": format!("#/$defs/{}/properties/{}", entity.name, name) }),
+                None => match action.variable(name).map(|v| &v.ty) {
+                    Some(VarType::Value(ty)) => type_schema(ty, references),
+                    // A custom computation: any value
+                    _ => json!({}),
+                },
+            };
+            (name.clone(), schema, true)
+        })
+        .collect();
+    let record = object(properties);
+    match action.cardinality {
+        Cardinality::One => record,
+        Cardinality::Many => json!({ "type": "array", "items": record }),
+    }
+}
+
+/// An object schema from (name, schema, required) properties, in order
+pub fn object(properties: Vec<(String, Value, bool)>) -> Value {
+    let required: Vec<_> = properties.iter().filter(|p| p.2).map(|p| p.0.clone()).collect();
+    let properties: Map<_, _> = properties.into_iter().map(|(name, schema, _)| (name, schema)).collect();
+    let mut object = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        object["required"] = json!(required);
+    }
+    object
+}
+
+/// Schema of a field or input with its decorators applied, and whether it is required
+pub fn property(name: &str, ty: &FieldType, decorators: &[Decorator], references: References) -> (String, Value, bool) {
+    let mut schema = type_schema(ty, references);
+    let optional = matches!(ty, FieldType::Optional(_)) || decorators.contains(&Decorator::Optional);
+    if optional && !matches!(ty, FieldType::Optional(_)) {
+        schema = nullable(schema);
+    }
+    for decorator in decorators {
+        if let Decorator::Validate(constraints) = decorator {
+            constrain(&mut schema, ty, constraints);
+        }
+    }
+    (name.to_string(), schema, !optional)
+}
+
+/// JSON Schema of a field type
+pub fn type_schema(ty: &FieldType, references: References) -> Value {
+    match ty {
+        FieldType::String => json!({ "type": "string" }),
+        FieldType::Number => json!({ "type": "number" }),
+        FieldType::Boolean => json!({ "type": "boolean" }),
+        FieldType::DateTime => json!({ "type": "string", "format": "date-time" }),
+        FieldType::Uuid => json!({ "type": "string", "format": "uuid" }),
+        FieldType::Email => json!({ "type": "string", "format": "email" }),
+        FieldType::Enum(values) => json!({ "type": "string", "enum": values }),
+        FieldType::Reference(entity) | FieldType::Ref(entity) => references(entity),
+        FieldType::Array(inner) | FieldType::List(inner) => {
+            json!({ "type": "array", "items": type_schema(inner, references) })
+        }
+        FieldType::Optional(inner) => nullable(type_schema(inner, references)),
+    }
+}
+
+/// Also allow null
+fn nullable(mut schema: Value) -> Value {
+    let Some(ty) = schema.get("type").cloned() else {
+        return json!({ "anyOf": [schema, { "type": "null" }] });
+    };
+    schema["type"] = json!([ty, "null"]);
+    if let Some(Value::Array(values)) = schema.get_mut("enum") {
+        values.push(Value::Null);
+    }
+    schema
+}
+
+/// Apply `@validate` bounds: lengths for strings, item counts for lists, values for numbers
+fn constrain(schema: &mut Value, ty: &FieldType, constraints: &ValidationConstraints) {
+    let base = match ty {
+        FieldType::Optional(inner) => inner,
+        other => other,
+    };
+    let (min, max) = match base {
+        FieldType::Number => ("minimum", "maximum"),
+        FieldType::Array(_) | FieldType::List(_) => ("minItems", "maxItems"),
+        _ => ("minLength", "maxLength"),
+    };
+    // Counts must be integers; numeric bounds keep their fraction
+    let bound = |value: f64| if matches!(base, FieldType::Number) { json!(value) } else { json!(value.max(0.0) as u64) };
+    if let Some(value) = constraints.min {
+        schema[min] = bound(value);
+    }
+    if let Some(value) = constraints.max {
+        schema[max] = bound(value);
+    }
+    if let Some(pattern) = &constraints.pattern {
+        schema["pattern"] = json!(pattern);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+
+    const BLOG: &str = r#"entity User:
+    id: uuid @primary
+    name: string @validate(min: 2, pattern: "^[a-z]+$")
+
+entity Post:
+    id: uuid @primary
+    author: ref<User>
+    reviewer: ref<User> @optional
+    tags: list<string> @validate(max: 5)
+    state: draft | published
+
+@api POST /posts
+action create_post:
+    input:
+        author: ref<User>
+        note: string?
+    output: Post(id, state)
+
+@api GET /posts
+action list_posts:
+    output: Post(id, author)
+"#;
+
+    fn blog() -> Value {
+        bundle(&Program::lower(&parse_intent(BLOG).unwrap()), "Blog")
+    }
+
+    #[test]
+    fn test_entities_reference_each_other() {
+        let bundle = blog();
+        assert_eq!(bundle["$schema"], DIALECT);
+        let defs: Vec<_> = bundle["$defs"].as_object().unwrap().keys().cloned().collect();
+        assert_eq!(defs, vec!["User", "Post", "CreatePostInput", "CreatePostOutput", "ListPostsOutput"]);
+
+        let post = &bundle["$defs"]["Post"];
+        assert_eq!(post["required"], json!(["id", "author", "tags", "state"]));
+        assert_eq!(post["properties"]["author"], json!({ "$ref": "#/$defs/User/properties/id" }));
+        assert_eq!(
+            post["properties"]["reviewer"],
+            json!({ "anyOf": [{ "$ref": "#/$defs/User/properties/id" }, { "type": "null" }] })
+        );
+        assert_eq!(post["properties"]["tags"], json!({ "type": "array", "items": { "type": "string" }, "maxItems": 5 }));
+        assert_eq!(
+            bundle["$defs"]["User"]["properties"]["name"],
+            json!({ "type": "string", "minLength": 2, "pattern": "^[a-z]+$" })
+        );
+    }
+
+    #[test]
+    fn test_payloads() {
+        let bundle = blog();
+        let input = &bundle["$defs"]["CreatePostInput"];
+        assert_eq!(input["required"], json!(["author"]));
+        assert_eq!(input["properties"]["note"]["type"], json!(["string", "null"]));
+
+        let output = &bundle["$defs"]["CreatePostOutput"];
+        assert_eq!(output["properties"]["state"], json!({ "$ref": "#/$defs/Post/properties/state" }));
+        let list = &bundle["$defs"]["ListPostsOutput"];
+        assert_eq!(list["type"], "array");
+        assert_eq!(list["items"]["required"], json!(["id", "author"]));
+    }
+}
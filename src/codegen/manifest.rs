@@ -0,0 +1,192 @@
+// Intent Compiler - Output Manifest
+// Content hashes of generated files, used to prune orphans and detect hand edits
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::codegen::{GenerationResult, VERSION};
+use crate::error::{CompileError, CompileResult};
+
+/// Manifest file written to the root of the output directory
+pub const MANIFEST_FILE: &str = ".intentc-manifest.json";
+
+/// Generated files of the last compilation and the SHA-256 of their content
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Compiler version that wrote the manifest
+    pub version: String,
+    /// Output-relative path → hex digest
+    pub files: BTreeMap<String, String>,
+    /// User-owned files of the last compilation, which it created at most once
+    #[serde(default)]
+    pub user_files: BTreeSet<String>,
+}
+
+impl Manifest {
+    /// Load the manifest of `output_dir`, or an empty one if nothing was generated there yet
+    pub fn load(output_dir: &Path) -> CompileResult<Self> {
+        let path = output_dir.join(MANIFEST_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&content)
+            .map_err(|e| CompileError::codegen(format!("invalid {}: {}", path.display(), e)))
+    }
+
+    /// Hash the generated files of `result`
+    pub fn record(result: &GenerationResult) -> Self {
+        let files = result
+            .files
+            .iter()
+            .filter(|(path, _)| !result.user_files.contains(*path))
+            .map(|(path, content)| (path.clone(), hash(content.as_bytes())))
+            .collect();
+        let user_files = result.user_files.iter().cloned().collect();
+        Self { version: VERSION.to_string(), files, user_files }
+    }
+
+    /// Serialize the manifest as written to the output directory
+    pub fn to_json(&self) -> CompileResult<String> {
+        serde_json::to_string_pretty(self)
+            .map(|json| json + "\n")
+            .map_err(|e| CompileError::codegen(format!("failed to serialize {}: {}", MANIFEST_FILE, e)))
+    }
+
+    /// Tracked files whose content no longer matches the recorded hash; deleted files don't count
+    pub fn edited_files(&self, output_dir: &Path) -> CompileResult<Vec<&str>> {
+        let mut edited = Vec::new();
+        for (path, digest) in &self.files {
+            match fs::read(output_dir.join(path)) {
+                Ok(content) if hash(&content) != *digest => edited.push(path.as_str()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(edited)
+    }
+
+    /// Existing files that `result` would replace with different content although the last
+    /// compilation didn't generate them: files that were user-owned and files it doesn't track
+    pub fn unowned_files<'a>(&self, output_dir: &Path, result: &'a GenerationResult) -> CompileResult<Vec<&'a str>> {
+        let mut unowned = Vec::new();
+        for (path, content) in &result.files {
+            if self.files.contains_key(path) && !self.user_files.contains(path) {
+                continue;
+            }
+            match fs::read(output_dir.join(path)) {
+                Ok(existing) if existing != content.as_bytes() => unowned.push(path.as_str()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(unowned)
+    }
+
+    /// Tracked files that `current` no longer generates, except those now owned by the user
+    pub fn orphans<'a>(&'a self, current: &Manifest, result: &GenerationResult) -> Vec<&'a str> {
+        self.files
+            .keys()
+            .filter(|path| !current.files.contains_key(*path) && !result.user_files.contains(*path))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Hex-encoded SHA-256 of `content`
+fn hash(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(output_dir: &Path, files: &[(&str, &str)]) -> GenerationResult {
+        let mut result = GenerationResult::new();
+        for (path, content) in files {
+            let full_path = output_dir.join(path);
+            fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            fs::write(full_path, content).unwrap();
+            result.add_file(*path, content.to_string());
+        }
+        result
+    }
+
+    #[test]
+    fn test_manifest_round_trips_and_detects_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = generated(dir.path(), &[("main.py", "app = 1\n"), ("models/user.py", "class User: ...\n")]);
+        let json = Manifest::record(&result).to_json().unwrap();
+        fs::write(dir.path().join(MANIFEST_FILE), json).unwrap();
+
+        let manifest = Manifest::load(dir.path()).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert!(manifest.edited_files(dir.path()).unwrap().is_empty());
+
+        fs::write(dir.path().join("main.py"), "app = 2\n").unwrap();
+        fs::remove_file(dir.path().join("models/user.py")).unwrap();
+        assert_eq!(manifest.edited_files(dir.path()).unwrap(), vec!["main.py"]);
+    }
+
+    #[test]
+    fn test_orphans_exclude_user_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = generated(dir.path(), &[("main.py", ""), ("models/user.py", ""), ("services/note_service.py", "")]);
+        let mut second = generated(dir.path(), &[("main.py", "")]);
+        second.add_user_file("services/note_service.py", None);
+
+        let previous = Manifest::record(&first);
+        assert_eq!(previous.orphans(&Manifest::record(&second), &second), vec!["models/user.py"]);
+    }
+
+    #[test]
+    fn test_turning_generation_gap_off_keeps_user_code() {
+        use crate::codegen::python::PythonGenerator;
+        use crate::codegen::{CodeGenerator, GeneratorOptions, output};
+        use crate::ir::Program;
+        use crate::parser::parse_intent;
+
+        let dir = tempfile::tempdir().unwrap();
+        let program = Program::lower(&parse_intent("entity Product:\n    id: uuid @primary\n").unwrap());
+        let compile = |generation_gap| {
+            PythonGenerator::new(GeneratorOptions { generation_gap, ..Default::default() }).generate(&program, dir.path()).unwrap()
+        };
+        let first = compile(true);
+        output::commit(dir.path(), &first, &[], &Manifest::record(&first)).unwrap();
+        let manifest = Manifest::load(dir.path()).unwrap();
+        assert!(manifest.user_files.contains("services/product_service.py"));
+        fs::write(dir.path().join("services/product_service.py"), "# custom code\n").unwrap();
+
+        let second = compile(false);
+        assert!(manifest.edited_files(dir.path()).unwrap().is_empty());
+        assert!(manifest.unowned_files(dir.path(), &second).unwrap().contains(&"services/product_service.py"));
+        // Generated files replace each other freely
+        assert!(!manifest.unowned_files(dir.path(), &second).unwrap().contains(&"main.py"));
+    }
+
+    #[test]
+    fn test_untracked_files_are_unowned() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.py"), "# mine\n").unwrap();
+        fs::write(dir.path().join("same.py"), "x = 1\n").unwrap();
+        let mut result = GenerationResult::new();
+        result.add_file("main.py", "app = 1\n".to_string());
+        result.add_file("same.py", "x = 1\n".to_string());
+        assert_eq!(Manifest::default().unowned_files(dir.path(), &result).unwrap(), vec!["main.py"]);
+    }
+
+    #[test]
+    fn test_missing_manifest_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Manifest::load(dir.path()).unwrap().files.is_empty());
+    }
+}
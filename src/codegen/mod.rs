@@ -1,13 +1,24 @@
 // Intent Compiler - Code Generator Module
 // Trait-based architecture for multi-language code generation
 
+pub mod diff;
+pub mod jsonschema;
+pub mod manifest;
+pub mod openapi;
+pub mod output;
 pub mod python;
+pub mod templates;
 
 pub const VERSION: &str = "0.2.0";
 
-use std::path::Path;
+/// API title when intent.toml names no package
+pub const DEFAULT_TITLE: &str = "Intent Compiler Generated API";
 
-use crate::ast::IntentFile;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::Dialect;
+use crate::ir::Program;
 use crate::error::CompileResult;
 
 /// Target language for code generation
@@ -36,11 +47,46 @@ impl std::fmt::Display for TargetLanguage {
     }
 }
 
+/// Project settings that shape the generated code
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+    /// Package name from intent.toml, used as the API title
+    pub package_name: Option<String>,
+    pub dialect: Dialect,
+    /// Default connection URL written to the generated settings
+    pub database_url: String,
+    /// Whether to generate tests
+    pub tests: bool,
+    /// Whether to generate migrations
+    pub migrations: bool,
+    /// Generate `*Base` services and controllers with user-owned subclasses
+    pub generation_gap: bool,
+    /// Project directory whose `<file>.tera` templates override the built-in ones
+    pub template_dir: Option<PathBuf>,
+    /// Generate migrations that drop tables or columns or narrow column types
+    pub allow_destructive: bool,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            package_name: None,
+            dialect: Dialect::Sqlite,
+            database_url: "sqlite:///./app.db".to_string(),
+            tests: true,
+            migrations: true,
+            generation_gap: false,
+            template_dir: None,
+            allow_destructive: false,
+        }
+    }
+}
+
 /// Code generator trait - implement for each target language
 #[allow(dead_code)]
 pub trait CodeGenerator {
-    /// Generate code from an intent file
-    fn generate(&self, ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult>;
+    /// Generate the file tree for a program; `output_dir` is only read, e.g. to keep user-owned files
+    fn generate(&self, program: &Program, output_dir: &Path) -> CompileResult<GenerationResult>;
 
     /// Get the target language
     fn language(&self) -> TargetLanguage;
@@ -49,15 +95,15 @@ pub trait CodeGenerator {
     fn file_extension(&self) -> &str;
 }
 
-/// Result of code generation
+/// Result of code generation: the generated file tree, held in memory until committed
 #[derive(Debug, Default)]
 pub struct GenerationResult {
-    /// Files that were generated
-    pub files_created: Vec<String>,
-    /// Total lines of code generated
-    pub lines_generated: usize,
+    /// Generated file contents by output-relative path
+    pub files: BTreeMap<String, String>,
     /// Any warnings during generation
     pub warnings: Vec<String>,
+    /// User-owned files, created once and never tracked in the output manifest
+    pub user_files: Vec<String>,
 }
 
 impl GenerationResult {
@@ -65,22 +111,34 @@ impl GenerationResult {
         Self::default()
     }
 
-    pub fn add_file(&mut self, path: impl Into<String>, lines: usize) {
-        self.files_created.push(path.into());
-        self.lines_generated += lines;
+    pub fn add_file(&mut self, path: impl Into<String>, content: String) {
+        self.files.insert(path.into(), content);
+    }
+
+    /// Record a user-owned file, with its content when this run creates it
+    pub fn add_user_file(&mut self, path: impl Into<String>, content: Option<String>) {
+        let path = path.into();
+        if let Some(content) = content {
+            self.add_file(path.clone(), content);
+        }
+        self.user_files.push(path);
     }
 
+    /// Total lines of code generated
+    pub fn lines_generated(&self) -> usize {
+        self.files.values().map(|content| content.lines().count()).sum()
+    }
 
     pub fn merge(&mut self, other: GenerationResult) {
-        self.files_created.extend(other.files_created);
-        self.lines_generated += other.lines_generated;
+        self.files.extend(other.files);
         self.warnings.extend(other.warnings);
+        self.user_files.extend(other.user_files);
     }
 }
 
 /// Create a code generator for the given target language
-pub fn create_generator(target: TargetLanguage) -> Box<dyn CodeGenerator> {
+pub fn create_generator(target: TargetLanguage, options: GeneratorOptions) -> Box<dyn CodeGenerator> {
     match target {
-        TargetLanguage::Python => Box::new(python::PythonGenerator::new()),
+        TargetLanguage::Python => Box::new(python::PythonGenerator::new(options)),
     }
 }
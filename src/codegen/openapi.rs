@@ -0,0 +1,231 @@
+// Intent Compiler - OpenAPI Export
+// Builds an OpenAPI 3.1 description of the generated API without running it
+
+use serde_json::{Map, Value, json};
+
+use crate::ast::{Decorator, Entity};
+use crate::codegen::VERSION;
+use crate::codegen::jsonschema::{object, property, type_schema};
+use crate::codegen::python::controllers::{is_form_login, served_path};
+use crate::codegen::python::models::to_pascal_case;
+use crate::ir::{ActionIr, Cardinality, Program, VarType};
+
+/// OpenAPI version of the exported document
+const OPENAPI_VERSION: &str = "3.1.0";
+
+/// Security scheme required by `@auth` actions
+const SECURITY_SCHEME: &str = "bearerAuth";
+
+/// Describe every `@api` action of `program`, at the paths the generated routers serve
+pub fn document(program: &Program, title: &str) -> Value {
+    let mut paths = Map::new();
+    let mut schemas = Map::new();
+    for action in &program.actions {
+        // Actions without a route or owner get no endpoint of their own
+        let (Some(route), Some(entity)) = (&action.route, &action.entity) else { continue };
+        let path = served_path(&route.path, entity);
+        let operation = operation(action, entity, program, &mut schemas);
+        let item = paths.entry(path).or_insert_with(|| json!({}));
+        item[route.method.to_string().to_lowercase()] = operation;
+    }
+
+    let mut components = json!({ "schemas": schemas });
+    if program.actions.iter().any(|a| a.route.is_some() && a.requires_auth) {
+        components["securitySchemes"] = json!({
+            SECURITY_SCHEME: { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" }
+        });
+    }
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": title,
+            "description": "API generated from Intent Definition Language",
+            "version": VERSION,
+        },
+        "paths": paths,
+        "components": components,
+    })
+}
+
+fn operation(action: &ActionIr, entity: &str, program: &Program, schemas: &mut Map<String, Value>) -> Value {
+    let mut operation = json!({ "operationId": action.name, "tags": [entity] });
+
+    let parameters: Vec<_> = action
+        .path_params()
+        .iter()
+        .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+        .collect();
+    if !parameters.is_empty() {
+        operation["parameters"] = json!(parameters);
+    }
+
+    if action.route.as_ref().is_some_and(|r| r.has_body()) {
+        operation["requestBody"] = request_body(action, entity, program, schemas);
+    }
+
+    let mut responses = json!({
+        "200": {
+            "description": "Successful Response",
+            "content": { "application/json": { "schema": response_schema(action, entity, program, schemas) } },
+        }
+    });
+    if action.requires_auth {
+        responses["401"] = json!({ "description": "Not authenticated" });
+        operation["security"] = json!([{ SECURITY_SCHEME: [] }]);
+    }
+    if !action.policies.is_empty() {
+        responses["403"] = json!({ "description": "Forbidden by policy" });
+    }
+    operation["responses"] = responses;
+    operation
+}
+
+/// Body of POST, PUT and PATCH routes: the action's input, else the entity's create model
+fn request_body(action: &ActionIr, entity: &str, program: &Program, schemas: &mut Map<String, Value>) -> Value {
+    if is_form_login(action) {
+        let form = object(vec![
+            ("username".to_string(), json!({ "type": "string" }), true),
+            ("password".to_string(), json!({ "type": "string" }), true),
+        ]);
+        return json!({ "required": true, "content": { "application/x-www-form-urlencoded": { "schema": form } } });
+    }
+
+    let params = action.def.input.iter().flat_map(|i| &i.fields);
+    let (name, schema) = if params.clone().next().is_some() {
+        let properties = params.map(|p| property(&p.name, &p.param_type, &p.decorators, &id_string)).collect();
+        (format!("{}Request", to_pascal_case(&action.name)), object(properties))
+    } else {
+        let properties = program.find_entity(entity).map(create_properties).unwrap_or_default();
+        (format!("{}Create", entity), object(properties))
+    };
+    schemas.insert(name.clone(), schema);
+    json!({ "required": true, "content": { "application/json": { "schema": reference(&name) } } })
+}
+
+/// Fields accepted when creating an entity: all but the primary key, required unless defaulted
+fn create_properties(entity: &Entity) -> Vec<(String, Value, bool)> {
+    entity
+        .fields
+        .iter()
+        .filter(|f| !f.decorators.contains(&Decorator::Primary))
+        .map(|f| {
+            let (name, schema, required) = property(&f.name, &f.field_type, &f.decorators, &id_string);
+            let defaulted = f.decorators.iter().any(|d| matches!(d, Decorator::Default(_)));
+            (name, schema, required && !defaulted)
+        })
+        .collect()
+}
+
+/// The output projection, an array of it for list routes, or a free-form object
+fn response_schema(action: &ActionIr, entity: &str, program: &Program, schemas: &mut Map<String, Value>) -> Value {
+    let Some(output) = action.def.output.as_ref().filter(|o| !o.fields.is_empty()) else {
+        return json!({ "type": "object" });
+    };
+    let source = program.find_entity(&output.entity);
+    let mut properties: Vec<_> = output
+        .fields
+        .iter()
+        .map(|name| {
+            let schema = match source.and_then(|e| e.fields.iter().find(|f| &f.name == name)) {
+                Some(field) => property(name, &field.field_type, &field.decorators, &id_string).1,
+                None => match action.variable(name).map(|v| &v.ty) {
+                    Some(VarType::Value(ty)) => type_schema(ty, &id_string),
+                    _ => json!({ "type": "string" }),
+                },
+            };
+            (name.clone(), schema, true)
+        })
+        .collect();
+    if action.name == "login" {
+        properties.push(("access_token".to_string(), json!({ "type": "string" }), true));
+        properties.push(("token_type".to_string(), json!({ "type": "string", "default": "bearer" }), false));
+    }
+
+    let name = format!("{}{}Response", entity, to_pascal_case(&action.name));
+    schemas.insert(name.clone(), object(properties));
+    match action.cardinality {
+        Cardinality::One => reference(&name),
+        Cardinality::Many => json!({ "type": "array", "items": reference(&name) }),
+    }
+}
+
+fn reference(schema: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", schema) })
+}
+
+/// References travel as the referenced record's ID
+fn id_string(_entity: &str) -> Value {
+    json!({ "type": "string" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+    use crate::preprocessor::inject_auth_actions;
+
+    fn spec(source: &str) -> Value {
+        let mut file = parse_intent(source).unwrap();
+        inject_auth_actions(&mut file);
+        document(&Program::lower(&file), "Shop")
+    }
+
+    const SHOP: &str = r#"auth entity User:
+    id: uuid @primary
+    email: email @unique
+    password_hash: string
+
+entity Product:
+    id: uuid @primary
+    name: string @validate(min: 1, max: 80)
+    price: number @validate(min: 0)
+    status: draft | live
+    description: string @optional
+
+@api GET /products
+action list_products:
+    output: Product(id, name, price)
+
+@api POST /products
+@auth
+action create_product:
+    input:
+        name: string
+        price: number
+        note: string?
+    output: Product(id, status, description)
+"#;
+
+    #[test]
+    fn test_routes_are_served_under_the_entity_router() {
+        let spec = spec(SHOP);
+        assert_eq!(spec["openapi"], "3.1.0");
+        let paths: Vec<_> = spec["paths"].as_object().unwrap().keys().cloned().collect();
+        assert_eq!(&paths[..4], ["/products/", "/users/signup", "/users/login", "/users/me"]);
+
+        let list = &spec["paths"]["/products/"]["get"];
+        assert_eq!(list["responses"]["200"]["content"]["application/json"]["schema"]["type"], "array");
+        let login = &spec["paths"]["/users/login"]["post"]["requestBody"]["content"];
+        assert!(login.get("application/x-www-form-urlencoded").is_some());
+    }
+
+    #[test]
+    fn test_inputs_outputs_and_security() {
+        let spec = spec(SHOP);
+        let create = &spec["paths"]["/products/"]["post"];
+        assert_eq!(create["security"], json!([{ "bearerAuth": [] }]));
+        assert_eq!(spec["components"]["securitySchemes"]["bearerAuth"]["scheme"], "bearer");
+
+        let request = &spec["components"]["schemas"]["CreateProductRequest"];
+        assert_eq!(request["required"], json!(["name", "price"]));
+        assert_eq!(request["properties"]["note"]["type"], json!(["string", "null"]));
+
+        let response = &spec["components"]["schemas"]["ProductCreateProductResponse"];
+        assert_eq!(response["properties"]["status"], json!({ "type": "string", "enum": ["draft", "live"] }));
+        assert_eq!(response["properties"]["description"]["type"], json!(["string", "null"]));
+
+        let list = &spec["components"]["schemas"]["ProductListProductsResponse"]["properties"];
+        assert_eq!(list["name"], json!({ "type": "string", "minLength": 1, "maxLength": 80 }));
+        assert_eq!(list["price"], json!({ "type": "number", "minimum": 0.0 }));
+    }
+}
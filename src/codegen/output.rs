@@ -0,0 +1,312 @@
+// Intent Compiler - Output Writer
+// Commits a generated file tree to the output directory, or previews the changes
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::codegen::GenerationResult;
+use crate::codegen::manifest::{MANIFEST_FILE, Manifest};
+use crate::error::CompileResult;
+
+/// How committing a generation result changes a file of the output directory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Create,
+    Update,
+    Delete,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Create => write!(f, "create"),
+            Change::Update => write!(f, "update"),
+            Change::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+/// Files that committing `result` would create, update or delete; unchanged files are left out
+pub fn plan<'a>(output_dir: &Path, result: &'a GenerationResult, orphans: &[&'a str]) -> CompileResult<Vec<(Change, &'a str)>> {
+    let mut changes = Vec::new();
+    for (path, content) in &result.files {
+        match fs::read(output_dir.join(path)) {
+            Ok(existing) if existing == content.as_bytes() => {}
+            Ok(_) => changes.push((Change::Update, path.as_str())),
+            Err(e) if e.kind() == ErrorKind::NotFound => changes.push((Change::Create, path.as_str())),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    for orphan in orphans {
+        if output_dir.join(orphan).exists() {
+            changes.push((Change::Delete, *orphan));
+        }
+    }
+    Ok(changes)
+}
+
+/// Write `result` and its manifest to `output_dir` and delete `orphans`.
+///
+/// Changed files are first written to a staging directory next to the output and then
+/// moved into place, setting aside the files they replace. If a move fails, the files
+/// already moved are put back, so the output is either fully updated or left as it was.
+/// Unchanged files are not rewritten, so file watchers such as `uvicorn --reload` don't fire.
+pub fn commit(output_dir: &Path, result: &GenerationResult, orphans: &[&str], manifest: &Manifest) -> CompileResult<()> {
+    commit_with(output_dir, result, orphans, manifest, |from, to| fs::rename(from, to))
+}
+
+/// `commit` with the rename that moves files in and out of the output, so tests can make it fail
+fn commit_with(
+    output_dir: &Path,
+    result: &GenerationResult,
+    orphans: &[&str],
+    manifest: &Manifest,
+    mut rename: impl FnMut(&Path, &Path) -> io::Result<()>,
+) -> CompileResult<()> {
+    fs::create_dir_all(output_dir)?;
+    let changes = plan(output_dir, result, orphans)?;
+    let manifest = manifest.to_json()?;
+    let manifest_changed = fs::read(output_dir.join(MANIFEST_FILE)).ok().as_deref() != Some(manifest.as_bytes());
+    if changes.is_empty() && !manifest_changed {
+        return Ok(());
+    }
+
+    // Stage everything before touching the output; the directory is removed on drop
+    let parent = output_dir.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let staging = tempfile::Builder::new().prefix(".intentc-staging").tempdir_in(parent)?;
+    let staged_dir = staging.path().join("new");
+    let mut staged = Vec::new();
+    for (change, path) in &changes {
+        if *change == Change::Delete {
+            continue;
+        }
+        let target = staged_dir.join(path);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&target, &result.files[*path])?;
+        staged.push(*path);
+    }
+    if manifest_changed {
+        fs::create_dir_all(&staged_dir)?;
+        fs::write(staged_dir.join(MANIFEST_FILE), manifest)?;
+        staged.push(MANIFEST_FILE);
+    }
+
+    let mut journal = Journal::default();
+    let swapped = swap_in(output_dir, staging.path(), &staged, orphans, &mut rename, &mut journal);
+    if let Err(e) = swapped {
+        journal.roll_back();
+        return Err(e.into());
+    }
+    for orphan in orphans {
+        remove_empty_parents(output_dir, &output_dir.join(orphan));
+    }
+    Ok(())
+}
+
+/// Changes made to the output by `swap_in`, in order, so they can be undone
+#[derive(Default)]
+struct Journal {
+    /// Output files and where the file they replaced was set aside, if there was one
+    files: Vec<(PathBuf, Option<PathBuf>)>,
+    /// Directories created in the output
+    dirs: Vec<PathBuf>,
+}
+
+impl Journal {
+    /// Put back the files set aside and remove what was added, as far as possible
+    fn roll_back(self) {
+        for (target, previous) in self.files.into_iter().rev() {
+            let _ = match previous {
+                Some(previous) => fs::rename(previous, &target),
+                None => fs::remove_file(&target),
+            };
+        }
+        for dir in self.dirs.into_iter().rev() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+/// Move the staged files into the output and the files they replace, along with the
+/// orphans, into the staging directory, recording each step in `journal`
+fn swap_in(
+    output_dir: &Path,
+    staging: &Path,
+    staged: &[&str],
+    orphans: &[&str],
+    rename: &mut impl FnMut(&Path, &Path) -> io::Result<()>,
+    journal: &mut Journal,
+) -> io::Result<()> {
+    for path in staged {
+        let target = output_dir.join(path);
+        for dir in target.ancestors().skip(1).take_while(|d| !d.exists()).collect::<Vec<_>>().into_iter().rev() {
+            fs::create_dir(dir)?;
+            journal.dirs.push(dir.to_path_buf());
+        }
+        let previous = set_aside(output_dir, staging, path, rename)?;
+        journal.files.push((target.clone(), previous));
+        rename(&staging.join("new").join(path), &target)?;
+    }
+    for orphan in orphans {
+        if let Some(previous) = set_aside(output_dir, staging, orphan, rename)? {
+            journal.files.push((output_dir.join(orphan), Some(previous)));
+        }
+    }
+    Ok(())
+}
+
+/// Move the output file at `path`, if there is one, into the staging directory
+fn set_aside(
+    output_dir: &Path,
+    staging: &Path,
+    path: &str,
+    rename: &mut impl FnMut(&Path, &Path) -> io::Result<()>,
+) -> io::Result<Option<PathBuf>> {
+    let target = output_dir.join(path);
+    if !target.exists() {
+        return Ok(None);
+    }
+    let previous = staging.join("old").join(path);
+    if let Some(dir) = previous.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    rename(&target, &previous)?;
+    Ok(Some(previous))
+}
+
+/// Delete the directories that removing `path` left empty, up to `output_dir`
+fn remove_empty_parents(output_dir: &Path, path: &Path) {
+    let mut dir = path.parent();
+    while let Some(parent) = dir.filter(|d| *d != output_dir) {
+        // Fails once the directory still holds something
+        if fs::remove_dir(parent).is_err() {
+            break;
+        }
+        dir = parent.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(files: &[(&str, &str)]) -> GenerationResult {
+        let mut result = GenerationResult::new();
+        for (path, content) in files {
+            result.add_file(*path, content.to_string());
+        }
+        result
+    }
+
+    #[test]
+    fn test_commit_writes_tree_and_prunes_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out");
+        let first = result(&[("main.py", "app = 1\n"), ("models/user.py", "class User: ...\n")]);
+        commit(&output, &first, &[], &Manifest::record(&first)).unwrap();
+        assert_eq!(fs::read_to_string(output.join("models/user.py")).unwrap(), "class User: ...\n");
+        assert!(output.join(MANIFEST_FILE).exists());
+
+        let second = result(&[("main.py", "app = 2\n")]);
+        commit(&output, &second, &["models/user.py"], &Manifest::record(&second)).unwrap();
+        assert_eq!(fs::read_to_string(output.join("main.py")).unwrap(), "app = 2\n");
+        assert!(!output.join("models").exists());
+        // Only the output is left behind, not the staging directory
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_commit_leaves_unchanged_files_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out");
+        let files = result(&[("main.py", "app = 1\n"), ("models.py", "x = 1\n")]);
+        commit(&output, &files, &[], &Manifest::record(&files)).unwrap();
+        let past = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        for path in ["main.py", "models.py", MANIFEST_FILE] {
+            fs::File::options().write(true).open(output.join(path)).unwrap().set_modified(past).unwrap();
+        }
+        let modified = |path: &str| fs::metadata(output.join(path)).unwrap().modified().unwrap();
+
+        commit(&output, &files, &[], &Manifest::record(&files)).unwrap();
+        assert_eq!(modified("main.py"), past);
+        assert_eq!(modified(MANIFEST_FILE), past);
+
+        let next = result(&[("main.py", "app = 2\n"), ("models.py", "x = 1\n")]);
+        commit(&output, &next, &[], &Manifest::record(&next)).unwrap();
+        assert_ne!(modified("main.py"), past);
+        assert_eq!(modified("models.py"), past);
+    }
+
+    #[test]
+    fn test_failed_commit_restores_previous_output() {
+        let first = result(&[("main.py", "app = 1\n"), ("models/user.py", "class User: ...\n"), ("old.py", "x = 1\n")]);
+        let next = result(&[("main.py", "app = 2\n"), ("models/user.py", "class User: pass\n"), ("routes/users.py", "")]);
+        let read = |dir: &Path| {
+            let mut files = Vec::new();
+            for entry in files_under(dir) {
+                files.push((entry.strip_prefix(dir).unwrap().to_path_buf(), fs::read_to_string(&entry).unwrap()));
+            }
+            files.sort();
+            files
+        };
+
+        // Fail each move in turn: whatever had been moved is put back
+        for fail_at in 0.. {
+            let dir = tempfile::tempdir().unwrap();
+            let output = dir.path().join("out");
+            commit(&output, &first, &[], &Manifest::record(&first)).unwrap();
+            let before = read(&output);
+
+            let mut moves = 0;
+            let committed = commit_with(&output, &next, &["old.py"], &Manifest::record(&next), |from, to| {
+                moves += 1;
+                if moves > fail_at {
+                    return Err(io::Error::other("disk full"));
+                }
+                fs::rename(from, to)
+            });
+            if committed.is_ok() {
+                assert!(fail_at > 0);
+                assert!(!output.join("old.py").exists());
+                break;
+            }
+            assert_eq!(read(&output), before, "after failing move {}", fail_at + 1);
+            assert!(!output.join("routes").exists());
+            assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        }
+    }
+
+    /// Every file under `dir`
+    fn files_under(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(files_under(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    #[test]
+    fn test_plan_lists_changes_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.py"), "app = 1\n").unwrap();
+        fs::write(dir.path().join("same.py"), "x = 1\n").unwrap();
+        fs::write(dir.path().join("old.py"), "").unwrap();
+        let next = result(&[("main.py", "app = 2\n"), ("same.py", "x = 1\n"), ("new.py", "")]);
+
+        let changes = plan(dir.path(), &next, &["old.py"]).unwrap();
+        assert_eq!(changes, vec![
+            (Change::Update, "main.py"),
+            (Change::Create, "new.py"),
+            (Change::Delete, "old.py"),
+        ]);
+        assert!(!dir.path().join("new.py").exists());
+    }
+}
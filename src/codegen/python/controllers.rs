@@ -1,106 +1,72 @@
 // Intent Compiler - Python Controller Generator
 // Generates controller classes for route handling
 
-use crate::ast::{Action, Decorator, IntentFile};
+use serde::Serialize;
+
+use crate::ir::{ActionIr, Cardinality, Program};
 use crate::codegen::GenerationResult;
+use crate::codegen::python::{EntityList, EntityNames, render_once, render_to};
+use crate::codegen::templates::Templates;
 use crate::error::CompileResult;
-use std::fs;
 use std::path::Path;
 
-pub fn generate_controllers(ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult> {
+/// Context of `controllers/controller.py`
+#[derive(Serialize)]
+struct ControllerContext<'a> {
+    #[serde(flatten)]
+    entity: EntityNames<'a>,
+    /// Route handlers for the entity's actions followed by its CRUD routes
+    routes: String,
+}
+
+pub fn generate_controllers(program: &Program, templates: &Templates, output_dir: &Path, generation_gap: bool) -> CompileResult<GenerationResult> {
     let mut result = GenerationResult::new();
 
-    // Create controllers directory
-    let controllers_dir = output_dir.join("controllers");
-    fs::create_dir_all(&controllers_dir)?;
+    // Generated routers live in controllers/base
+    if generation_gap {
+        render_to(templates, "__init__.py", &(), &mut result, "controllers/base/__init__.py")?;
+    }
 
     // Generate entity-specific controllers
-    for entity in &ast.entities {
-        let content = generate_entity_controller(entity, ast);
-        let filename = format!("{}_controller.py", entity.name.to_lowercase());
-        let path = controllers_dir.join(&filename);
-        fs::write(&path, &content)?;
-        result.add_file(format!("controllers/{}", filename), content.lines().count());
+    for entity in &program.entities {
+        let name = &entity.name;
+        let mut routes: String = program
+            .actions_for(name)
+            .map(|action| generate_action_route(action, name, program))
+            .collect();
+        routes.push_str(&generate_crud_routes(name, &name.to_lowercase(), program));
+
+        let context = ControllerContext { entity: EntityNames::new(name), routes };
+        let path = format!("controllers/{}_controller.py", name.to_lowercase());
+        if generation_gap {
+            let base_path = format!("controllers/base/{}_controller.py", name.to_lowercase());
+            render_to(templates, "controllers/controller.py", &context, &mut result, base_path)?;
+            // The module extending the router is the user's: only create it
+            render_once(templates, "controllers/extension.py", &context.entity, output_dir, &mut result, path)?;
+        } else {
+            render_to(templates, "controllers/controller.py", &context, &mut result, path)?;
+        }
     }
 
     // Generate __init__.py
-    let init_content = generate_controllers_init(ast);
-    let init_path = controllers_dir.join("__init__.py");
-    fs::write(&init_path, &init_content)?;
-    result.add_file("controllers/__init__.py", init_content.lines().count());
+    render_to(templates, "controllers/__init__.py", &EntityList::new(program), &mut result, "controllers/__init__.py")?;
 
     Ok(result)
 }
 
-fn generate_entity_controller(entity: &crate::ast::Entity, ast: &IntentFile) -> String {
-    let name = &entity.name;
-    let name_lower = name.to_lowercase();
-    
-    let mut content = String::new();
-    
-    // Header
-    content.push_str("# Intent Compiler Generated Controller with Routes\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-    content.push_str("from typing import Optional, List\n");
-    content.push_str("from fastapi import APIRouter, Depends, HTTPException, status
-from fastapi.security import OAuth2PasswordRequestForm\n");
-    content.push_str("from sqlalchemy.orm import Session\n\n");
-    content.push_str("from db.database import get_db\n");
-    content.push_str(&format!("from db.models import {}Model\n", name));
-    if let Some(auth_entity) = &ast.auth_entity {
-        if auth_entity != name {
-            content.push_str(&format!("from db.models import {}Model\n", auth_entity));
-        }
-    }
-    content.push_str("from models import *\n");
-    content.push_str(&format!("from services.{}_service import {}_service\n", name_lower, name_lower));
-    
-    let auth_import = if let Some(auth_entity) = &ast.auth_entity {
-        format!("from core.security import get_current_{}, get_password_hash\n", auth_entity.to_lowercase())
-    } else {
-        "from core.security import get_current_user_token, get_password_hash\n".to_string()
-    };
-    content.push_str(&auth_import);
-    
-    content.push_str("from logic.policies import *\n\n");
-    
-    // Router definition
-    content.push_str(&format!("router = APIRouter(prefix=\"/{}s\", tags=[\"{}\"])\n\n\n", name_lower, name));
-
-    // Action methods as Routes
-    for action in &ast.actions {
-        if let Some(target_entity) = action.infer_entity(ast) {
-            if target_entity == *name {
-                content.push_str(&generate_action_route(action, name, ast));
-            }
-        }
-    }
-
-    // CRUD methods as Routes
-    content.push_str(&generate_crud_routes(name, &name_lower, ast));
-    
-    content
-}
-
-fn generate_crud_routes(name: &str, name_lower: &str, ast: &IntentFile) -> String {
+fn generate_crud_routes(name: &str, name_lower: &str, program: &Program) -> String {
     let mut content = String::new();
     
     // Helper to check if an action already defines this route
     let route_exists = |method: crate::ast::HttpMethod, path_to_check: &str| -> bool {
         let name_plural = format!("{}s", name_lower);
-        ast.actions.iter().any(|a| {
-            a.decorators.iter().any(|d| {
-                if let Decorator::Api { method: m, path: p } = d {
-                    if *m != method { return false; }
-                    let p_clean = p.trim_matches('/');
-                    let check_clean = path_to_check.trim_matches('/');
-                    p_clean == check_clean || 
-                    p_clean == format!("{}/{}", name_plural, check_clean).trim_matches('/') ||
-                    p_clean == format!("{}/{}", name_lower, check_clean).trim_matches('/')
-                } else {
-                    false
-                }
-            })
+        program.actions.iter().filter_map(|a| a.route.as_ref()).any(|route| {
+            if route.method != method { return false; }
+            let p_clean = route.path.trim_matches('/');
+            let check_clean = path_to_check.trim_matches('/');
+            p_clean == check_clean || 
+            p_clean == format!("{}/{}", name_plural, check_clean).trim_matches('/') ||
+            p_clean == format!("{}/{}", name_lower, check_clean).trim_matches('/')
         })
     };
 
@@ -126,42 +92,21 @@ fn generate_crud_routes(name: &str, name_lower: &str, ast: &IntentFile) -> Strin
     content
 }
 
-fn generate_action_route(action: &Action, entity_name: &str, ast: &IntentFile) -> String {
+fn generate_action_route(action: &ActionIr, entity_name: &str, program: &Program) -> String {
     let mut content = String::new();
     let action_name = &action.name;
     let entity_lower = entity_name.to_lowercase();
     
     // Get API info
-    let default_path = "/".to_string();
-    let (method, path) = action.decorators.iter().find_map(|d| {
-        if let Decorator::Api { method, path } = d {
-            Some((method, path))
-        } else {
-            None
-        }
-    }).unwrap_or((&crate::ast::HttpMethod::Get, &default_path));
+    let (method, path) = action.route.as_ref()
+        .map(|r| (&r.method, r.path.clone()))
+        .unwrap_or((&crate::ast::HttpMethod::Get, "/".to_string()));
 
     let method_str = format!("{:?}", method).to_lowercase();
-    
-    // Strip entity prefix from path if present (e.g. /users/signup -> /signup because router has /users prefix)
-    let entity_prefix = format!("/{}s", entity_lower);
-    let entity_prefix_single = format!("/{}", entity_lower);
-    let mut relative_path = path.clone();
-    if relative_path.starts_with(&entity_prefix) {
-        relative_path = relative_path[entity_prefix.len()..].to_string();
-    } else if relative_path.starts_with(&entity_prefix_single) {
-        relative_path = relative_path[entity_prefix_single.len()..].to_string();
-    }
-    
-    if relative_path.is_empty() {
-        relative_path = "/".to_string();
-    }
-    if !relative_path.starts_with('/') {
-        relative_path = format!("/{}", relative_path);
-    }
+    let relative_path = relative_path(&path, &entity_lower);
 
     // Response model
-    let mut response_model = if let Some(output) = &action.output {
+    let mut response_model = if let Some(output) = &action.def.output {
         if !output.fields.is_empty() {
             format!("{}{}Response", entity_name, crate::codegen::python::models::to_pascal_case(action_name))
         } else {
@@ -171,9 +116,7 @@ fn generate_action_route(action: &Action, entity_name: &str, ast: &IntentFile) -
         "dict".to_string()
     };
 
-    // Determine if it should be a list
-    let returns_list = matches!(method, crate::ast::HttpMethod::Get) && !path.contains('{') && !action_name.starts_with("get_");
-    if returns_list && response_model != "dict" {
+    if action.cardinality == Cardinality::Many && response_model != "dict" {
         response_model = format!("List[{}]", response_model);
     }
 
@@ -183,27 +126,23 @@ fn generate_action_route(action: &Action, entity_name: &str, ast: &IntentFile) -
     let mut params = Vec::new();
     let mut call_params = Vec::new();
 
-    for segment in path.split('/') {
-        if segment.starts_with('{') && segment.ends_with('}') {
-            let param_name = &segment[1..segment.len()-1];
-            params.push(format!("{}: str", param_name));
-            call_params.push(param_name.to_string());
-        }
+    for param_name in action.path_params() {
+        params.push(format!("{}: str", param_name));
+        call_params.push(param_name.clone());
     }
 
-    if matches!(method, crate::ast::HttpMethod::Post | crate::ast::HttpMethod::Put | crate::ast::HttpMethod::Patch) {
-        let has_input = action.input.as_ref().map(|i| !i.fields.is_empty()).unwrap_or(false);
+    if action.route.as_ref().is_some_and(|r| r.has_body()) {
+        let input = action.def.input.as_ref();
+        let has_input = input.map(|i| !i.fields.is_empty()).unwrap_or(false);
         if has_input {
              let request_model = format!("{}Request", crate::codegen::python::models::to_pascal_case(action_name));
              
              // Special handling for login via form data (for Swagger UI support)
-             if action_name == "login" && 
-                action.input.as_ref().map(|i| i.fields.iter().any(|f| f.name == "password") && (i.fields.iter().any(|f| f.name == "username") || i.fields.iter().any(|f| f.name == "email"))).unwrap_or(false)
-             {
+             if is_form_login(action) {
                  params.push("form_data: OAuth2PasswordRequestForm = Depends()".to_string());
                  
                  // Map form_data to request model
-                 let email_field = if action.input.as_ref().unwrap().fields.iter().any(|f| f.name == "email") { "email" } else { "username" };
+                 let email_field = if input.is_some_and(|i| i.fields.iter().any(|f| f.name == "email")) { "email" } else { "username" };
                  call_params.push(format!("{}({} = form_data.username, password = form_data.password)", request_model, email_field));
              } else {
                  params.push(format!("data: {}", request_model));
@@ -218,9 +157,8 @@ fn generate_action_route(action: &Action, entity_name: &str, ast: &IntentFile) -
     params.push("db: Session = Depends(get_db)".to_string());
     call_params.push("db".to_string());
 
-    let requires_auth = action.decorators.iter().any(|d| matches!(d, Decorator::Auth { .. }));
-    if requires_auth {
-        if let Some(auth_entity) = &ast.auth_entity {
+    if action.requires_auth {
+        if let Some(auth_entity) = &program.auth_entity {
             params.push(format!("current_user: {}Model = Depends(get_current_{})", auth_entity, auth_entity.to_lowercase()));
         } else {
             params.push("current_user: dict = Depends(get_current_user_token)".to_string());
@@ -233,11 +171,7 @@ fn generate_action_route(action: &Action, entity_name: &str, ast: &IntentFile) -
 
     // Policy Check
     let has_id = path.contains("{id}");
-    let needs_resource = action.decorators.iter().any(|d| {
-        if let Decorator::Policy(name) = d {
-            name.contains('.')
-        } else { false }
-    });
+    let needs_resource = action.policies.iter().any(|p| p.entity.is_some());
 
     if needs_resource && has_id {
         content.push_str("    # Fetch resource for policy check\n");
@@ -245,10 +179,10 @@ fn generate_action_route(action: &Action, entity_name: &str, ast: &IntentFile) -
         content.push_str("    if not resource:\n");
         content.push_str(&format!("        raise HTTPException(status_code=404, detail=\"{} not found\")\n", entity_name));
         
-        let policy_check = generate_policy_enforcement(action, ast, "resource").unwrap_or_default();
+        let policy_check = generate_policy_enforcement(action, "resource");
         content.push_str(&policy_check);
     } else {
-        let policy_check = generate_policy_enforcement(action, ast, "None").unwrap_or_default();
+        let policy_check = generate_policy_enforcement(action, "None");
         content.push_str(&policy_check);
     }
 
@@ -258,62 +192,58 @@ fn generate_action_route(action: &Action, entity_name: &str, ast: &IntentFile) -
 }
 
 
-fn generate_policy_enforcement(action: &Action, ast: &IntentFile, target_var: &str) -> CompileResult<String> {
-    let mut content = String::new();
-    
-    for decorator in &action.decorators {
-        if let Decorator::Policy(name) = decorator {
-            let func_name = if ast.policies.iter().any(|p| p.name == *name) {
-                Some(format!("check_{}", name))
-            } else if name.contains('.') {
-                let parts: Vec<&str> = name.split('.').collect();
-                if parts.len() == 2 {
-                    let entity_name = parts[0];
-                    let policy_name = parts[1];
-                    let entity = ast.entities.iter().find(|e| e.name == entity_name);
-                    if let Some(entity) = entity {
-                        if entity.policies.iter().any(|p| p.name == policy_name) {
-                            Some(format!("check_{}_{}", entity_name, policy_name))
-                        } else { None }
-                    } else { None }
-                } else { None }
-            } else { None };
+/// Path served for an action's route: the router is mounted at `/{entity}s`
+pub(crate) fn served_path(path: &str, entity_name: &str) -> String {
+    let entity_lower = entity_name.to_lowercase();
+    format!("/{}s{}", entity_lower, relative_path(path, &entity_lower))
+}
 
-            if let Some(func) = func_name {
-                let resource_arg = if target_var == "None" {
-                    ""
-                } else {
-                    &format!(", resource={}", target_var)
-                };
+/// Route path relative to the entity router
+fn relative_path(path: &str, entity_lower: &str) -> String {
+    // Strip entity prefix from path if present (e.g. /users/signup -> /signup because router has /users prefix)
+    let entity_prefix = format!("/{}s", entity_lower);
+    let entity_prefix_single = format!("/{}", entity_lower);
+    let mut relative_path = path.to_string();
+    if relative_path.starts_with(&entity_prefix) {
+        relative_path = relative_path[entity_prefix.len()..].to_string();
+    } else if relative_path.starts_with(&entity_prefix_single) {
+        relative_path = relative_path[entity_prefix_single.len()..].to_string();
+    }
 
-                content.push_str(&format!("    # Enforce policy: {}\n", name));
-                content.push_str(&format!("    {}(user=current_user{})\n", func, resource_arg));
-            }
-        }
+    if relative_path.is_empty() {
+        relative_path = "/".to_string();
     }
-    
-    Ok(content)
+    if !relative_path.starts_with('/') {
+        relative_path = format!("/{}", relative_path);
+    }
+    relative_path
+}
+
+/// Whether a login action takes OAuth2 form data instead of JSON (for Swagger UI support)
+pub(crate) fn is_form_login(action: &ActionIr) -> bool {
+    let fields = action.def.input.iter().flat_map(|i| &i.fields);
+    action.name == "login"
+        && fields.clone().any(|f| f.name == "password")
+        && fields.clone().any(|f| f.name == "username" || f.name == "email")
 }
 
-fn generate_controllers_init(ast: &IntentFile) -> String {
+fn generate_policy_enforcement(action: &ActionIr, target_var: &str) -> String {
     let mut content = String::new();
-    content.push_str("# Intent Compiler Generated Controllers\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
     
-    for entity in &ast.entities {
-        let name_lower = entity.name.to_lowercase();
-        content.push_str(&format!(
-            "from controllers.{}_controller import router as {}_router\n",
-            name_lower, name_lower
-        ));
-    }
-    
-    content.push_str("\n__all__ = [\n");
-    for entity in &ast.entities {
-        let name_lower = entity.name.to_lowercase();
-        content.push_str(&format!("    \"{}_router\",\n", name_lower));
+    for policy in &action.policies {
+        let (label, func) = match &policy.entity {
+            Some(entity) => (format!("{}.{}", entity, policy.name), format!("check_{}_{}", entity, policy.name)),
+            None => (policy.name.clone(), format!("check_{}", policy.name)),
+        };
+        let resource_arg = if target_var == "None" {
+            String::new()
+        } else {
+            format!(", resource={}", target_var)
+        };
+
+        content.push_str(&format!("    # Enforce policy: {}\n", label));
+        content.push_str(&format!("    {}(user=current_user{})\n", func, resource_arg));
     }
-    content.push_str("]\n");
     
     content
 }
@@ -1,308 +1,297 @@
 // Intent Compiler - Alembic Migration Generator
-// Generates Alembic migrations from entity definitions
+// Generates the initial Alembic migration and a new revision for each schema change
 
 use std::fs;
 use std::path::Path;
+
 use chrono::Utc;
+use serde::Serialize;
 
-use crate::ast::{Entity, FieldType, Decorator, IntentFile};
+use crate::ir::Program;
 use crate::codegen::GenerationResult;
-use crate::error::CompileResult;
-
-/// Generate Alembic migrations
-pub fn generate_migrations(ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult> {
-    let mut result = GenerationResult::new();
-
-    // Generate alembic.ini
-    let alembic_ini = generate_alembic_ini();
-    fs::write(output_dir.join("alembic.ini"), &alembic_ini)?;
-    result.add_file("alembic.ini", alembic_ini.lines().count());
-
-    // Generate env.py
-    let env_py = generate_env_py();
-    fs::write(output_dir.join("db/migrations/env.py"), &env_py)?;
-    result.add_file("db/migrations/env.py", env_py.lines().count());
-
-    // Generate script.py.mako
-    let script_mako = generate_script_mako();
-    fs::write(output_dir.join("db/migrations/script.py.mako"), &script_mako)?;
-    result.add_file("db/migrations/script.py.mako", script_mako.lines().count());
-
-    // Generate initial migration
-    let (migration, lines) = generate_initial_migration(ast)?;
-    let migration_filename = format!("001_initial.py");
-    fs::write(output_dir.join("db/migrations/versions").join(&migration_filename), &migration)?;
-    result.add_file(format!("db/migrations/versions/{}", migration_filename), lines);
-
-    Ok(result)
+use crate::codegen::python::render_to;
+use crate::codegen::python::schema::{self, LOCK_FILE, Lock, TableChange, TableSchema};
+use crate::codegen::templates::Templates;
+use crate::error::{CompileError, CompileResult};
+
+/// Directory holding the Alembic revisions
+const VERSIONS_DIR: &str = "db/migrations/versions";
+
+/// Revision of the initial migration
+const INITIAL_REVISION: &str = "001_initial";
+
+/// A table created by the initial migration
+#[derive(Serialize)]
+struct TableView<'a> {
+    name: &'a str,
+    /// `sa.Column(...)` expressions and table constraints
+    columns: Vec<String>,
+    indexes: &'a [schema::IndexSchema],
 }
 
-/// Generate alembic.ini
-fn generate_alembic_ini() -> String {
-    r#"# Intent Compiler Generated Alembic Configuration
-# A generic, single database configuration.
-
-[alembic]
-script_location = db/migrations
-prepend_sys_path = .
-version_path_separator = os
-
-sqlalchemy.url = sqlite:///./app.db
-
-[post_write_hooks]
-
-[loggers]
-keys = root,sqlalchemy,alembic
-
-[handlers]
-keys = console
-
-[formatters]
-keys = generic
-
-[logger_root]
-level = WARN
-handlers = console
-qualname =
-
-[logger_sqlalchemy]
-level = WARN
-handlers =
-qualname = sqlalchemy.engine
-
-[logger_alembic]
-level = INFO
-handlers =
-qualname = alembic
-
-[handler_console]
-class = StreamHandler
-args = (sys.stderr,)
-level = NOTSET
-formatter = generic
-
-[formatter_generic]
-format = %(levelname)-5.5s [%(name)s] %(message)s
-datefmt = %H:%M:%S
-"#.to_string()
+/// Context of `db/migrations/versions/001_initial.py`
+#[derive(Serialize)]
+struct MigrationContext<'a> {
+    create_date: String,
+    tables: Vec<TableView<'a>>,
 }
 
-/// Generate env.py for Alembic
-fn generate_env_py() -> String {
-    r#"# Intent Compiler Generated Alembic Environment
-from logging.config import fileConfig
-
-from sqlalchemy import engine_from_config
-from sqlalchemy import pool
-
-from alembic import context
-
-import sys
-from pathlib import Path
-
-# Add parent directory to path for imports
-sys.path.insert(0, str(Path(__file__).parent.parent.parent))
-
-from db.models import Base
-
-config = context.config
-
-if config.config_file_name is not None:
-    fileConfig(config.config_file_name)
-
-target_metadata = Base.metadata
-
-
-def run_migrations_offline() -> None:
-    """Run migrations in 'offline' mode."""
-    url = config.get_main_option("sqlalchemy.url")
-    context.configure(
-        url=url,
-        target_metadata=target_metadata,
-        literal_binds=True,
-        dialect_opts={"paramstyle": "named"},
-    )
-
-    with context.begin_transaction():
-        context.run_migrations()
-
-
-def run_migrations_online() -> None:
-    """Run migrations in 'online' mode."""
-    connectable = engine_from_config(
-        config.get_section(config.config_ini_section, {}),
-        prefix="sqlalchemy.",
-        poolclass=pool.NullPool,
-    )
-
-    with connectable.connect() as connection:
-        context.configure(
-            connection=connection, target_metadata=target_metadata
-        )
-
-        with context.begin_transaction():
-            context.run_migrations()
-
-
-if context.is_offline_mode():
-    run_migrations_offline()
-else:
-    run_migrations_online()
-"#.to_string()
+/// Context of a revision migrating from the previous snapshot
+#[derive(Serialize)]
+struct RevisionContext {
+    revision: String,
+    down_revision: String,
+    create_date: String,
+    /// One line per change
+    changes: Vec<String>,
+    /// Python statements of `upgrade()` and `downgrade()`
+    upgrade: Vec<String>,
+    downgrade: Vec<String>,
 }
 
-/// Generate script.py.mako template
-fn generate_script_mako() -> String {
-    r#""""${message}
+/// Generate Alembic migrations.
+///
+/// Without a snapshot in `intent.lock`, `001_initial.py` creates every table. Once there is
+/// one, revisions are history: they are kept as they are, and a schema change adds a revision
+/// chained to the one recorded in the snapshot. Changes that lose data are refused unless
+/// `allow_destructive` acknowledges them.
+pub fn generate_migrations(
+    program: &Program,
+    templates: &Templates,
+    output_dir: &Path,
+    allow_destructive: bool,
+) -> CompileResult<GenerationResult> {
+    let mut result = GenerationResult::new();
 
-Revision ID: ${up_revision}
-Revises: ${down_revision | comma,n}
-Create Date: ${create_date}
+    // Generate alembic.ini, env.py and script.py.mako
+    for path in ["alembic.ini", "db/migrations/env.py", "db/migrations/script.py.mako"] {
+        render_to(templates, path, &(), &mut result, path)?;
+    }
 
-"""
-from typing import Sequence, Union
+    let tables = schema::tables(program);
+    let revision = match load_lock(output_dir)? {
+        None => {
+            generate_initial(&tables, templates, output_dir, &mut result)?;
+            INITIAL_REVISION.to_string()
+        }
+        Some(lock) => {
+            for path in existing_revisions(output_dir)? {
+                result.add_user_file(path, None);
+            }
+            let diff = schema::diff(&lock.tables, &tables);
+            for change in &diff.unsupported {
+                result.warnings.push(format!("{} needs a hand-written migration", change));
+            }
+            let data_loss: Vec<String> = diff.changes.iter().flat_map(TableChange::data_loss).collect();
+            if !data_loss.is_empty() && !allow_destructive {
+                return Err(CompileError::destructive_migration(format!(
+                    "the next migration would lose data: {} (pass --allow-destructive to generate it, \
+                     or mark renames with @renamed_from(\"old_name\"))",
+                    data_loss.join(", ")
+                )));
+            }
+            for change in &data_loss {
+                result.warnings.push(format!("migration will {}", change));
+            }
+            if diff.changes.is_empty() {
+                lock.revision
+            } else {
+                generate_revision(&lock.revision, &diff.changes, templates, &mut result)?
+            }
+        }
+    };
 
-from alembic import op
-import sqlalchemy as sa
-${imports if imports else ""}
+    let lock = Lock { revision, tables };
+    let json = serde_json::to_string_pretty(&lock)
+        .map_err(|e| CompileError::codegen(format!("failed to serialize {}: {}", LOCK_FILE, e)))?;
+    result.add_file(LOCK_FILE, json + "\n");
 
-# revision identifiers, used by Alembic.
-revision: str = ${repr(up_revision)}
-down_revision: Union[str, None] = ${repr(down_revision)}
-branch_labels: Union[str, Sequence[str], None] = ${repr(branch_labels)}
-depends_on: Union[str, Sequence[str], None] = ${repr(depends_on)}
+    Ok(result)
+}
 
+/// Generate the migration creating every table, keeping its date so that recompiling doesn't change it
+fn generate_initial(tables: &[TableSchema], templates: &Templates, output_dir: &Path, result: &mut GenerationResult) -> CompileResult<()> {
+    let path = format!("{}/{}.py", VERSIONS_DIR, INITIAL_REVISION);
+    let context = MigrationContext {
+        create_date: existing_create_date(&output_dir.join(&path)).unwrap_or_else(now),
+        tables: tables
+            .iter()
+            .map(|table| TableView {
+                name: &table.name,
+                columns: table.create_args(),
+                indexes: &table.indexes,
+            })
+            .collect(),
+    };
+    render_to(templates, "db/migrations/versions/001_initial.py", &context, result, path)
+}
 
-def upgrade() -> None:
-    ${upgrades if upgrades else "pass"}
+/// Longest revision id `alembic_version.version_num`, a VARCHAR(32), can hold
+const MAX_REVISION_LENGTH: usize = 32;
+
+/// Generate the revision following `down_revision`, returning its id
+fn generate_revision(
+    down_revision: &str,
+    changes: &[TableChange],
+    templates: &Templates,
+    result: &mut GenerationResult,
+) -> CompileResult<String> {
+    let descriptions: Vec<String> = changes.iter().flat_map(TableChange::describe).collect();
+    let number = down_revision
+        .split('_')
+        .next()
+        .and_then(|n| n.parse::<u32>().ok())
+        .unwrap_or(1);
+    let slug = match descriptions.as_slice() {
+        [only] => only.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect(),
+        _ => "update_schema".to_string(),
+    };
+    let mut revision = format!("{:03}_{}", number + 1, slug);
+    // Shorten at a word boundary; the full description stays in the docstring
+    if revision.len() > MAX_REVISION_LENGTH {
+        let cut = revision[..=MAX_REVISION_LENGTH].rfind('_').unwrap_or(MAX_REVISION_LENGTH);
+        revision.truncate(cut);
+    }
 
+    let context = RevisionContext {
+        revision: revision.clone(),
+        down_revision: down_revision.to_string(),
+        create_date: now(),
+        upgrade: changes.iter().flat_map(TableChange::upgrade).collect(),
+        downgrade: changes.iter().rev().flat_map(|change| change.inverse().upgrade()).collect(),
+        changes: descriptions,
+    };
+    let content = templates.render("db/migrations/versions/revision.py", &context)?;
+    // A generated revision is history from now on, like the ones before it
+    result.add_user_file(format!("{}/{}.py", VERSIONS_DIR, revision), Some(content));
+    Ok(revision)
+}
 
-def downgrade() -> None:
-    ${downgrades if downgrades else "pass"}
-"#.to_string()
+/// The snapshot of the last compilation into `output_dir`, if it recorded one
+fn load_lock(output_dir: &Path) -> CompileResult<Option<Lock>> {
+    let path = output_dir.join(LOCK_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    serde_json::from_str(&fs::read_to_string(&path)?)
+        .map(Some)
+        .map_err(|e| CompileError::codegen(format!("invalid {}: {}", path.display(), e)))
 }
 
-/// Generate initial migration from entities
-fn generate_initial_migration(ast: &IntentFile) -> CompileResult<(String, usize)> {
-    let mut content = String::new();
-
-    content.push_str("\"\"\"Initial migration - create all tables\n\n");
-    content.push_str("Revision ID: 001_initial\n");
-    content.push_str("Revises: \n");
-    content.push_str(&format!("Create Date: {}\n\n", Utc::now().format("%Y-%m-%d %H:%M:%S")));
-    content.push_str("\"\"\"\n");
-    content.push_str("from typing import Sequence, Union\n\n");
-    content.push_str("from alembic import op\n");
-    content.push_str("import sqlalchemy as sa\n\n\n");
-    
-    content.push_str("# revision identifiers, used by Alembic.\n");
-    content.push_str("revision: str = '001_initial'\n");
-    content.push_str("down_revision: Union[str, None] = None\n");
-    content.push_str("branch_labels: Union[str, Sequence[str], None] = None\n");
-    content.push_str("depends_on: Union[str, Sequence[str], None] = None\n\n\n");
-
-    // Generate upgrade function
-    content.push_str("def upgrade() -> None:\n");
-    content.push_str("    \"\"\"Create all tables\"\"\"\n");
-    
-    for entity in &ast.entities {
-        content.push_str(&generate_create_table(entity)?);
+/// Output-relative paths of the revisions already in `output_dir`
+fn existing_revisions(output_dir: &Path) -> CompileResult<Vec<String>> {
+    let dir = output_dir.join(VERSIONS_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
     }
-    
-    content.push_str("\n\n");
-
-    // Generate downgrade function
-    content.push_str("def downgrade() -> None:\n");
-    content.push_str("    \"\"\"Drop all tables\"\"\"\n");
-    
-    for entity in ast.entities.iter().rev() {
-        let table_name = entity.name.to_lowercase() + "s";
-        content.push_str(&format!("    op.drop_table('{}')\n", table_name));
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if name.ends_with(".py") {
+            paths.push(format!("{}/{}", VERSIONS_DIR, name));
+        }
     }
-
-    let lines = content.lines().count();
-    Ok((content, lines))
+    paths.sort();
+    Ok(paths)
 }
 
-/// Generate create_table call for an entity
-fn generate_create_table(entity: &Entity) -> CompileResult<String> {
-    let table_name = entity.name.to_lowercase() + "s";
-    let mut content = String::new();
+/// The `Create Date:` of a previously generated migration
+fn existing_create_date(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("Create Date: "))
+        .map(str::to_string)
+}
 
-    content.push_str(&format!("    op.create_table(\n"));
-    content.push_str(&format!("        '{}',\n", table_name));
+fn now() -> String {
+    Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
 
-    for field in &entity.fields {
-        content.push_str(&format!("        {},\n", generate_column_def(field)?));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::manifest::Manifest;
+    use crate::codegen::{CodeGenerator, GeneratorOptions, output};
+    use crate::codegen::python::PythonGenerator;
+    use crate::parser::parse_intent;
+
+    fn compile(source: &str, output_dir: &Path) -> GenerationResult {
+        let program = Program::lower(&parse_intent(source).unwrap());
+        let result = PythonGenerator::default().generate(&program, output_dir).unwrap();
+        output::commit(output_dir, &result, &[], &Manifest::record(&result)).unwrap();
+        result
     }
 
-    content.push_str("    )\n");
+    #[test]
+    fn test_destructive_change_needs_acknowledgement() {
+        let dir = tempfile::tempdir().unwrap();
+        compile("entity User:\n    id: uuid @primary\n    age: number\n", dir.path());
+        let program = Program::lower(&parse_intent("entity User:\n    id: uuid @primary\n").unwrap());
 
-    // Create indexes
-    for field in &entity.fields {
-        if field.decorators.contains(&Decorator::Index) {
-            content.push_str(&format!(
-                "    op.create_index('ix_{0}_{1}', '{0}', ['{1}'])\n",
-                table_name, field.name
-            ));
-        }
-    }
+        let refused = PythonGenerator::default().generate(&program, dir.path()).unwrap_err();
+        assert!(refused.to_string().contains("drop column users.age"));
 
-    Ok(content)
-}
-
-/// Generate a column definition for migration
-fn generate_column_def(field: &crate::ast::Field) -> CompileResult<String> {
-    let is_primary = field.decorators.contains(&Decorator::Primary);
-    let is_nullable = field.decorators.contains(&Decorator::Optional);
-    let is_unique = field.decorators.contains(&Decorator::Unique);
-
-    let col_type = match &field.field_type {
-        FieldType::String => "sa.String(255)".to_string(),
-        FieldType::Number => "sa.Float()".to_string(),
-        FieldType::Boolean => "sa.Boolean()".to_string(),
-        FieldType::DateTime => "sa.DateTime()".to_string(),
-        FieldType::Uuid => "sa.String(36)".to_string(),  // UUID as 36-char string
-        FieldType::Email => "sa.String(255)".to_string(), // Email as string
-        FieldType::Enum(values) => {
-            let vals = values.iter().map(|v| format!("'{}'", v)).collect::<Vec<_>>().join(", ");
-            format!("sa.Enum({})", vals)
-        }
-        FieldType::Reference(name) => {
-            format!("sa.String(255), sa.ForeignKey('{}.id')", name.to_lowercase() + "s")
-        }
-        FieldType::Ref(name) => {
-            format!("sa.String(255), sa.ForeignKey('{}.id')", name.to_lowercase() + "s")
-        }
-        FieldType::Array(_) => "sa.JSON()".to_string(),
-        FieldType::List(_) => "sa.JSON()".to_string(),
-        FieldType::Optional(inner) => match inner.as_ref() {
-            FieldType::String => "sa.String(255)".to_string(),
-            FieldType::Number => "sa.Float()".to_string(),
-            FieldType::Boolean => "sa.Boolean()".to_string(),
-            FieldType::DateTime => "sa.DateTime()".to_string(),
-            FieldType::Uuid => "sa.String(36)".to_string(),
-            FieldType::Email => "sa.String(255)".to_string(),
-            _ => "sa.String(255)".to_string(),
-        },
-    };
-
-    let mut options = Vec::new();
-    if is_primary {
-        options.push("primary_key=True");
+        let generator = PythonGenerator::new(GeneratorOptions { allow_destructive: true, ..Default::default() });
+        let result = generator.generate(&program, dir.path()).unwrap();
+        assert!(result.files.contains_key("db/migrations/versions/002_drop_column_users_age.py"));
+        assert_eq!(result.warnings, vec!["migration will drop column users.age"]);
     }
-    if !is_nullable && !is_primary {
-        options.push("nullable=False");
+
+    #[test]
+    fn test_schema_change_adds_chained_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = compile("entity User:\n    id: uuid @primary\n", dir.path());
+        assert!(first.files.contains_key("db/migrations/versions/001_initial.py"));
+
+        let unchanged = compile("entity User:\n    id: uuid @primary\n", dir.path());
+        assert!(!unchanged.files.keys().any(|path| path.starts_with(VERSIONS_DIR)));
+        assert!(unchanged.user_files.contains(&"db/migrations/versions/001_initial.py".to_string()));
+
+        let changed = compile("entity User:\n    id: uuid @primary\n    age: number\n", dir.path());
+        let revision = &changed.files["db/migrations/versions/002_add_column_users_age.py"];
+        assert!(revision.contains("down_revision: Union[str, None] = '001_initial'"));
+        assert!(revision.contains("batch_op.add_column(sa.Column('age', sa.Float(), nullable=False))"));
+        assert!(changed.files[LOCK_FILE].contains("\"revision\": \"002_add_column_users_age\""));
     }
-    if is_unique {
-        options.push("unique=True");
+
+    #[test]
+    fn test_dropped_unique_constraint_has_the_name_it_was_created_with() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = compile("entity User:\n    id: uuid @primary\n    email: email @unique\n", dir.path());
+        assert!(first.files["db/migrations/versions/001_initial.py"]
+            .contains("sa.UniqueConstraint('email', name='uq_users_email'),"));
+        assert!(first.files["db/models.py"].contains("\"uq\": \"uq_%(table_name)s_%(column_0_name)s\""));
+
+        let changed = compile("entity User:\n    id: uuid @primary\n    email: email\n", dir.path());
+        let revision = changed.files.iter().find(|(path, _)| path.starts_with(&format!("{}/002_", VERSIONS_DIR))).unwrap().1;
+        assert!(revision.contains("batch_op.drop_constraint('uq_users_email', type_='unique')"));
+        assert!(revision.contains("batch_op.create_unique_constraint('uq_users_email', ['email'])"));
     }
 
-    let options_str = if options.is_empty() {
-        String::new()
-    } else {
-        format!(", {}", options.join(", "))
-    };
+    #[test]
+    fn test_long_revision_id_fits_alembic_version_table() {
+        let dir = tempfile::tempdir().unwrap();
+        compile("entity Coupon:\n    id: uuid @primary\n    note: string\n", dir.path());
+        let changed = compile("entity Coupon:\n    id: uuid @primary\n    memo: string @renamed_from(\"note\")\n", dir.path());
+        let revision = &changed.files["db/migrations/versions/002_rename_column_coupons_note.py"];
+        assert!(revision.contains("revision: str = '002_rename_column_coupons_note'"));
+        assert!(revision.contains("rename column coupons.note to memo"));
+        assert!(changed.files[LOCK_FILE].contains("\"revision\": \"002_rename_column_coupons_note\""));
+
+        let dir = tempfile::tempdir().unwrap();
+        compile("entity WarehouseInventoryReservation:\n    id: uuid @primary\n", dir.path());
+        let changed = compile("entity WarehouseInventoryReservation:\n    id: uuid @primary\n    quantity: number\n", dir.path());
+        assert!(changed.files.contains_key("db/migrations/versions/002_add_column.py"));
+    }
 
-    Ok(format!("sa.Column('{}', {}{})", field.name, col_type, options_str))
+    #[test]
+    fn test_optional_column_is_nullable() {
+        let dir = tempfile::tempdir().unwrap();
+        compile("entity Tag:\n    id: uuid @primary\n", dir.path());
+        let changed = compile("entity Tag:\n    id: uuid @primary\n    label: string?\n", dir.path());
+        let revision = &changed.files["db/migrations/versions/002_add_column_tags_label.py"];
+        // Existing rows get NULL, so the column needs no server default
+        assert!(revision.contains("batch_op.add_column(sa.Column('label', sa.String(255)))"));
+    }
 }
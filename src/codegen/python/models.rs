@@ -1,133 +1,124 @@
 // Intent Compiler - Pydantic Model Generator
 // Generates Pydantic models from entity definitions
 
-use std::fs;
-use std::path::Path;
 
-use crate::ast::{Entity, FieldType, Decorator, IntentFile};
+use serde::Serialize;
+
+use crate::ast::{Entity, FieldType, Decorator};
+use crate::ir::{Program, VarType};
 use crate::codegen::GenerationResult;
+use crate::codegen::python::{EntityList, EntityNames, render_to};
+use crate::codegen::templates::Templates;
 use crate::error::CompileResult;
 
-/// Generate Pydantic models for all entities
-pub fn generate_models(ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult> {
-    let mut result = GenerationResult::new();
+/// A model field as `name: python_type = default`
+#[derive(Serialize)]
+struct FieldView {
+    name: String,
+    python_type: String,
+    default: Option<String>,
+}
 
-    for entity in &ast.entities {
-        let (content, lines) = generate_entity_model(entity)?;
-        let filename = format!("{}.py", entity.name.to_lowercase());
-        let path = output_dir.join("models").join(&filename);
-        fs::write(&path, &content)?;
-        result.add_file(format!("models/{}", filename), lines);
+impl FieldView {
+    fn new(name: &str, python_type: String, default: Option<String>) -> Self {
+        Self { name: name.to_string(), python_type, default }
     }
+}
 
-    // Generate request models for actions
-    let (req_content, req_lines) = generate_action_requests(ast)?;
-    if req_lines > 0 {
-        let req_path = output_dir.join("models/requests.py");
-        fs::write(&req_path, &req_content)?;
-        result.add_file("models/requests.py", req_lines);
-    }
+/// A `Literal` alias for an enum field
+#[derive(Serialize)]
+struct EnumView<'a> {
+    field: &'a str,
+    name: String,
+    python_type: String,
+}
 
-    // Generate models/__init__.py with all exports
-    let init_content = generate_models_init(ast);
-    let init_path = output_dir.join("models/__init__.py");
-    fs::write(&init_path, &init_content)?;
+/// Context of `models/entity.py`
+#[derive(Serialize)]
+struct EntityContext<'a> {
+    name: &'a str,
+    enums: Vec<EnumView<'a>>,
+    /// Fields of the base and create models: every field but the primary key
+    base_fields: Vec<FieldView>,
+    /// The same fields, all optional
+    update_fields: Vec<FieldView>,
+    primary_fields: Vec<FieldView>,
+}
 
-    Ok(result)
+/// A request or response model for an action
+#[derive(Serialize)]
+struct ActionModel {
+    name: String,
+    fields: Vec<FieldView>,
 }
 
-/// Generate a single entity model
-fn generate_entity_model(entity: &Entity) -> CompileResult<(String, usize)> {
-    let mut content = String::new();
-
-    // Imports
-    content.push_str("# Intent Compiler Generated Pydantic Model\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-    content.push_str("from datetime import datetime\n");
-    content.push_str("from typing import Optional, Literal\n");
-    content.push_str("from pydantic import BaseModel, Field\n\n\n");
-
-    // Generate enum classes for enum fields
-    for field in &entity.fields {
-        if let FieldType::Enum(values) = &field.field_type {
-            content.push_str(&format!(
-                "# Enum for {} field\n",
-                field.name
-            ));
-            content.push_str(&format!(
-                "{}Type = Literal[{}]\n\n",
-                capitalize(&field.name),
-                values.iter().map(|v| format!("\"{}\"", v)).collect::<Vec<_>>().join(", ")
-            ));
-        }
-    }
+/// Context of `models/requests.py`
+#[derive(Serialize)]
+struct RequestsContext {
+    requests: Vec<ActionModel>,
+    responses: Vec<ActionModel>,
+}
 
-    // Base model (for creation)
-    content.push_str(&format!("class {}Base(BaseModel):\n", entity.name));
-    content.push_str("    model_config = {\"extra\": \"forbid\"}\n");
-    content.push_str("    \"\"\"Base model with common fields\"\"\"\n");
-    
-    let mut has_fields = false;
-    for field in &entity.fields {
-        // Skip primary key in base model
-        if field.decorators.contains(&Decorator::Primary) {
-            continue;
-        }
-        has_fields = true;
-        content.push_str(&generate_field_line(field));
-    }
-    
-    if !has_fields {
-        content.push_str("    pass\n");
-    }
-    content.push_str("\n\n");
+/// Context of `models/__init__.py`
+#[derive(Serialize)]
+struct InitContext<'a> {
+    entities: Vec<EntityNames<'a>>,
+    /// Whether models/requests.py defines any request model
+    requests: bool,
+    /// Request and response model names exported alongside the entity models
+    action_models: Vec<String>,
+}
 
-    // Create model
-    content.push_str(&format!("class {}Create({}Base):\n", entity.name, entity.name));
-    content.push_str("    \"\"\"Model for creating new records\"\"\"\n");
-    content.push_str("    pass\n\n\n");
+/// Generate Pydantic models for all entities
+pub fn generate_models(program: &Program, templates: &Templates) -> CompileResult<GenerationResult> {
+    let mut result = GenerationResult::new();
 
-    // Update model (all fields optional)
-    content.push_str(&format!("class {}Update(BaseModel):\n", entity.name));
-    content.push_str("    model_config = {\"extra\": \"forbid\"}\n");
-    content.push_str("    \"\"\"Model for updating records (all fields optional)\"\"\"\n");
-    
-    let mut has_update_fields = false;
-    for field in &entity.fields {
-        if field.decorators.contains(&Decorator::Primary) {
-            continue;
-        }
-        has_update_fields = true;
-        content.push_str(&generate_optional_field_line(field));
-    }
-    
-    if !has_update_fields {
-        content.push_str("    pass\n");
+    for entity in &program.entities {
+        let path = format!("models/{}.py", entity.name.to_lowercase());
+        render_to(templates, "models/entity.py", &entity_context(entity), &mut result, path)?;
     }
-    content.push_str("\n\n");
 
-    // Full model (includes ID)
-    content.push_str(&format!("class {}({}Base):\n", entity.name, entity.name));
-    content.push_str("    \"\"\"Full model with all fields including ID\"\"\"\n");
-    
-    // Add primary key field
-    for field in &entity.fields {
-        if field.decorators.contains(&Decorator::Primary) {
-            content.push_str(&generate_field_line(field));
-        }
+    // Generate request models for actions
+    let requests = RequestsContext {
+        requests: action_requests(program),
+        responses: action_responses(program),
+    };
+    render_to(templates, "models/requests.py", &requests, &mut result, "models/requests.py")?;
+
+    // Generate models/__init__.py with all exports
+    render_to(templates, "models/__init__.py", &init_context(program), &mut result, "models/__init__.py")?;
+
+    Ok(result)
+}
+
+/// Build the context of a single entity model
+fn entity_context(entity: &Entity) -> EntityContext<'_> {
+    let enums = entity
+        .fields
+        .iter()
+        .filter(|field| matches!(field.field_type, FieldType::Enum(_)))
+        .map(|field| EnumView {
+            field: &field.name,
+            name: format!("{}Type", capitalize(&field.name)),
+            python_type: field_type_to_python(&field.field_type),
+        })
+        .collect();
+    let (primary, others): (Vec<_>, Vec<_>) = entity
+        .fields
+        .iter()
+        .partition(|field| field.decorators.contains(&Decorator::Primary));
+
+    EntityContext {
+        name: &entity.name,
+        enums,
+        base_fields: others.iter().map(|field| field_view(field)).collect(),
+        update_fields: others.iter().map(|field| optional_field_view(field)).collect(),
+        primary_fields: primary.iter().map(|field| field_view(field)).collect(),
     }
-    content.push_str("\n");
-    content.push_str("    model_config = {\n");
-    content.push_str("        \"from_attributes\": True,\n");
-    content.push_str("        \"extra\": \"forbid\"\n");
-    content.push_str("    }\n");
-
-    let lines = content.lines().count();
-    Ok((content, lines))
 }
 
-/// Generate a field line for Pydantic model
-fn generate_field_line(field: &crate::ast::Field) -> String {
+/// A field of a Pydantic model
+fn field_view(field: &crate::ast::Field) -> FieldView {
     let python_type = field_type_to_python(&field.field_type);
     let is_optional = field.decorators.contains(&Decorator::Optional);
     
@@ -146,24 +137,18 @@ fn generate_field_line(field: &crate::ast::Field) -> String {
         python_type
     };
 
-    match default {
-        Some(val) => {
-            let default_val = format_default_value(&val, &field.field_type);
-            format!("    {}: {} = {}\n", field.name, type_str, default_val)
-        }
-        None if is_optional => {
-            format!("    {}: {} = None\n", field.name, type_str)
-        }
-        None => {
-            format!("    {}: {}\n", field.name, type_str)
-        }
-    }
+    let default = match default {
+        Some(val) => Some(format_default_value(&val, &field.field_type)),
+        None if is_optional => Some("None".to_string()),
+        None => None,
+    };
+    FieldView::new(&field.name, type_str, default)
 }
 
-/// Generate an optional field line for update model
-fn generate_optional_field_line(field: &crate::ast::Field) -> String {
+/// An optional field for the update model
+fn optional_field_view(field: &crate::ast::Field) -> FieldView {
     let python_type = field_type_to_python(&field.field_type);
-    format!("    {}: Optional[{}] = None\n", field.name, python_type)
+    FieldView::new(&field.name, format!("Optional[{}]", python_type), Some("None".to_string()))
 }
 
 /// Convert IDL field type to Python type string
@@ -215,159 +200,89 @@ fn format_default_value(value: &str, field_type: &FieldType) -> String {
     }
 }
 
-/// Generate models/__init__.py
-fn generate_models_init(ast: &IntentFile) -> String {
-    let mut content = String::new();
-    content.push_str("# Intent Compiler Generated Models\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-
-    for entity in &ast.entities {
-        let module = entity.name.to_lowercase();
-        content.push_str(&format!(
-            "from .{} import {}, {}Create, {}Update\n",
-            module, entity.name, entity.name, entity.name
-        ));
-    }
-
-    // Import request models
-    let requests_exist = ast.actions.iter().any(|a| a.input.as_ref().map(|i| !i.fields.is_empty()).unwrap_or(false));
-    if requests_exist {
-        content.push_str("from .requests import *\n");
-    }
-
-    content.push_str("\n__all__ = [\n");
-    for entity in &ast.entities {
-        content.push_str(&format!("    \"{}\",\n", entity.name));
-        content.push_str(&format!("    \"{}Create\",\n", entity.name));
-        content.push_str(&format!("    \"{}Update\",\n", entity.name));
-    }
-    
-    // Add request models AND response models to __all__
-    for action in &ast.actions {
-        let has_input = action.input.as_ref().map(|i| !i.fields.is_empty()).unwrap_or(false);
+/// Build the context of models/__init__.py
+fn init_context(program: &Program) -> InitContext<'_> {
+    let mut action_models = Vec::new();
+    for action in &program.actions {
+        let has_input = action.def.input.as_ref().map(|i| !i.fields.is_empty()).unwrap_or(false);
         if has_input {
-            let model_name = format!("{}Request", to_pascal_case(&action.name));
-            content.push_str(&format!("    \"{}\",\n", model_name));
+            action_models.push(format!("{}Request", to_pascal_case(&action.name)));
         }
         
         // Add response model if applicable
-        if let Some(output) = &action.output {
-            if !output.fields.is_empty() {
-                 let model_name = format!("{}{}Response", output.entity, to_pascal_case(&action.name));
-                 content.push_str(&format!("    \"{}\",\n", model_name));
-            }
+        if let Some(output) = action.def.output.as_ref().filter(|o| !o.fields.is_empty()) {
+            action_models.push(format!("{}{}Response", output.entity, to_pascal_case(&action.name)));
         }
     }
-    
-    content.push_str("]\n\n");
 
-    // Rebuild models to resolve circular references
-    for entity in &ast.entities {
-        content.push_str(&format!("{}.model_rebuild()\n", entity.name));
-        content.push_str(&format!("{}Create.model_rebuild()\n", entity.name));
-        content.push_str(&format!("{}Update.model_rebuild()\n", entity.name));
+    InitContext {
+        entities: EntityList::new(program).entities,
+        requests: program.actions.iter().any(|a| a.def.input.as_ref().map(|i| !i.fields.is_empty()).unwrap_or(false)),
+        action_models,
     }
-    
-    content
 }
 
-/// Generate request models for actions
-fn generate_action_requests(ast: &IntentFile) -> CompileResult<(String, usize)> {
-    let mut content = String::new();
-    let mut model_names = Vec::new();
-
-    content.push_str("# Intent Compiler Generated Request Models\n");
-    content.push_str("from typing import Optional, List, Literal\n");
-    content.push_str("from datetime import datetime\n");
-    content.push_str("from pydantic import BaseModel, Field\n\n\n");
-
-    for action in &ast.actions {
-        let input_fields = action.input.as_ref().map(|i| &i.fields);
-        if input_fields.map(|f| f.is_empty()).unwrap_or(true) {
-            continue;
-        }
-
-        let model_name = format!("{}Request", to_pascal_case(&action.name));
-        model_names.push(model_name.clone());
-        content.push_str(&format!("class {}(BaseModel):\n", model_name));
-        content.push_str("    model_config = {\"extra\": \"forbid\"}\n");
-        
-        if let Some(fields) = input_fields {
-            for param in fields {
-                 let python_type = field_type_to_python_input(&param.param_type);
-                 let default = if python_type.starts_with("Optional[") { " = None" } else { "" };
-                 content.push_str(&format!("    {}: {}{}\n", param.name, python_type, default));
+/// Request models for actions with input
+fn action_requests(program: &Program) -> Vec<ActionModel> {
+    program
+        .actions
+        .iter()
+        .filter_map(|action| {
+            let fields = &action.def.input.as_ref()?.fields;
+            if fields.is_empty() {
+                return None;
             }
-        }
-        content.push_str("\n\n");
-    }
-
-    if model_names.is_empty() {
-        // Continue to response models (don't return early yet)
-    } else {
-        // Add __all__ for proper import * support
-        let all_items = model_names.iter()
-            .map(|n| format!("\"{}\"", n))
-            .collect::<Vec<_>>()
-            .join(", ");
-        content.push_str(&format!("__all__ = [{}]\n", all_items));
-    }
+            let fields = fields
+                .iter()
+                .map(|param| {
+                    let python_type = field_type_to_python_input(&param.param_type);
+                    let default = python_type.starts_with("Optional[").then(|| "None".to_string());
+                    FieldView::new(&param.name, python_type, default)
+                })
+                .collect();
+            Some(ActionModel { name: format!("{}Request", to_pascal_case(&action.name)), fields })
+        })
+        .collect()
+}
 
-    // Generate response models for output projections
-    content.push_str("\n# Output projection models\n");
+/// Response models for output projections
+fn action_responses(program: &Program) -> Vec<ActionModel> {
     let mut response_models = Vec::new();
     
-    for action in &ast.actions {
-        if let Some(output) = &action.output {
-            if !output.fields.is_empty() {
-                // Find entity to get field types
-                let entity = ast.entities.iter().find(|e| e.name == output.entity);
-                if let Some(entity) = entity {
-                    let model_name = format!("{}{}Response", output.entity, to_pascal_case(&action.name));
-                    response_models.push(model_name.clone());
-                    
-                    content.push_str(&format!("class {}(BaseModel):\n", model_name));
-                    content.push_str("    model_config = {\"extra\": \"ignore\"}\n");
-                    
-                    for field_name in &output.fields {
-                        // Find field in entity
-                        if let Some(field) = entity.fields.iter().find(|f| &f.name == field_name) {
-                            let python_type = field_type_to_python(&field.field_type);
-                            // We make fields in response models optional? Or required if they are in entity?
-                            // Generally if it's a projection, value should be there.
-                            content.push_str(&format!("    {}: {}\n", field.name, python_type));
-                        } else {
-                            // Fallback if field not found (e.g. implicitly 'id')
-                             content.push_str(&format!("    {}: str\n", field_name));
-                        }
-                    }
-                    
-                    // Special handling for login response to support OAuth2 (Swagger UI)
-                    if action.name == "login" {
-                         content.push_str("    access_token: str\n");
-                         content.push_str("    token_type: str = \"bearer\"\n");
-                    }
-
-                    content.push_str("\n\n");
+    for action in &program.actions {
+        if let Some(output) = action.def.output.as_ref().filter(|o| !o.fields.is_empty()) {
+            // Find entity to get field types
+            if let Some(entity) = program.entities.iter().find(|e| e.name == output.entity) {
+                let mut fields = Vec::new();
+                for field_name in &output.fields {
+                    // Find field in entity
+                    let python_type = if let Some(field) = entity.fields.iter().find(|f| &f.name == field_name) {
+                        field_type_to_python(&field.field_type)
+                    } else if let Some(VarType::Value(ty)) = action.variable(field_name).map(|v| &v.ty) {
+                        // Derived variable with a known type
+                        field_type_to_python(ty)
+                    } else {
+                        // Fallback if field not found (e.g. implicitly 'id')
+                        "str".to_string()
+                    };
+                    fields.push(FieldView::new(field_name, python_type, None));
                 }
+                
+                // Special handling for login response to support OAuth2 (Swagger UI)
+                if action.name == "login" {
+                    fields.push(FieldView::new("access_token", "str".to_string(), None));
+                    fields.push(FieldView::new("token_type", "str".to_string(), Some("\"bearer\"".to_string())));
+                }
+
+                response_models.push(ActionModel {
+                    name: format!("{}{}Response", output.entity, to_pascal_case(&action.name)),
+                    fields,
+                });
             }
         }
     }
-    
-    if !response_models.is_empty() {
-         let all_items = response_models.iter()
-            .map(|n| format!("\"{}\"", n))
-            .collect::<Vec<_>>()
-            .join(", ");
-        if model_names.is_empty() {
-             content.push_str(&format!("__all__ = [{}]\n", all_items));
-        } else {
-             content.push_str(&format!("__all__.extend([{}])\n", all_items));
-        }
-    }
 
-    let lines = content.lines().count();
-    Ok((content, lines))
+    response_models
 }
 
 /// Capitalize first letter
@@ -382,7 +297,7 @@ pub fn capitalize(s: &str) -> String {
 /// Convert snake_case to PascalCase
 pub fn to_pascal_case(s: &str) -> String {
     s.split('_')
-        .map(|part| capitalize(part))
+        .map(capitalize)
         .collect::<Vec<_>>()
         .join("")
 }
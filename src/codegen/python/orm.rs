@@ -1,91 +1,109 @@
 // Intent Compiler - SQLAlchemy ORM Generator
 // Generates SQLAlchemy models from entity definitions
 
-use std::fs;
-use std::path::Path;
 
-use crate::ast::{Entity, FieldType, Decorator, IntentFile};
+use serde::Serialize;
+
+use crate::ast::{Entity, FieldType, Decorator};
+use crate::ir::Program;
 use crate::codegen::GenerationResult;
+use crate::codegen::python::render_to;
+use crate::codegen::templates::Templates;
 use crate::error::CompileResult;
 
+/// A mapped column as `name = Column(definition)`
+#[derive(Serialize)]
+struct ColumnView<'a> {
+    name: &'a str,
+    /// Column type and options
+    definition: String,
+}
+
+/// A relationship to the model of a referenced entity
+#[derive(Serialize)]
+struct RelationshipView {
+    name: String,
+    model: String,
+    back_populates: String,
+}
+
+/// An ORM model class
+#[derive(Serialize)]
+struct ModelView<'a> {
+    name: &'a str,
+    table: String,
+    /// `Index(...)` expressions for composite indexes
+    indexes: Vec<String>,
+    columns: Vec<ColumnView<'a>>,
+    relationships: Vec<RelationshipView>,
+    /// Field shown by `__repr__`
+    primary_key: &'a str,
+}
+
+/// Context of `db/models.py`
+#[derive(Serialize)]
+struct OrmContext<'a> {
+    models: Vec<ModelView<'a>>,
+}
+
 /// Generate SQLAlchemy ORM models
-pub fn generate_orm_models(ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult> {
+pub fn generate_orm_models(program: &Program, templates: &Templates) -> CompileResult<GenerationResult> {
     let mut result = GenerationResult::new();
-    let mut content = String::new();
-
-    // Imports
-    content.push_str("# Intent Compiler Generated SQLAlchemy Models\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-    content.push_str("import uuid\n");
-    content.push_str("from datetime import datetime\n");
-    content.push_str("from typing import Optional\n\n");
-    content.push_str("from sqlalchemy import Column, String, Float, Boolean, DateTime, Enum, ForeignKey, Integer\n");
-    content.push_str("from sqlalchemy.orm import DeclarativeBase, relationship\n\n\n");
-    
-    // Base class
-    content.push_str("class Base(DeclarativeBase):\n");
-    content.push_str("    \"\"\"Base class for all ORM models\"\"\"\n");
-    content.push_str("    pass\n\n\n");
 
     // Generate each entity as a SQLAlchemy model
-    for entity in &ast.entities {
-        content.push_str(&generate_orm_model(entity, ast)?);
-        content.push_str("\n\n");
-    }
-
-    let lines = content.lines().count();
-    let path = output_dir.join("db/models.py");
-    fs::write(&path, &content)?;
-    result.add_file("db/models.py", lines);
+    let context = OrmContext {
+        models: program.entities.iter().map(orm_model).collect(),
+    };
+    render_to(templates, "db/models.py", &context, &mut result, "db/models.py")?;
 
     Ok(result)
 }
 
-/// Generate a single ORM model
-fn generate_orm_model(entity: &Entity, _ast: &IntentFile) -> CompileResult<String> {
-    let mut content = String::new();
+/// Build a single ORM model
+fn orm_model(entity: &Entity) -> ModelView<'_> {
     let table_name = entity.name.to_lowercase() + "s"; // Simple pluralization
 
-    content.push_str(&format!("class {}Model(Base):\n", entity.name));
-    content.push_str(&format!("    \"\"\"SQLAlchemy model for {}\"\"\"\n", entity.name));
-    content.push_str(&format!("    __tablename__ = \"{}\"\n\n", table_name));
-
-    // Generate columns
-    for field in &entity.fields {
-        content.push_str(&generate_column(field)?);
-    }
+    let indexes = entity
+        .indexes
+        .iter()
+        .map(|columns| {
+            let quoted: Vec<String> = columns.iter().map(|c| format!("\"{}\"", c)).collect();
+            format!("Index(\"ix_{}_{}\", {})", table_name, columns.join("_"), quoted.join(", "))
+        })
+        .collect();
 
     // Generate relationships for foreign keys
-    for field in &entity.fields {
-        if let FieldType::Reference(ref_entity) = &field.field_type {
-            let relationship_name = ref_entity.to_lowercase();
-            content.push_str(&format!(
-                "    {} = relationship(\"{}Model\", back_populates=\"{}s\")\n",
-                relationship_name, ref_entity, entity.name.to_lowercase()
-            ));
-        }
-    }
+    let relationships = entity
+        .fields
+        .iter()
+        .filter_map(|field| match &field.field_type {
+            FieldType::Reference(ref_entity) => Some(RelationshipView {
+                name: ref_entity.to_lowercase(),
+                model: ref_entity.clone(),
+                back_populates: format!("{}s", entity.name.to_lowercase()),
+            }),
+            _ => None,
+        })
+        .collect();
 
-    // Add repr method
-    content.push_str("\n");
-    content.push_str("    def __repr__(self):\n");
-    
     // Find the primary key field
-    let pk_field = entity.fields.iter()
+    let primary_key = entity.fields.iter()
         .find(|f| f.decorators.contains(&Decorator::Primary))
         .map(|f| &f.name)
         .unwrap_or(&entity.fields[0].name);
-    
-    content.push_str(&format!(
-        "        return f\"<{}({}={{self.{}}})\"\n",
-        entity.name, pk_field, pk_field
-    ));
 
-    Ok(content)
+    ModelView {
+        name: &entity.name,
+        table: table_name,
+        indexes,
+        columns: entity.fields.iter().map(column).collect(),
+        relationships,
+        primary_key,
+    }
 }
 
-/// Generate a SQLAlchemy column definition
-fn generate_column(field: &crate::ast::Field) -> CompileResult<String> {
+/// Build a SQLAlchemy column definition
+fn column(field: &crate::ast::Field) -> ColumnView<'_> {
     let is_primary = field.decorators.contains(&Decorator::Primary);
     let is_unique = field.decorators.contains(&Decorator::Unique);
     let is_optional = field.decorators.contains(&Decorator::Optional) || matches!(field.field_type, FieldType::Optional(_));
@@ -99,9 +117,7 @@ fn generate_column(field: &crate::ast::Field) -> CompileResult<String> {
         }
     });
 
-    let column_type = field_type_to_sqlalchemy(&field.field_type);
-    
-    let mut options = Vec::new();
+    let mut options = vec![field_type_to_sqlalchemy(&field.field_type)];
     
     if is_primary {
         options.push("primary_key=True".to_string());
@@ -121,16 +137,7 @@ fn generate_column(field: &crate::ast::Field) -> CompileResult<String> {
         options.push(format!("default={}", default_value));
     }
 
-    let options_str = if options.is_empty() {
-        String::new()
-    } else {
-        format!(", {}", options.join(", "))
-    };
-
-    Ok(format!(
-        "    {} = Column({}{})\n",
-        field.name, column_type, options_str
-    ))
+    ColumnView { name: &field.name, definition: options.join(", ") }
 }
 
 /// Convert IDL field type to SQLAlchemy column type
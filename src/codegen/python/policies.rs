@@ -1,67 +1,54 @@
-use crate::ast::IntentFile;
+use serde::Serialize;
+
+use crate::ir::Program;
 use crate::codegen::GenerationResult;
+use crate::codegen::python::render_to;
+use crate::codegen::templates::Templates;
 use crate::error::CompileResult;
-use std::fs;
-use std::path::Path;
 
-pub fn generate_policies(ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult> {
-    let mut result = GenerationResult::new();
-    let mut content = String::new();
+/// A policy check function
+#[derive(Serialize)]
+struct PolicyView<'a> {
+    function: String,
+    name: &'a str,
+    /// Python condition the user (and resource) must satisfy
+    condition: String,
+}
 
-    content.push_str("# Intent Compiler Generated Policies\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-    content.push_str("from fastapi import HTTPException, status\n");
-    content.push_str("from typing import Any, Optional\n\n");
+/// Context of `logic/policies.py`
+#[derive(Serialize)]
+struct PoliciesContext<'a> {
+    policies: Vec<PolicyView<'a>>,
+}
 
-    for policy in &ast.policies {
-        content.push_str(&generate_policy_function(policy, ast, None));
-        content.push_str("\n\n");
-    }
+pub fn generate_policies(program: &Program, templates: &Templates) -> CompileResult<GenerationResult> {
+    let mut result = GenerationResult::new();
 
-    for entity in &ast.entities {
-        for policy in &entity.policies {
-            content.push_str(&generate_policy_function(policy, ast, Some(&entity.name)));
-            content.push_str("\n\n");
-        }
+    let mut policies: Vec<PolicyView> = program.policies.iter().map(|p| policy_view(p, None)).collect();
+    for entity in &program.entities {
+        policies.extend(entity.policies.iter().map(|p| policy_view(p, Some(&entity.name))));
     }
 
-    let path = output_dir.join("logic/policies.py");
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    fs::write(&path, &content)?;
-    result.add_file("logic/policies.py", content.lines().count());
+    render_to(templates, "logic/policies.py", &PoliciesContext { policies }, &mut result, "logic/policies.py")?;
 
     Ok(result)
 }
 
-fn generate_policy_function(policy: &crate::ast::Policy, _ast: &IntentFile, entity_context: Option<&str>) -> String {
-    let mut content = String::new();
-    let func_name = if let Some(entity) = entity_context {
+fn policy_view<'a>(policy: &'a crate::ast::Policy, entity_context: Option<&str>) -> PolicyView<'a> {
+    let function = if let Some(entity) = entity_context {
         format!("check_{}_{}", entity, policy.name)
     } else {
         format!("check_{}", policy.name)
     };
 
-    content.push_str(&format!("def {}(user: Any, resource: Any = None) -> None:\n", func_name));
-    content.push_str(&format!("    \"\"\"Policy: {}\"\"\"\n", policy.name));
-
-    // We assume 'user' maps to policy.subject
-    // And 'resource' maps to the entity if it's an entity-scoped policy
-
+    // 'user' maps to policy.subject, and 'resource' to the entity of an entity-scoped policy
     let target_var = if entity_context.is_some() { "resource" } else { "None" };
-    
-    // We reuse expression_to_python logic but need to adapt it. 
-    // Since we don't have access to api.rs's internal helper, we might need to duplicate or move `expression_to_python`.
-    // For now, I'll duplicate the simple version or ask to move it to a shared place.
-    // Let's implement a local version for now that uses `user` instead of `current_user`.
-    
-    let check_expr = expression_to_python(&policy.require, &policy.subject, target_var);
-    
-    content.push_str(&format!("    if not ({}):\n", check_expr));
-    content.push_str(&format!("        raise HTTPException(status_code=403, detail=\"Access denied by policy {}\")\n", policy.name));
 
-    content
+    PolicyView {
+        function,
+        name: &policy.name,
+        condition: expression_to_python(&policy.require, &policy.subject, target_var),
+    }
 }
 
 fn expression_to_python(expr: &crate::ast::Expression, subject: &str, target_var: &str) -> String {
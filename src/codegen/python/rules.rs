@@ -1,84 +1,58 @@
 // Intent Compiler - Business Rules Generator
 // Generates Python functions from rule definitions
 
-use std::fs;
-use std::path::Path;
 
-use crate::ast::{Expression, Consequence, LiteralValue, BinaryOperator, LogicalOperator, IntentFile, Rule};
+use serde::Serialize;
+
+use crate::ast::{Expression, Consequence, LiteralValue, BinaryOperator, LogicalOperator, Rule};
+use crate::ir::Program;
 use crate::codegen::GenerationResult;
+use crate::codegen::python::render_to;
+use crate::codegen::templates::Templates;
 use crate::error::CompileResult;
 
-/// Generate business rules
-pub fn generate_rules(ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult> {
-    let mut result = GenerationResult::new();
-    let mut content = String::new();
-
-    // Imports
-    content.push_str("# Intent Compiler Generated Business Rules\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-    content.push_str("from typing import Any, Dict\n");
-    content.push_str("from fastapi import HTTPException\n");
-    content.push_str("import logging\n\n");
-    content.push_str("logger = logging.getLogger(__name__)\n\n\n");
-
-    // Generate ValidationError class
-    content.push_str("class ValidationError(Exception):\n");
-    content.push_str("    \"\"\"Raised when a business rule validation fails\"\"\"\n");
-    content.push_str("    def __init__(self, message: str):\n");
-    content.push_str("        self.message = message\n");
-    content.push_str("        super().__init__(message)\n\n\n");
+/// A rule function
+#[derive(Serialize)]
+struct RuleView<'a> {
+    name: &'a str,
+    /// Python condition that triggers the consequence
+    condition: String,
+    /// Python statement run when the condition holds
+    consequence: String,
+    /// Entity whose validation runs the rule
+    entity: Option<String>,
+}
 
-    // Generate each rule
-    for rule in &ast.rules {
-        content.push_str(&generate_rule(rule)?);
-        content.push_str("\n\n");
-    }
+/// Context of `logic/rules.py`
+#[derive(Serialize)]
+struct RulesContext<'a> {
+    rules: Vec<RuleView<'a>>,
+}
 
-    // Generate a function to run all rules
-    content.push_str("def validate_all(entity_name: str, data: Dict[str, Any]) -> None:\n");
-    content.push_str("    \"\"\"Run all validation rules for an entity\"\"\"\n");
-    content.push_str("    rules = {\n");
-    for rule in &ast.rules {
-        // Extract entity name from the condition
-        let entity = extract_entity_from_expression(&rule.condition);
-        if let Some(entity_name) = entity {
-            content.push_str(&format!(
-                "        \"{}\": [{}],\n",
-                entity_name, rule.name
-            ));
-        }
-    }
-    content.push_str("    }\n");
-    content.push_str("    for rule_func in rules.get(entity_name, []):\n");
-    content.push_str("        rule_func(data)\n");
+/// Generate business rules
+pub fn generate_rules(program: &Program, templates: &Templates) -> CompileResult<GenerationResult> {
+    let mut result = GenerationResult::new();
 
-    let lines = content.lines().count();
-    let path = output_dir.join("logic/rules.py");
-    fs::write(&path, &content)?;
-    result.add_file("logic/rules.py", lines);
+    let context = RulesContext {
+        rules: program.rules.iter().map(rule_view).collect(),
+    };
+    render_to(templates, "logic/rules.py", &context, &mut result, "logic/rules.py")?;
 
     // Generate __init__.py
-    let init_content = "# Intent Compiler Generated Logic\nfrom .rules import *\n";
-    fs::write(output_dir.join("logic/__init__.py"), init_content)?;
+    render_to(templates, "logic/__init__.py", &(), &mut result, "logic/__init__.py")?;
 
     Ok(result)
 }
 
-/// Generate a single rule function
-fn generate_rule(rule: &Rule) -> CompileResult<String> {
-    let mut content = String::new();
-
-    content.push_str(&format!("def {}(data: Dict[str, Any]) -> None:\n", rule.name));
-    content.push_str(&format!("    \"\"\"Business rule: {}\"\"\"\n", rule.name));
-    
-    // Generate condition check
-    let condition = generate_expression(&rule.condition);
-    content.push_str(&format!("    if {}:\n", condition));
-    
-    // Generate consequence
-    content.push_str(&generate_consequence(&rule.consequence));
-
-    Ok(content)
+/// Build a single rule function
+fn rule_view(rule: &Rule) -> RuleView<'_> {
+    RuleView {
+        name: &rule.name,
+        condition: generate_expression(&rule.condition),
+        consequence: generate_consequence(&rule.consequence),
+        // Extract entity name from the condition
+        entity: extract_entity_from_expression(&rule.condition),
+    }
 }
 
 /// Generate Python expression from AST Expression
@@ -130,24 +104,14 @@ fn generate_expression(expr: &Expression) -> String {
 fn generate_consequence(consequence: &Consequence) -> String {
     match consequence {
         Consequence::Reject(message) => {
-            format!(
-                "        raise HTTPException(status_code=400, detail=\"{}\")\n",
-                message
-            )
+            format!("raise HTTPException(status_code=400, detail=\"{}\")", message)
         }
         Consequence::Log(message) => {
-            format!(
-                "        logger.info(\"{}\")\n",
-                message
-            )
+            format!("logger.info(\"{}\")", message)
         }
         Consequence::ActionCall { action, args } => {
             let args_str: Vec<String> = args.iter().map(generate_expression).collect();
-            format!(
-                "        {}({})\n",
-                action,
-                args_str.join(", ")
-            )
+            format!("{}({})", action, args_str.join(", "))
         }
     }
 }
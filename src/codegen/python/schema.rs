@@ -0,0 +1,602 @@
+// Intent Compiler - Database Schema Snapshots
+// The schema recorded in intent.lock and the Alembic operations between two snapshots
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{Decorator, Entity, Field, FieldType};
+use crate::ir::Program;
+
+/// Snapshot file written to the root of the output directory
+pub const LOCK_FILE: &str = "intent.lock";
+
+/// The database schema as of the latest generated migration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lock {
+    /// Alembic revision that brings a database to this schema
+    pub revision: String,
+    pub tables: Vec<TableSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnSchema>,
+    pub indexes: Vec<IndexSchema>,
+    /// Previous table name from `@renamed_from`; a hint for the next diff, not recorded
+    #[serde(skip)]
+    pub renamed_from: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    /// SQLAlchemy type expression, e.g. `sa.String(255)`
+    #[serde(rename = "type")]
+    pub sa_type: String,
+    pub nullable: bool,
+    pub primary_key: bool,
+    pub unique: bool,
+    /// Referenced column, e.g. `users.id`
+    pub foreign_key: Option<String>,
+    /// Previous column name from `@renamed_from`; a hint for the next diff, not recorded
+    #[serde(skip)]
+    pub renamed_from: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexSchema {
+    pub name: String,
+    pub columns: Vec<String>,
+}
+
+/// Build the schema of every entity's table
+pub fn tables(program: &Program) -> Vec<TableSchema> {
+    program.entities.iter().map(table).collect()
+}
+
+fn table(entity: &Entity) -> TableSchema {
+    let name = table_name(&entity.name);
+    let mut indexes: Vec<IndexSchema> = entity
+        .fields
+        .iter()
+        .filter(|field| field.decorators.contains(&Decorator::Index))
+        .map(|field| IndexSchema {
+            name: format!("ix_{}_{}", name, field.name),
+            columns: vec![field.name.clone()],
+        })
+        .collect();
+    indexes.extend(entity.indexes.iter().map(|columns| IndexSchema {
+        name: format!("ix_{}_{}", name, columns.join("_")),
+        columns: columns.clone(),
+    }));
+
+    TableSchema {
+        columns: entity.fields.iter().map(column).collect(),
+        indexes,
+        renamed_from: entity.renamed_from.as_deref().map(table_name),
+        name,
+    }
+}
+
+fn table_name(entity: &str) -> String {
+    entity.to_lowercase() + "s"
+}
+
+fn column(field: &Field) -> ColumnSchema {
+    let sa_type = match &field.field_type {
+        FieldType::Number => "sa.Float()".to_string(),
+        FieldType::Boolean => "sa.Boolean()".to_string(),
+        FieldType::DateTime => "sa.DateTime()".to_string(),
+        FieldType::Uuid => "sa.String(36)".to_string(),  // UUID as 36-char string
+        FieldType::Enum(values) => {
+            let vals = values.iter().map(|v| format!("'{}'", v)).collect::<Vec<_>>().join(", ");
+            format!("sa.Enum({})", vals)
+        }
+        FieldType::Array(_) | FieldType::List(_) => "sa.JSON()".to_string(),
+        FieldType::Optional(inner) => match inner.as_ref() {
+            FieldType::Number => "sa.Float()".to_string(),
+            FieldType::Boolean => "sa.Boolean()".to_string(),
+            FieldType::DateTime => "sa.DateTime()".to_string(),
+            FieldType::Uuid => "sa.String(36)".to_string(),
+            _ => "sa.String(255)".to_string(),
+        },
+        FieldType::String | FieldType::Email | FieldType::Reference(_) | FieldType::Ref(_) => "sa.String(255)".to_string(),
+    };
+    let foreign_key = match &field.field_type {
+        FieldType::Reference(name) | FieldType::Ref(name) => Some(format!("{}.id", table_name(name))),
+        _ => None,
+    };
+
+    ColumnSchema {
+        name: field.name.clone(),
+        sa_type,
+        nullable: matches!(field.field_type, FieldType::Optional(_)) || field.decorators.contains(&Decorator::Optional),
+        primary_key: field.decorators.contains(&Decorator::Primary),
+        unique: field.decorators.contains(&Decorator::Unique),
+        foreign_key,
+        renamed_from: field.decorators.iter().find_map(|d| match d {
+            Decorator::RenamedFrom(name) => Some(name.clone()),
+            _ => None,
+        }),
+    }
+}
+
+/// Name of the unique constraint on `table.column`. Constraints are named so later
+/// migrations can drop them; the ORM base names them the same way.
+fn unique_name(table: &str, column: &str) -> String {
+    format!("uq_{}_{}", table, column)
+}
+
+/// Name of the foreign key constraint on `table.column`
+fn foreign_key_name(table: &str, column: &str) -> String {
+    format!("fk_{}_{}", table, column)
+}
+
+impl ColumnSchema {
+    /// The `sa.Column(...)` expression creating the column of `table`, with its foreign key
+    /// but not its unique constraint, which is created on the table
+    fn expr(&self, table: &str) -> String {
+        let mut args = vec![format!("'{}'", self.name), self.sa_type.clone()];
+        if let Some(target) = &self.foreign_key {
+            args.push(format!("sa.ForeignKey('{}', name='{}')", target, foreign_key_name(table, &self.name)));
+        }
+        if self.primary_key {
+            args.push("primary_key=True".to_string());
+        }
+        if !self.nullable && !self.primary_key {
+            args.push("nullable=False".to_string());
+        }
+        format!("sa.Column({})", args.join(", "))
+    }
+}
+
+impl TableSchema {
+    /// Arguments of `op.create_table` after the table name: the columns, then the
+    /// unique constraints
+    pub fn create_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = self.columns.iter().map(|column| column.expr(&self.name)).collect();
+        args.extend(self.columns.iter().filter(|c| c.unique).map(|column| {
+            format!("sa.UniqueConstraint('{}', name='{}')", column.name, unique_name(&self.name, &column.name))
+        }));
+        args
+    }
+
+    /// `op.create_table(...)` followed by its `op.create_index(...)` calls
+    fn create_statements(&self) -> Vec<String> {
+        let mut create = format!("op.create_table(\n        '{}',\n", self.name);
+        for arg in self.create_args() {
+            create.push_str(&format!("        {},\n", arg));
+        }
+        create.push_str("    )");
+        let mut statements = vec![create];
+        statements.extend(self.indexes.iter().map(|index| {
+            format!("op.create_index('{}', '{}', {})", index.name, self.name, column_list(&index.columns))
+        }));
+        statements
+    }
+}
+
+/// A change to one table, in the order it is applied by `upgrade()`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableChange {
+    Create(TableSchema),
+    Drop(TableSchema),
+    Rename { from: String, to: String },
+    Alter { table: String, ops: Vec<ColumnOp> },
+}
+
+/// A change applied inside `op.batch_alter_table`, which also works on SQLite
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnOp {
+    AddColumn(ColumnSchema),
+    DropColumn(ColumnSchema),
+    AlterColumn { old: ColumnSchema, new: ColumnSchema },
+    CreateIndex(IndexSchema),
+    DropIndex(IndexSchema),
+    AddUnique(String),
+    DropUnique(String),
+    AddForeignKey { column: String, target: String },
+    DropForeignKey { column: String, target: String },
+}
+
+impl TableChange {
+    /// Python statements of `upgrade()`
+    pub fn upgrade(&self) -> Vec<String> {
+        match self {
+            TableChange::Create(table) => table.create_statements(),
+            TableChange::Drop(table) => vec![format!("op.drop_table('{}')", table.name)],
+            TableChange::Rename { from, to } => vec![format!("op.rename_table('{}', '{}')", from, to)],
+            TableChange::Alter { table, ops } => {
+                let mut batch = format!("with op.batch_alter_table('{}') as batch_op:", table);
+                for op in ops {
+                    batch.push_str(&format!("\n        {}", op.statement(table)));
+                }
+                vec![batch]
+            }
+        }
+    }
+
+    /// The change that undoes this one, for `downgrade()`
+    pub fn inverse(&self) -> TableChange {
+        match self {
+            TableChange::Create(table) => TableChange::Drop(table.clone()),
+            TableChange::Drop(table) => TableChange::Create(table.clone()),
+            TableChange::Rename { from, to } => TableChange::Rename { from: to.clone(), to: from.clone() },
+            TableChange::Alter { table, ops } => TableChange::Alter {
+                table: table.clone(),
+                ops: ops.iter().rev().map(ColumnOp::inverse).collect(),
+            },
+        }
+    }
+
+    /// Human-readable summary lines
+    pub fn describe(&self) -> Vec<String> {
+        match self {
+            TableChange::Create(table) => vec![format!("create table {}", table.name)],
+            TableChange::Drop(table) => vec![format!("drop table {}", table.name)],
+            TableChange::Rename { from, to } => vec![format!("rename table {} to {}", from, to)],
+            TableChange::Alter { table, ops } => ops.iter().map(|op| op.describe(table)).collect(),
+        }
+    }
+
+    /// Descriptions of the parts of `upgrade()` that lose data
+    pub fn data_loss(&self) -> Vec<String> {
+        match self {
+            TableChange::Drop(table) => vec![format!("drop table {}", table.name)],
+            TableChange::Alter { table, ops } => ops
+                .iter()
+                .filter_map(|op| match op {
+                    ColumnOp::DropColumn(column) => Some(format!("drop column {}.{}", table, column.name)),
+                    ColumnOp::AlterColumn { old, new } if narrows(&old.sa_type, &new.sa_type) => Some(format!(
+                        "narrow {}.{} from {} to {}",
+                        table, new.name, old.sa_type, new.sa_type
+                    )),
+                    _ => None,
+                })
+                .collect(),
+            TableChange::Create(_) | TableChange::Rename { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Whether values of SQLAlchemy type `old` may not fit `new`
+fn narrows(old: &str, new: &str) -> bool {
+    if old == new {
+        return false;
+    }
+    // Text is unbounded
+    let text_length = |ty: &str| match ty {
+        "sa.Text()" => Some(u32::MAX),
+        _ => ty.strip_prefix("sa.String(")?.strip_suffix(')')?.parse::<u32>().ok(),
+    };
+    let enum_values = |ty: &str| {
+        let values = ty.strip_prefix("sa.Enum(")?.strip_suffix(')')?;
+        Some(values.split(", ").map(str::to_string).collect::<Vec<_>>())
+    };
+    match (text_length(old), text_length(new), enum_values(old), enum_values(new)) {
+        (Some(old_length), Some(new_length), _, _) => new_length < old_length,
+        (_, _, Some(old_values), Some(new_values)) => old_values.iter().any(|v| !new_values.contains(v)),
+        // Numbers, booleans, datetimes and enum values have short text forms
+        (None, Some(_), old_values, _) => {
+            old_values.is_none() && !matches!(old, "sa.Float()" | "sa.Boolean()" | "sa.DateTime()")
+        }
+        _ => true,
+    }
+}
+
+impl ColumnOp {
+    fn statement(&self, table: &str) -> String {
+        match self {
+            // Its constraints are added by their own operations
+            ColumnOp::AddColumn(column) => {
+                let bare = ColumnSchema { unique: false, foreign_key: None, ..column.clone() };
+                format!("batch_op.add_column({})", bare.expr(table))
+            }
+            ColumnOp::DropColumn(column) => format!("batch_op.drop_column('{}')", column.name),
+            ColumnOp::AlterColumn { old, new } => {
+                let mut args = vec![format!("'{}'", old.name)];
+                if old.name != new.name {
+                    args.push(format!("new_column_name='{}'", new.name));
+                }
+                args.push(format!("existing_type={}", old.sa_type));
+                if old.sa_type != new.sa_type {
+                    args.push(format!("type_={}", new.sa_type));
+                }
+                if old.nullable == new.nullable {
+                    args.push(format!("existing_nullable={}", python_bool(old.nullable)));
+                } else {
+                    args.push(format!("nullable={}", python_bool(new.nullable)));
+                }
+                format!("batch_op.alter_column({})", args.join(", "))
+            }
+            ColumnOp::CreateIndex(index) => {
+                format!("batch_op.create_index('{}', {})", index.name, column_list(&index.columns))
+            }
+            ColumnOp::DropIndex(index) => format!("batch_op.drop_index('{}')", index.name),
+            ColumnOp::AddUnique(column) => {
+                format!("batch_op.create_unique_constraint('{}', ['{}'])", unique_name(table, column), column)
+            }
+            ColumnOp::DropUnique(column) => {
+                format!("batch_op.drop_constraint('{}', type_='unique')", unique_name(table, column))
+            }
+            ColumnOp::AddForeignKey { column, target } => {
+                let (target_table, target_column) = target.split_once('.').unwrap_or((target, "id"));
+                format!(
+                    "batch_op.create_foreign_key('{}', '{}', ['{}'], ['{}'])",
+                    foreign_key_name(table, column),
+                    target_table,
+                    column,
+                    target_column
+                )
+            }
+            ColumnOp::DropForeignKey { column, .. } => {
+                format!("batch_op.drop_constraint('{}', type_='foreignkey')", foreign_key_name(table, column))
+            }
+        }
+    }
+
+    fn inverse(&self) -> ColumnOp {
+        match self {
+            ColumnOp::AddColumn(column) => ColumnOp::DropColumn(column.clone()),
+            ColumnOp::DropColumn(column) => ColumnOp::AddColumn(column.clone()),
+            ColumnOp::AlterColumn { old, new } => ColumnOp::AlterColumn { old: new.clone(), new: old.clone() },
+            ColumnOp::CreateIndex(index) => ColumnOp::DropIndex(index.clone()),
+            ColumnOp::DropIndex(index) => ColumnOp::CreateIndex(index.clone()),
+            ColumnOp::AddUnique(column) => ColumnOp::DropUnique(column.clone()),
+            ColumnOp::DropUnique(column) => ColumnOp::AddUnique(column.clone()),
+            ColumnOp::AddForeignKey { column, target } => {
+                ColumnOp::DropForeignKey { column: column.clone(), target: target.clone() }
+            }
+            ColumnOp::DropForeignKey { column, target } => {
+                ColumnOp::AddForeignKey { column: column.clone(), target: target.clone() }
+            }
+        }
+    }
+
+    fn describe(&self, table: &str) -> String {
+        match self {
+            ColumnOp::AddColumn(column) => format!("add column {}.{}", table, column.name),
+            ColumnOp::DropColumn(column) => format!("drop column {}.{}", table, column.name),
+            ColumnOp::AlterColumn { old, new } if old.name != new.name => {
+                format!("rename column {}.{} to {}", table, old.name, new.name)
+            }
+            ColumnOp::AlterColumn { new, .. } => format!("alter column {}.{}", table, new.name),
+            ColumnOp::CreateIndex(index) => format!("create index {}", index.name),
+            ColumnOp::DropIndex(index) => format!("drop index {}", index.name),
+            ColumnOp::AddUnique(column) => format!("add unique constraint on {}.{}", table, column),
+            ColumnOp::DropUnique(column) => format!("drop unique constraint on {}.{}", table, column),
+            ColumnOp::AddForeignKey { column, target } => format!("add foreign key {}.{} -> {}", table, column, target),
+            ColumnOp::DropForeignKey { column, target } => format!("drop foreign key {}.{} -> {}", table, column, target),
+        }
+    }
+}
+
+/// Changes between two snapshots, plus the ones Alembic can't apply automatically
+#[derive(Debug, Default)]
+pub struct SchemaDiff {
+    pub changes: Vec<TableChange>,
+    pub unsupported: Vec<String>,
+}
+
+/// Compute the changes that migrate a database from `old` to `new`
+pub fn diff(old: &[TableSchema], new: &[TableSchema]) -> SchemaDiff {
+    let mut result = SchemaDiff::default();
+    let mut renamed = Vec::new();
+    for table in new {
+        let Some(previous) = previous_version(old, new, table, |t| &t.name, |t| &t.renamed_from) else {
+            result.changes.push(TableChange::Create(table.clone()));
+            continue;
+        };
+        if previous.name != table.name {
+            renamed.push(previous.name.as_str());
+            result.changes.push(TableChange::Rename { from: previous.name.clone(), to: table.name.clone() });
+        }
+        let ops = alter_ops(previous, table, &mut result.unsupported);
+        if !ops.is_empty() {
+            result.changes.push(TableChange::Alter { table: table.name.clone(), ops });
+        }
+    }
+    // Drop tables last, once nothing new references them
+    for table in old.iter().rev() {
+        if !new.iter().any(|t| t.name == table.name) && !renamed.contains(&table.name.as_str()) {
+            result.changes.push(TableChange::Drop(table.clone()));
+        }
+    }
+    result
+}
+
+/// The old version of `item`: the one with the same name, or the one it was renamed from as
+/// long as no new item still uses that name
+fn previous_version<'a, T>(
+    old: &'a [T],
+    new: &[T],
+    item: &T,
+    name: impl Fn(&T) -> &String,
+    renamed_from: impl Fn(&T) -> &Option<String>,
+) -> Option<&'a T> {
+    old.iter().find(|o| name(o) == name(item)).or_else(|| {
+        let previous = renamed_from(item).as_ref()?;
+        if new.iter().any(|n| name(n) == previous) {
+            return None;
+        }
+        old.iter().find(|o| name(o) == previous)
+    })
+}
+
+/// Operations on a table present in both snapshots: constraints and indexes that go away
+/// are dropped first, new ones are created last
+fn alter_ops(old: &TableSchema, new: &TableSchema, unsupported: &mut Vec<String>) -> Vec<ColumnOp> {
+    let mut drops = Vec::new();
+    let mut ops = Vec::new();
+    let mut creates = Vec::new();
+
+    for index in &old.indexes {
+        if !new.indexes.contains(index) {
+            drops.push(ColumnOp::DropIndex(index.clone()));
+        }
+    }
+    let mut renamed = Vec::new();
+    for column in &new.columns {
+        let Some(previous) = previous_version(&old.columns, &new.columns, column, |c| &c.name, |c| &c.renamed_from) else {
+            ops.push(ColumnOp::AddColumn(column.clone()));
+            if column.unique {
+                creates.push(ColumnOp::AddUnique(column.name.clone()));
+            }
+            if let Some(target) = &column.foreign_key {
+                creates.push(ColumnOp::AddForeignKey { column: column.name.clone(), target: target.clone() });
+            }
+            continue;
+        };
+        if previous.name != column.name {
+            renamed.push(previous.name.as_str());
+        }
+        if previous.primary_key != column.primary_key {
+            unsupported.push(format!("primary key change of {}.{}", new.name, column.name));
+        }
+        if previous.name != column.name || previous.sa_type != column.sa_type || previous.nullable != column.nullable {
+            ops.push(ColumnOp::AlterColumn { old: previous.clone(), new: column.clone() });
+        }
+        if previous.unique && !column.unique {
+            drops.push(ColumnOp::DropUnique(column.name.clone()));
+        } else if !previous.unique && column.unique {
+            creates.push(ColumnOp::AddUnique(column.name.clone()));
+        }
+        if previous.foreign_key != column.foreign_key {
+            if let Some(target) = &previous.foreign_key {
+                drops.push(ColumnOp::DropForeignKey { column: column.name.clone(), target: target.clone() });
+            }
+            if let Some(target) = &column.foreign_key {
+                creates.push(ColumnOp::AddForeignKey { column: column.name.clone(), target: target.clone() });
+            }
+        }
+    }
+    for column in &old.columns {
+        if !new.columns.iter().any(|c| c.name == column.name) && !renamed.contains(&column.name.as_str()) {
+            // Dropped explicitly so that downgrade() restores them
+            if column.unique {
+                drops.push(ColumnOp::DropUnique(column.name.clone()));
+            }
+            if let Some(target) = &column.foreign_key {
+                drops.push(ColumnOp::DropForeignKey { column: column.name.clone(), target: target.clone() });
+            }
+            ops.push(ColumnOp::DropColumn(column.clone()));
+        }
+    }
+    for index in &new.indexes {
+        if !old.indexes.contains(index) {
+            creates.push(ColumnOp::CreateIndex(index.clone()));
+        }
+    }
+
+    drops.into_iter().chain(ops).chain(creates).collect()
+}
+
+/// A Python list literal of column names
+fn column_list(columns: &[String]) -> String {
+    format!("['{}']", columns.join("', '"))
+}
+
+fn python_bool(value: bool) -> &'static str {
+    if value { "True" } else { "False" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+
+    fn schema(source: &str) -> Vec<TableSchema> {
+        tables(&Program::lower(&parse_intent(source).unwrap()))
+    }
+
+    #[test]
+    fn test_column_changes_are_batched_and_inverted() {
+        let old = schema("entity User:\n    id: uuid @primary\n    name: string\n    nickname: string\n");
+        let new = schema("entity User:\n    id: uuid @primary\n    name: string @optional @index\n    age: number\n");
+
+        let diff = diff(&old, &new);
+        assert!(diff.unsupported.is_empty());
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].upgrade(), vec![
+            "with op.batch_alter_table('users') as batch_op:\n        \
+             batch_op.alter_column('name', existing_type=sa.String(255), nullable=True)\n        \
+             batch_op.add_column(sa.Column('age', sa.Float(), nullable=False))\n        \
+             batch_op.drop_column('nickname')\n        \
+             batch_op.create_index('ix_users_name', ['name'])"
+        ]);
+        assert_eq!(diff.changes[0].inverse().upgrade(), vec![
+            "with op.batch_alter_table('users') as batch_op:\n        \
+             batch_op.drop_index('ix_users_name')\n        \
+             batch_op.add_column(sa.Column('nickname', sa.String(255), nullable=False))\n        \
+             batch_op.drop_column('age')\n        \
+             batch_op.alter_column('name', existing_type=sa.String(255), nullable=False)"
+        ]);
+    }
+
+    #[test]
+    fn test_tables_enums_and_foreign_keys() {
+        let old = schema("entity Team:\n    id: uuid @primary\n\nentity User:\n    id: uuid @primary\n    role: admin | member\n");
+        let new = schema("entity Org:\n    id: uuid @primary\n\nentity User:\n    id: uuid @primary\n    role: admin | member | guest\n    org: ref<Org>\n");
+
+        let diff = diff(&old, &new);
+        let summary: Vec<String> = diff.changes.iter().flat_map(TableChange::describe).collect();
+        assert_eq!(summary, vec![
+            "create table orgs",
+            "alter column users.role",
+            "add column users.org",
+            "add foreign key users.org -> orgs.id",
+            "drop table teams",
+        ]);
+        assert!(diff.changes[1].upgrade()[0].contains(
+            "type_=sa.Enum('admin', 'member', 'guest')"
+        ));
+        assert!(diff.changes[1].upgrade()[0].contains(
+            "batch_op.add_column(sa.Column('org', sa.String(255), nullable=False))\n        \
+             batch_op.create_foreign_key('fk_users_org', 'orgs', ['org'], ['id'])"
+        ));
+        assert_eq!(diff.changes[2].inverse(), TableChange::Create(old[0].clone()));
+    }
+
+    #[test]
+    fn test_renames_keep_data() {
+        let old = schema("entity Person:\n    id: uuid @primary\n    name: string\n");
+        let new = schema("@renamed_from(\"Person\")\nentity User:\n    id: uuid @primary\n    full_name: string @renamed_from(\"name\")\n");
+
+        let diff = diff(&old, &new);
+        let summary: Vec<String> = diff.changes.iter().flat_map(TableChange::describe).collect();
+        assert_eq!(summary, vec!["rename table persons to users", "rename column users.name to full_name"]);
+        assert!(diff.changes[1].upgrade()[0].contains(
+            "batch_op.alter_column('name', new_column_name='full_name', existing_type=sa.String(255), existing_nullable=False)"
+        ));
+        assert!(diff.changes.iter().all(|change| change.data_loss().is_empty()));
+    }
+
+    #[test]
+    fn test_drops_and_narrowing_lose_data() {
+        let old = schema("entity Team:\n    id: uuid @primary\n\nentity User:\n    id: uuid @primary\n    role: admin | member\n    score: string\n    name: string\n    joined: datetime\n");
+        let new = schema("entity User:\n    id: uuid @primary\n    role: admin | guest\n    score: number\n    joined: string\n");
+
+        let loss: Vec<String> = diff(&old, &new).changes.iter().flat_map(TableChange::data_loss).collect();
+        assert_eq!(loss, vec![
+            "narrow users.role from sa.Enum('admin', 'member') to sa.Enum('admin', 'guest')",
+            "narrow users.score from sa.String(255) to sa.Float()",
+            "drop column users.name",
+            "drop table teams",
+        ]);
+        assert!(!narrows("sa.Enum('a')", "sa.Enum('a', 'b')"));
+        assert!(!narrows("sa.String(36)", "sa.String(255)"));
+        assert!(!narrows("sa.Enum('a')", "sa.String(255)"));
+        for old in ["sa.Float()", "sa.Boolean()", "sa.DateTime()", "sa.String(36)"] {
+            assert!(!narrows(old, "sa.String(255)"), "{}", old);
+            assert!(!narrows(old, "sa.Text()"), "{}", old);
+        }
+        assert!(narrows("sa.Text()", "sa.String(255)"));
+        assert!(narrows("sa.JSON()", "sa.String(255)"));
+    }
+
+    #[test]
+    fn test_unchanged_schema_has_no_changes() {
+        let tables = schema("entity User:\n    id: uuid @primary\n    email: email @unique\n");
+        assert!(diff(&tables, &tables).changes.is_empty());
+    }
+}
@@ -1,210 +1,134 @@
 // Intent Compiler - Python Service Generator
 // Generates service classes with business logic
 
-use crate::ast::{Action, Decorator, DeriveValue, IntentFile, MapTransform};
+use serde::Serialize;
+
+use crate::ast::{Decorator, DeriveValue, MapTransform};
+use crate::ir::{ActionIr, ActionKind, Cardinality, Program, ResultSource};
 use crate::codegen::GenerationResult;
+use crate::codegen::python::{EntityList, EntityNames, render_once, render_to};
+use crate::codegen::templates::Templates;
 use crate::error::CompileResult;
-use std::fs;
 use std::path::Path;
 
-pub fn generate_services(ast: &IntentFile, output_dir: &Path) -> CompileResult<GenerationResult> {
+/// Context of `services/service.py`
+#[derive(Serialize)]
+struct ServiceContext<'a> {
+    #[serde(flatten)]
+    entity: EntityNames<'a>,
+    /// Other entities whose ORM models the action methods query
+    imported_models: Vec<&'a str>,
+    /// Methods implementing the entity's actions
+    methods: String,
+    /// Whether this is the generated `<Entity>ServiceBase` of a generation-gap layout
+    base: bool,
+}
+
+pub fn generate_services(program: &Program, templates: &Templates, output_dir: &Path, generation_gap: bool) -> CompileResult<GenerationResult> {
     let mut result = GenerationResult::new();
 
-    // Create services directory
-    let services_dir = output_dir.join("services");
-    fs::create_dir_all(&services_dir)?;
+    // Generated base classes live in services/base
+    if generation_gap {
+        render_to(templates, "__init__.py", &(), &mut result, "services/base/__init__.py")?;
+    }
 
     // Generate entity-specific services
-    for entity in &ast.entities {
-        let content = generate_entity_service(entity, ast);
-        let filename = format!("{}_service.py", entity.name.to_lowercase());
-        let path = services_dir.join(&filename);
-        fs::write(&path, &content)?;
-        result.add_file(format!("services/{}", filename), content.lines().count());
+    for entity in &program.entities {
+        let name = &entity.name;
+        let context = ServiceContext {
+            entity: EntityNames::new(name),
+            imported_models: imported_models(name, program),
+            methods: program
+                .actions_for(name)
+                .map(|action| generate_action_method(action, name, program))
+                .collect(),
+            base: generation_gap,
+        };
+        let path = format!("services/{}_service.py", name.to_lowercase());
+        if generation_gap {
+            let base_path = format!("services/base/{}_service.py", name.to_lowercase());
+            render_to(templates, "services/service.py", &context, &mut result, base_path)?;
+            // The subclass is the user's: only create it
+            render_once(templates, "services/extension.py", &context.entity, output_dir, &mut result, path)?;
+        } else {
+            render_to(templates, "services/service.py", &context, &mut result, path)?;
+        }
     }
 
     // Generate __init__.py
-    let init_content = generate_services_init(ast);
-    let init_path = services_dir.join("__init__.py");
-    fs::write(&init_path, &init_content)?;
-    result.add_file("services/__init__.py", init_content.lines().count());
+    render_to(templates, "services/__init__.py", &EntityList::new(program), &mut result, "services/__init__.py")?;
 
     Ok(result)
 }
 
-fn generate_entity_service(entity: &crate::ast::Entity, ast: &IntentFile) -> String {
-    let name = &entity.name;
-    let name_lower = name.to_lowercase();
-    
-    let mut content = String::new();
-    
-    // Header
-    content.push_str("# Intent Compiler Generated Service\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-    content.push_str("from typing import Optional\n");
-    content.push_str("from sqlalchemy.orm import Session\n");
-    content.push_str("from fastapi import HTTPException\n\n");
-    for entity in &ast.entities {
-        if entity.name != *name {
-             // Check if this entity is used in any process step
-             let is_used = ast.actions.iter()
-                .filter(|a| a.output.as_ref().map(|o| o.entity == *name).unwrap_or(false))
-                .any(|a| a.process.as_ref().map(|p| p.steps.iter().any(|s| match s {
+/// Other entities touched by the process of an action that outputs `name`
+fn imported_models<'a>(name: &str, program: &'a Program) -> Vec<&'a str> {
+    program
+        .entities
+        .iter()
+        .filter(|entity| entity.name != name)
+        .filter(|entity| {
+            program.actions.iter()
+                .filter(|a| a.def.output.as_ref().map(|o| o.entity == name).unwrap_or(false))
+                .any(|a| a.def.process.as_ref().map(|p| p.steps.iter().any(|s| match s {
                     crate::ast::ProcessStep::Mutate(m) => m.entity == entity.name,
                     crate::ast::ProcessStep::Delete(d) => d.entity == entity.name,
                     crate::ast::ProcessStep::Derive(d) => matches!(&d.value, DeriveValue::Select { entity: e, .. } if e == &entity.name),
-                })).unwrap_or(false));
-             
-             if is_used {
-                 content.push_str(&format!("from db.models import {}Model\n", entity.name));
-             }
-        }
-    }
-    content.push_str(&format!("from db.models import {}Model\n", name));
-    content.push_str(&format!("from repositories.{}_repository import {}_repository\n", name_lower, name_lower));
-    content.push_str("from core.security import get_password_hash, verify_password, create_access_token\n\n\n");
-    
-    // Service class
-    content.push_str(&format!("class {}Service:\n", name));
-    content.push_str(&format!("    \"\"\"Service for {} entity with business logic\"\"\"\n\n", name));
-    content.push_str(&format!("    repo = {}_repository\n\n", name_lower));
-    
-    // Generate CRUD methods
-    content.push_str(&generate_crud_methods(name, &name_lower));
-    
-    // Generate action-specific methods for this entity
-    for action in &ast.actions {
-        if let Some(target_entity) = action.infer_entity(ast) {
-            if target_entity == *name {
-                content.push_str(&generate_action_method(action, name, &name_lower, ast));
-            }
-        }
-    }
-    
-    // Singleton instance
-    content.push_str(&format!("\n# Singleton instance\n"));
-    content.push_str(&format!("{}_service = {}Service()\n", name_lower, name));
-    
-    content
-}
-
-fn generate_crud_methods(name: &str, _name_lower: &str) -> String {
-    let mut content = String::new();
-    
-    // Get all
-    content.push_str(&format!("    def get_all(self, db: Session, skip: int = 0, limit: int = 100) -> list[{}Model]:\n", name));
-    content.push_str("        \"\"\"Get all records with pagination\"\"\"\n");
-    content.push_str("        return self.repo.get_all(db, skip=skip, limit=limit)\n\n");
-    
-    // Get by ID
-    content.push_str(&format!("    def get_by_id(self, db: Session, id: str) -> Optional[{}Model]:\n", name));
-    content.push_str("        \"\"\"Get a record by ID\"\"\"\n");
-    content.push_str("        return self.repo.get_by_id(db, id)\n\n");
-    
-    
-    content
+                })).unwrap_or(false))
+        })
+        .map(|entity| entity.name.as_str())
+        .collect()
 }
 
-fn generate_action_method(action: &Action, entity_name: &str, _entity_lower: &str, ast: &IntentFile) -> String {
+fn generate_action_method(action: &ActionIr, entity_name: &str, program: &Program) -> String {
     let mut content = String::new();
     let action_name = &action.name;
+    let def = &action.def;
     
     // Build parameters (match controllers.rs)
-    let mut params = Vec::new();
-
-    // Get API decorator for path params
-    let default_path = "/".to_string();
-    let (_, path) = action.decorators.iter().find_map(|d| {
-        if let Decorator::Api { method, path } = d {
-            Some((method, path))
-        } else {
-            None
-        }
-    }).unwrap_or((&crate::ast::HttpMethod::Get, &default_path));
-
-    for segment in path.split('/') {
-        if segment.starts_with('{') && segment.ends_with('}') {
-            let param_name = &segment[1..segment.len()-1];
-            params.push(param_name.to_string());
-        }
-    }
+    let mut params: Vec<String> = action.path_params().to_vec();
 
     // Add data if applicable
-    let has_api = action.decorators.iter().any(|d| matches!(d, Decorator::Api { .. }));
-    let method = action.decorators.iter().find_map(|d| {
-        if let Decorator::Api { method, .. } = d { Some(method) } else { None }
-    }).unwrap_or(&crate::ast::HttpMethod::Get);
-
-    if has_api && matches!(method, crate::ast::HttpMethod::Post | crate::ast::HttpMethod::Put | crate::ast::HttpMethod::Patch) {
-        params.push("data".to_string());
-    } else if !has_api {
-        if let Some(input) = &action.input {
-            for field in &input.fields {
-                params.push(field.name.clone());
+    match &action.route {
+        Some(route) if route.has_body() => params.push("data".to_string()),
+        Some(_) => {}
+        None => {
+            if let Some(input) = &def.input {
+                params.extend(input.fields.iter().map(|f| f.name.clone()));
             }
         }
     }
 
     params.push("db: Session".to_string());
 
-    // Check for implicit current_user usage in process
-    let uses_current_user = action.process.as_ref().map(|p| {
-        p.steps.iter().any(|s| match s {
-             crate::ast::ProcessStep::Derive(d) => match &d.value {
-                 DeriveValue::FieldAccess { path } => path[0] == "current_user",
-                 DeriveValue::Identifier(id) => id.starts_with("current_user."),
-                 _ => false
-             },
-             _ => false
-        })
-    }).unwrap_or(false);
-
-    let requires_auth = action.decorators.iter().any(|d| matches!(d, Decorator::Auth { .. }));
     // Add current_user if explicit auth or implicit usage
-    if requires_auth || uses_current_user {
+    if action.requires_auth || action.uses_current_user {
         params.push("current_user".to_string());
     }
 
     let params_str = params.join(", ");
     let has_data = params.contains(&"data".to_string());
     let mut derived_vars = std::collections::HashSet::new();
-
-    // Check if this is a process-based action
-    let has_process = action.process.is_some();    
-    // Check if this is a simple select-based action (no mutations/deletions)
-    let has_find = action.process.as_ref().map(|p| {
-        let has_select = p.steps.iter().any(|step| {
-            if let crate::ast::ProcessStep::Derive(d) = step {
-                matches!(&d.value, DeriveValue::Select { .. })
-            } else {
-                false
-            }
-        });
-        let has_mutations = p.steps.iter().any(|step| {
-            !matches!(step, crate::ast::ProcessStep::Derive(_))
-        });
-        has_select && !has_mutations
-    }).unwrap_or(false);
+    let returns_list = action.cardinality == Cardinality::Many;
+    let steps = def.process.as_ref().map(|p| p.steps.as_slice()).unwrap_or_default();
     
     // Check for password hashing (signup-style)
-    let has_hash = action.input.as_ref().map(|i| {
+    let has_hash = def.input.as_ref().map(|i| {
         i.fields.iter().any(|f| {
             f.decorators.iter().any(|d| matches!(d, Decorator::Map { transform: MapTransform::Hash, .. }))
         })
     }).unwrap_or(false);
     
-    let returns_list = matches!(method, crate::ast::HttpMethod::Get) && !path.contains('{') && !action_name.starts_with("get_");
-    
-    if has_find {
-        // Find/Select-style method (e.g. Login or List by filter)
-        let return_type = if returns_list { "list[dict]" } else { "dict" };
-        content.push_str(&format!("    def {}(self, {}) -> {}:\n", action_name, params_str, return_type));
-        content.push_str(&format!("        \"\"\"Business logic for {}\"\"\"\n", action_name));
-        
-        // Process derives in order
-        if let Some(process) = &action.process {
-            for step in &process.steps {
-                if let crate::ast::ProcessStep::Derive(derive) = step {
+    match action.kind {
+        ActionKind::Query => {
+            // Find/Select-style method (e.g. Login or List by filter)
+            let return_type = if returns_list { "list[dict]" } else { "dict" };
+            content.push_str(&format!("    def {}(self, {}) -> {}:\n", action_name, params_str, return_type));
+            content.push_str(&format!("        \"\"\"Business logic for {}\"\"\"\n", action_name));
+            
+            // Process derives in order
+            for step in steps {
+                let crate::ast::ProcessStep::Derive(derive) = step else { continue };
                 match &derive.value {
                     DeriveValue::Select { entity, predicate } => {
                         let py_code = if returns_list {
@@ -232,109 +156,57 @@ fn generate_action_method(action: &Action, entity_name: &str, _entity_lower: &st
                     }
                     _ => {}
                 }
-                }
             }
-        }
-        
-        // Return output
-        if returns_list {
-            // Find the plural variable from the select step
-            let found_var = action.process.as_ref()
-                .and_then(|p| p.steps.iter().find_map(|step| {
-                    if let crate::ast::ProcessStep::Derive(d) = step {
-                        if matches!(&d.value, DeriveValue::Select { .. }) {
-                            Some(d.name.clone())
-                        } else { None }
-                    } else { None }
-                }))
-                .unwrap_or_else(|| "results".to_string());
-
-            content.push_str(&format!("        return [\n            {{\n"));
-            if let Some(output) = &action.output {
-                for field in &output.fields {
-                    // For lists, we assume fields belong to the items in found_var
-                    content.push_str(&format!("                \"{}\": item.{},\n", field, field));
+            
+            // Return output, read from the selected record
+            let found_var = match &action.result {
+                ResultSource::Variable(var) => var.as_str(),
+                _ => "None",
+            };
+            if returns_list {
+                content.push_str("        return [\n            {\n");
+                for field in &action.output {
+                    content.push_str(&format!("                \"{}\": item.{},\n", field.name, field.name));
                 }
-            }
-            content.push_str(&format!("            }} for item in {}\n        ]\n\n", found_var));
-        } else {
-            content.push_str("        return {\n");
-            if let Some(output) = &action.output {
-                for field in &output.fields {
-                    let is_derived = action.process.as_ref().map(|p| {
-                        p.steps.iter().any(|step| {
-                            match step {
-                                crate::ast::ProcessStep::Derive(d) => d.name == *field,
-                                _ => false
-                            }
-                        })
-                    }).unwrap_or(false);
-                    
-                    if is_derived {
-                        content.push_str(&format!("            \"{}\": {},\n", field, field));
+                content.push_str(&format!("            }} for item in {}\n        ]\n\n", found_var));
+            } else {
+                content.push_str("        return {\n");
+                for field in &action.output {
+                    if field.is_variable {
+                        content.push_str(&format!("            \"{}\": {},\n", field.name, field.name));
                     } else {
-                        // Assume it's from the found entity (first derive with select)
-                        let found_var = action.process.as_ref()
-                            .and_then(|p| p.steps.iter().find_map(|step| {
-                                if let crate::ast::ProcessStep::Derive(d) = step {
-                                    if matches!(&d.value, DeriveValue::Select { .. }) {
-                                        Some(d.name.clone())
-                                    } else { None }
-                                } else { None }
-                            }))
-                            .unwrap_or_else(|| "user".to_string());
-                        content.push_str(&format!("            \"{}\": {}.{},\n", field, found_var, field));
+                        content.push_str(&format!("            \"{}\": {}.{},\n", field.name, found_var, field.name));
                     }
                 }
+                
+                // Special handling for login response
+                if action.name == "login" {
+                    content.push_str("            \"access_token\": token,\n");
+                    content.push_str("            \"token_type\": \"bearer\",\n");
+                }
+                
+                content.push_str("        }\n\n");
             }
-            
-            // Special handling for login response
-            if action.name == "login" {
-                content.push_str("            \"access_token\": token,\n");
-                content.push_str("            \"token_type\": \"bearer\",\n");
-            }
-            
-            content.push_str("        }\n\n");
         }
-    } else if has_process {
-        // Generic Process-based method
-        content.push_str(&format!("    def {}(self, {}) -> dict:\n", action_name, params_str));
-        content.push_str(&format!("        \"\"\"Process execution for {}\"\"\"\n", action_name));
+        ActionKind::Command => {
+            // Generic Process-based method
+            content.push_str(&format!("    def {}(self, {}) -> dict:\n", action_name, params_str));
+            content.push_str(&format!("        \"\"\"Process execution for {}\"\"\"\n", action_name));
 
-        if let Some(process) = &action.process {
-            for step in &process.steps {
+            for step in steps {
                 match step {
                     crate::ast::ProcessStep::Derive(derive) => {
-                         match &derive.value {
-                            DeriveValue::Select { entity, predicate } => {
-                                let py_code = select_to_python(entity, predicate, has_data, &derived_vars);
-                                content.push_str(&format!("        {} = {}\n", derive.name, py_code));
-                            }
-                            DeriveValue::Compute { function, args } => {
-                                let py_code = compute_to_python(function, args, has_data, &derived_vars);
-                                content.push_str(&format!("        {} = {}\n", derive.name, py_code));
-                            }
+                         let py_code = match &derive.value {
+                            DeriveValue::Select { entity, predicate } => select_to_python(entity, predicate, has_data, &derived_vars),
+                            DeriveValue::Compute { function, args } => compute_to_python(function, args, has_data, &derived_vars),
                             DeriveValue::SystemCall { namespace, capability, args } => {
-                                let py_code = system_call_to_python(namespace, capability, args, has_data, &derived_vars);
-                                content.push_str(&format!("        {} = {}\n", derive.name, py_code));
-                            }
-                            DeriveValue::Identifier(id) => {
-                                 let val = resolve_identifier_python(id, has_data, &derived_vars);
-                                 content.push_str(&format!("        {} = {}\n", derive.name, val));
-                            }
-                            DeriveValue::FieldAccess { path } => {
-                                 let val = resolve_field_access_python(path, has_data, &derived_vars);
-                                 content.push_str(&format!("        {} = {}\n", derive.name, val));
-                            }
-                            DeriveValue::Literal(lit) => {
-                                 let val = match lit {
-                                     crate::ast::LiteralValue::String(s) => format!("\"{}\"", s),
-                                     crate::ast::LiteralValue::Number(n) => n.to_string(),
-                                     crate::ast::LiteralValue::Boolean(b) => if *b { "True".to_string() } else { "False".to_string() },
-                                 };
-                                 content.push_str(&format!("        {} = {}\n", derive.name, val));
+                                system_call_to_python(namespace, capability, args, has_data, &derived_vars)
                             }
-                         }
+                            DeriveValue::Identifier(id) => resolve_identifier_python(id, has_data, &derived_vars),
+                            DeriveValue::FieldAccess { path } => resolve_field_access_python(path, has_data, &derived_vars),
+                            DeriveValue::Literal(lit) => literal_to_python(lit),
+                         };
+                         content.push_str(&format!("        {} = {}\n", derive.name, py_code));
                          derived_vars.insert(derive.name.clone());
                     }
                     crate::ast::ProcessStep::Mutate(mutate) => {
@@ -367,175 +239,110 @@ fn generate_action_method(action: &Action, entity_name: &str, _entity_lower: &st
                 }
             }
             content.push_str("        db.commit()\n");
-        }
 
-        // Return output
-        let mut target_var = None;
-        if let Some(_output) = &action.output {
-             target_var = action.process.as_ref()
-                .and_then(|p| p.steps.iter().filter_map(|step| {
-                    match step {
-                        crate::ast::ProcessStep::Derive(d) => Some(d.name.clone()),
-                        crate::ast::ProcessStep::Mutate(m) => {
-                             if m.predicate.is_none() { Some(format!("new_{}", m.entity.to_lowercase())) }
-                             else { Some("resource".to_string()) }
-                        },
-                        _ => None
-                    }
-                }).last());
+            // Return output, read from the record the process produced
+            let target_var = match &action.result {
+                ResultSource::Variable(var) => Some(var.clone()),
+                ResultSource::Created(entity) => Some(format!("new_{}", entity.to_lowercase())),
+                ResultSource::Updated { key, .. } => {
+                    let id_expr = match key {
+                        Some(crate::ast::FieldReference::InputField(name)) => resolve_identifier_python(name, has_data, &derived_vars),
+                        Some(crate::ast::FieldReference::DerivedField { name, field }) => format!("{}.{}", name, field),
+                        Some(crate::ast::FieldReference::Literal(lit)) => literal_to_python(lit),
+                        None if params.contains(&"id".to_string()) => "id".to_string(),
+                        None => "data.id".to_string(),
+                    };
+                    content.push_str(&format!("        resource = self.repo.get_by_id(db, {})\n", id_expr));
+                    Some("resource".to_string())
+                }
+                ResultSource::CurrentUser => Some("current_user".to_string()),
+                _ => None,
+            };
 
-             if target_var == Some("resource".to_string()) {
-                 // Try to find the ID from the mutation predicate
-                 let mut id_expr = if params.contains(&"id".to_string()) { "id".to_string() } else { "data.id".to_string() };
-                 
-                 if let Some(process) = &action.process {
-                     for step in &process.steps {
-                         if let crate::ast::ProcessStep::Mutate(m) = step {
-                             if let Some(pred) = &m.predicate {
-                                 // simple heuristic to get id from predicate
-                                 let is_id_check = match &pred.field {
-                                     crate::ast::FieldReference::InputField(n) => n == "id",
-                                     crate::ast::FieldReference::DerivedField { field, .. } => field == "id",
-                                     _ => false
-                                 };
-                                 
-                                 if is_id_check {
-                                     match &pred.value {
-                                         crate::ast::FieldReference::InputField(name) => {
-                                             id_expr = resolve_identifier_python(name, has_data, &derived_vars);
-                                         },
-                                         crate::ast::FieldReference::DerivedField { name, field } => {
-                                             id_expr = format!("{}.{}", name, field);
-                                         },
-                                          crate::ast::FieldReference::Literal(lit) => {
-                                             match lit {
-                                                 crate::ast::LiteralValue::String(s) => id_expr = format!("\"{}\"", s),
-                                                 _ => {}
-                                             }
-                                         },
-                                     }
-                                 }
-                             }
+            content.push_str("        return {\n");
+            if def.output.is_some() {
+                 if let Some(var) = target_var {
+                     for field in &action.output {
+                         if field.is_variable {
+                             content.push_str(&format!("            \"{}\": {},\n", field.name, field.name));
+                         } else {
+                             content.push_str(&format!("            \"{}\": {}.{},\n", field.name, var, field.name));
                          }
                      }
+                 } else {
+                     content.push_str("            \"id\": \"done\",\n");
                  }
-                 content.push_str(&format!("        resource = self.repo.get_by_id(db, {})\n", id_expr));
-             }
-        }
-
-        content.push_str("        return {\n");
-        if let Some(output) = &action.output {
-             if let Some(var) = target_var {
-                 for field in &output.fields {
-                     if derived_vars.contains(field) {
-                         content.push_str(&format!("            \"{}\": {},\n", field, field));
-                     } else {
-                         content.push_str(&format!("            \"{}\": {}.{},\n", field, var, field));
-                     }
-                 }
-             } else if action.decorators.iter().any(|d| matches!(d, Decorator::Auth { .. })) && output.entity == "User" {
-                 // Fallback for self-updates like /profile
-                 for field in &output.fields {
-                     if derived_vars.contains(field) {
-                         content.push_str(&format!("            \"{}\": {},\n", field, field));
-                     } else {
-                         content.push_str(&format!("            \"{}\": current_user.{},\n", field, field));
-                     }
-                 }
-             } else {
-                 content.push_str("            \"id\": \"done\",\n");
-             }
+            }
+            content.push_str("        }\n\n");
         }
-        content.push_str("        }\n\n");
-    } else if has_hash {
-        // Signup-style method
-        content.push_str(&format!("    def {}(self, {}) -> {}Model:\n", action_name, params_str, entity_name));
-        content.push_str(&format!("        \"\"\"Business logic for {}\"\"\"\n", action_name));
-        content.push_str("        data_dict = data.model_dump()\n");
-        
-        if let Some(input) = &action.input {
-            for param in &input.fields {
-                for dec in &param.decorators {
-                    if let Decorator::Map { target, transform } = dec {
-                        if matches!(transform, MapTransform::Hash) {
+        ActionKind::Crud if has_hash => {
+            // Signup-style method
+            content.push_str(&format!("    def {}(self, {}) -> {}Model:\n", action_name, params_str, entity_name));
+            content.push_str(&format!("        \"\"\"Business logic for {}\"\"\"\n", action_name));
+            content.push_str("        data_dict = data.model_dump()\n");
+            
+            if let Some(input) = &def.input {
+                for param in &input.fields {
+                    for dec in &param.decorators {
+                        if let Decorator::Map { target, transform } = dec
+                            && matches!(transform, MapTransform::Hash) {
                             content.push_str(&format!("        data_dict['{}'] = get_password_hash(data_dict.pop('{}'))\n", target, param.name));
                         }
                     }
                 }
             }
+            
+            content.push_str("        return self.repo.create(db, data_dict)\n\n");
         }
-        
-        content.push_str("        return self.repo.create(db, data_dict)\n\n");
-    } else {
-        // Generic action (like create_product or list_products)
-        // Determine return type
-        let returns_list = if matches!(method, crate::ast::HttpMethod::Get) && !path.contains('{') {
-            true
-        } else {
-            false
-        };
-
-        if returns_list {
-            content.push_str(&format!("    def {}(self, {}) -> list[{}Model]:\n", action_name, params_str, entity_name));
-        } else {
-            content.push_str(&format!("    def {}(self, {}) -> {}Model:\n", action_name, params_str, entity_name));
-        }
-        content.push_str(&format!("        \"\"\"Business logic for {}\"\"\"\n", action_name));
+        ActionKind::Crud => {
+            // Generic action (like create_product or list_products)
+            if returns_list {
+                content.push_str(&format!("    def {}(self, {}) -> list[{}Model]:\n", action_name, params_str, entity_name));
+            } else {
+                content.push_str(&format!("    def {}(self, {}) -> {}Model:\n", action_name, params_str, entity_name));
+            }
+            content.push_str(&format!("        \"\"\"Business logic for {}\"\"\"\n", action_name));
 
-        if matches!(method, crate::ast::HttpMethod::Post) {
-             content.push_str("        data_dict = data.model_dump()\n");
-             
-             // Check if entity has user_id or similar and set it from current_user
-             let target_entity = ast.find_entity(entity_name);
-             if let Some(entity) = target_entity {
-                 for field in &entity.fields {
-                     if (field.name == "user_id" || field.name == "owner_id" || field.name == format!("{}_id", entity.name.to_lowercase()))
-                        && !action.input.as_ref().map(|i| i.fields.iter().any(|f| f.name == field.name)).unwrap_or(false) {
-                         content.push_str(&format!("        if \"{}\" not in data_dict and \"current_user\" in locals():\n", field.name));
-                         content.push_str(&format!("            data_dict[\"{}\"] = current_user.id\n", field.name));
+            let method = action.route.as_ref().map(|r| &r.method);
+            if matches!(method, Some(crate::ast::HttpMethod::Post)) {
+                 content.push_str("        data_dict = data.model_dump()\n");
+                 
+                 // Check if entity has user_id or similar and set it from current_user
+                 if let Some(entity) = program.find_entity(entity_name) {
+                     for field in &entity.fields {
+                         if (field.name == "user_id" || field.name == "owner_id" || field.name == format!("{}_id", entity.name.to_lowercase()))
+                            && !def.input.as_ref().map(|i| i.fields.iter().any(|f| f.name == field.name)).unwrap_or(false) {
+                             content.push_str(&format!("        if \"{}\" not in data_dict and \"current_user\" in locals():\n", field.name));
+                             content.push_str(&format!("            data_dict[\"{}\"] = current_user.id\n", field.name));
+                         }
                      }
                  }
-             }
 
-             content.push_str("        return self.repo.create(db, data_dict)\n\n");
-        } else if matches!(method, crate::ast::HttpMethod::Get) {
-             if path.contains('{') {
-                 content.push_str("        result = self.repo.get_by_id(db, id)\n");
-                 content.push_str("        if not result:\n");
-                 content.push_str("            raise HTTPException(status_code=404, detail=\"Not found\")\n");
-                 content.push_str("        return result\n\n");
-             } else if action_name.starts_with("get_") && (requires_auth || uses_current_user) {
-                 content.push_str("        return current_user\n\n");
-             } else {
-                 content.push_str("        return self.repo.get_all(db)\n\n");
-             }
+                 content.push_str("        return self.repo.create(db, data_dict)\n\n");
+            } else if matches!(method, Some(crate::ast::HttpMethod::Get)) {
+                 if !action.path_params().is_empty() {
+                     content.push_str("        result = self.repo.get_by_id(db, id)\n");
+                     content.push_str("        if not result:\n");
+                     content.push_str("            raise HTTPException(status_code=404, detail=\"Not found\")\n");
+                     content.push_str("        return result\n\n");
+                 } else if action.result == ResultSource::CurrentUser {
+                     content.push_str("        return current_user\n\n");
+                 } else {
+                     content.push_str("        return self.repo.get_all(db)\n\n");
+                 }
+            }
         }
     }
     
     content
 }
 
-fn generate_services_init(ast: &IntentFile) -> String {
-    let mut content = String::new();
-    content.push_str("# Intent Compiler Generated Services\n");
-    content.push_str("# Generated automatically - do not edit\n\n");
-    
-    for entity in &ast.entities {
-        let name_lower = entity.name.to_lowercase();
-        content.push_str(&format!(
-            "from services.{}_service import {}Service, {}_service\n",
-            name_lower, entity.name, name_lower
-        ));
+fn literal_to_python(lit: &crate::ast::LiteralValue) -> String {
+    match lit {
+        crate::ast::LiteralValue::String(s) => format!("\"{}\"", s),
+        crate::ast::LiteralValue::Number(n) => n.to_string(),
+        crate::ast::LiteralValue::Boolean(b) => if *b { "True".to_string() } else { "False".to_string() },
     }
-    
-    content.push_str("\n__all__ = [\n");
-    for entity in &ast.entities {
-        content.push_str(&format!("    \"{}Service\",\n", entity.name));
-        content.push_str(&format!("    \"{}_service\",\n", entity.name.to_lowercase()));
-    }
-    content.push_str("]\n");
-    
-    content
 }
 
 fn select_query_to_python(entity: &str, predicate: &crate::ast::Predicate, has_data: bool, derived_vars: &std::collections::HashSet<String>) -> String {
@@ -693,3 +500,29 @@ fn system_call_to_python(namespace: &str, capability: &str, args: &[crate::ast::
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{CodeGenerator, GeneratorOptions};
+    use crate::codegen::python::PythonGenerator;
+    use crate::parser::parse_intent;
+
+    #[test]
+    fn test_generation_gap_keeps_user_files() {
+        let source = "entity Note:\n    id: uuid @primary\n    body: string\n";
+        let program = Program::lower(&parse_intent(source).unwrap());
+        let generator = PythonGenerator::new(GeneratorOptions { generation_gap: true, ..Default::default() });
+        let dir = tempfile::tempdir().unwrap();
+
+        let first = generator.generate(&program, dir.path()).unwrap();
+        assert!(first.files["services/base/note_service.py"].contains("class NoteServiceBase:"));
+        assert!(first.files.contains_key("services/note_service.py"));
+
+        std::fs::create_dir_all(dir.path().join("services")).unwrap();
+        std::fs::write(dir.path().join("services/note_service.py"), "# customized\n").unwrap();
+        let second = generator.generate(&program, dir.path()).unwrap();
+        assert!(!second.files.contains_key("services/note_service.py"));
+        assert!(second.user_files.contains(&"services/note_service.py".to_string()));
+    }
+}
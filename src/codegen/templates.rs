@@ -0,0 +1,125 @@
+// Intent Compiler - Template Engine
+// Renders generated files from Tera templates, embedded by default and overridable per project
+
+use std::error::Error as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tera::{Context, Tera};
+use walkdir::WalkDir;
+
+use crate::error::{CompileError, CompileResult};
+
+/// Project directory holding template overrides
+pub const TEMPLATE_DIR: &str = "templates";
+
+/// File extension of override files, e.g. `templates/main.py.tera` overrides `main.py`
+pub const TEMPLATE_EXTENSION: &str = ".tera";
+
+/// A target's built-in templates, the project's overrides and the globals every template sees
+pub struct Templates {
+    tera: Tera,
+    globals: Context,
+    /// Override files whose name matches no built-in template
+    pub unused_overrides: Vec<PathBuf>,
+}
+
+impl Templates {
+    /// Load `builtin` (name, source) pairs, replacing each one that has a file
+    /// `<name>.tera` under `override_dir`
+    pub fn load(builtin: &[(&str, &str)], override_dir: Option<&Path>) -> CompileResult<Self> {
+        let mut sources: Vec<(String, String)> = builtin
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect();
+        let mut unused_overrides = Vec::new();
+
+        if let Some(dir) = override_dir.filter(|d| d.is_dir()) {
+            for entry in WalkDir::new(dir).sort_by_file_name() {
+                let entry = entry.map_err(|e| CompileError::config(e.to_string()))?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let path = entry.path();
+                let name = path
+                    .strip_prefix(dir)
+                    .ok()
+                    .and_then(|relative| relative.to_str())
+                    .and_then(|relative| relative.strip_suffix(TEMPLATE_EXTENSION))
+                    .map(|name| name.replace('\\', "/"));
+                match sources.iter_mut().find(|(builtin, _)| Some(builtin) == name.as_ref()) {
+                    Some((_, source)) => *source = fs::read_to_string(path)?,
+                    None => unused_overrides.push(path.to_path_buf()),
+                }
+            }
+        }
+
+        let mut tera = Tera::default();
+        // Generated code is not HTML
+        tera.autoescape_on(Vec::new());
+        tera.add_raw_templates(sources).map_err(|e| template_error(&e))?;
+        Ok(Self { tera, globals: Context::new(), unused_overrides })
+    }
+
+    /// Make a value available to every template under `key`
+    pub fn set_global(&mut self, key: &str, value: &impl Serialize) {
+        self.globals.insert(key, value);
+    }
+
+    /// Render a template with the globals plus the fields of `context`; `&()` adds none
+    pub fn render(&self, name: &str, context: &impl Serialize) -> CompileResult<String> {
+        let mut full = self.globals.clone();
+        let value = serde_json::to_value(context).map_err(|e| CompileError::codegen(e.to_string()))?;
+        if !value.is_null() {
+            full.extend(Context::from_value(value).map_err(|e| template_error(&e))?);
+        }
+        self.tera.render(name, &full).map_err(|e| template_error(&e))
+    }
+}
+
+/// Tera errors nest the useful message (missing variable, syntax error) in their sources
+fn template_error(error: &tera::Error) -> CompileError {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    CompileError::codegen(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BUILTIN: &[(&str, &str)] = &[("hello.py", "print(\"{{ greeting }}, {{ name }}\")\n")];
+
+    #[test]
+    fn test_builtin_renders_with_globals() {
+        let mut templates = Templates::load(BUILTIN, None).unwrap();
+        templates.set_global("greeting", &"hello");
+        let out = templates.render("hello.py", &json!({ "name": "<world>" })).unwrap();
+        assert_eq!(out, "print(\"hello, <world>\")\n");
+    }
+
+    #[test]
+    fn test_project_overrides_replace_builtins() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("hello.py.tera"), "print({{ name | upper }})\n").unwrap();
+        fs::write(dir.path().join("other.py.tera"), "").unwrap();
+
+        let templates = Templates::load(BUILTIN, Some(dir.path())).unwrap();
+        assert_eq!(templates.render("hello.py", &json!({ "name": "x" })).unwrap(), "print(X)\n");
+        assert_eq!(templates.unused_overrides, vec![dir.path().join("other.py.tera")]);
+    }
+
+    #[test]
+    fn test_render_errors_name_the_missing_variable() {
+        let templates = Templates::load(BUILTIN, None).unwrap();
+        let err = templates.render("hello.py", &json!({})).unwrap_err();
+        assert!(err.to_string().contains("greeting"), "{}", err);
+    }
+}
@@ -0,0 +1,641 @@
+// Intent Compiler - Diagnostic Codes
+// Stable codes for every diagnostic, with long-form explanations for `intentc explain`
+
+/// A documented diagnostic code
+pub struct DiagnosticCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+    /// A complete intent file that triggers the diagnostic. Configuration errors show
+    /// intent.toml and environment errors a command line instead.
+    pub bad: &'static str,
+    /// The same example with the problem fixed
+    pub fixed: &'static str,
+}
+
+/// Look up a code, case-insensitively (`e0203` finds `E0203`)
+pub fn lookup(code: &str) -> Option<&'static DiagnosticCode> {
+    CODES.iter().find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+/// Every diagnostic the compiler can emit.
+/// E00xx: syntax, E01xx: entities and types, E02xx: actions and process blocks,
+/// E03xx: rules and policies, E09xx: code generation and I/O, Wxxxx: warnings,
+/// Sxxxx: security audit findings
+pub const CODES: &[DiagnosticCode] = &[
+    DiagnosticCode {
+        code: "E0001",
+        title: "syntax error",
+        explanation: "The file does not match the Intent grammar. The caret points at the first token the parser could not accept; the message lists what it expected instead.",
+        bad: "entity User\n    id: uuid @primary\n",
+        fixed: "entity User:\n    id: uuid @primary\n",
+    },
+    DiagnosticCode {
+        code: "E0002",
+        title: "malformed construct",
+        explanation: "A construct was accepted by the grammar but could not be turned into a definition, for example a predicate that follows a path through two records. Predicates compare a column or a field of one derived record.",
+        bad: "entity Post:\n    id: uuid @primary\n    author_id: uuid\n\naction find_post:\n    input:\n        id: uuid\n    process:\n        derive post = select Post where post.author.id == input.id\n    output: Post(id)\n",
+        fixed: "entity Post:\n    id: uuid @primary\n    author_id: uuid\n\naction find_post:\n    input:\n        id: uuid\n    process:\n        derive post = select Post where author_id == input.id\n    output: Post(id)\n",
+    },
+    DiagnosticCode {
+        code: "E0101",
+        title: "duplicate entity",
+        explanation: "Two entities share the same name. Entity names become model and table names, so they must be unique within a file.",
+        bad: "entity User:\n    id: uuid @primary\n\nentity User:\n    name: string\n",
+        fixed: "entity User:\n    id: uuid @primary\n    name: string\n",
+    },
+    DiagnosticCode {
+        code: "E0102",
+        title: "multiple auth entities",
+        explanation: "Only one entity can be marked with `auth entity`; it is the account type used by login, signup and `current_user`.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\nauth entity Admin:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: user | admin\n",
+    },
+    DiagnosticCode {
+        code: "E0103",
+        title: "duplicate field",
+        explanation: "An entity declares the same field name twice.",
+        bad: "entity Post:\n    id: uuid @primary\n    title: string\n    title: string\n",
+        fixed: "entity Post:\n    id: uuid @primary\n    title: string\n    subtitle: string\n",
+    },
+    DiagnosticCode {
+        code: "E0104",
+        title: "multiple primary keys",
+        explanation: "An entity may have only one field marked `@primary`. Composite primary keys are not supported.",
+        bad: "entity Post:\n    id: uuid @primary\n    slug: string @primary\n",
+        fixed: "entity Post:\n    id: uuid @primary\n    slug: string @unique\n",
+    },
+    DiagnosticCode {
+        code: "E0105",
+        title: "auth entity without email",
+        explanation: "The generated login and signup flows identify accounts by an `email` field, so the auth entity must declare one.",
+        bad: "auth entity User:\n    id: uuid @primary\n    password_hash: string\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n",
+    },
+    DiagnosticCode {
+        code: "E0106",
+        title: "auth entity without password",
+        explanation: "The auth entity needs a `password` or `password_hash` field to store the hashed credential checked by login.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n",
+    },
+    DiagnosticCode {
+        code: "E0107",
+        title: "unknown entity",
+        explanation: "A type or argument names an entity that is not defined in the file. Entity names are case-sensitive.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\nentity Post:\n    id: uuid @primary\n    author: Usr\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\nentity Post:\n    id: uuid @primary\n    author: User\n",
+    },
+    DiagnosticCode {
+        code: "E0108",
+        title: "empty enum",
+        explanation: "An enum type must list at least one value. The grammar needs two values to recognize an enum, so only an AST loaded with `compile --from-ast` can contain an empty one.",
+        bad: "{\"entities\": [{\"name\": \"Task\", \"is_auth\": false, \"policies\": [], \"location\": {\"line\": 1, \"column\": 1, \"span\": [0, 0]}, \"fields\": [\n    {\"name\": \"id\", \"field_type\": \"Uuid\", \"decorators\": [\"Primary\"], \"location\": {\"line\": 2, \"column\": 5, \"span\": [0, 0]}},\n    {\"name\": \"status\", \"field_type\": {\"Enum\": []}, \"decorators\": [], \"location\": {\"line\": 3, \"column\": 5, \"span\": [0, 0]}}\n]}], \"actions\": [], \"rules\": [], \"policies\": [], \"auth_entity\": null}\n",
+        fixed: "entity Task:\n    id: uuid @primary\n    status: active | inactive\n",
+    },
+    DiagnosticCode {
+        code: "E0109",
+        title: "duplicate enum value",
+        explanation: "An enum type lists the same value more than once.",
+        bad: "entity Task:\n    id: uuid @primary\n    status: active | inactive | active\n",
+        fixed: "entity Task:\n    id: uuid @primary\n    status: active | inactive\n",
+    },
+    DiagnosticCode {
+        code: "E0110",
+        title: "optional primary key",
+        explanation: "A primary key identifies every row, so it can never be null. Remove `@optional` from the key field.",
+        bad: "entity Task:\n    id: uuid @primary @optional\n",
+        fixed: "entity Task:\n    id: uuid @primary\n",
+    },
+    DiagnosticCode {
+        code: "E0201",
+        title: "duplicate action",
+        explanation: "Two actions share the same name. Action names become service methods and route handlers, so they must be unique.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    output: User(id, email)\n\naction get_user:\n    input:\n        email: email\n    output: User(id, email)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    output: User(id, email)\n\naction get_user_by_email:\n    input:\n        email: email\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0202",
+        title: "duplicate parameter",
+        explanation: "An action's input section declares the same parameter twice.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        email: email\n        email: string\n    process:\n        derive user = select User where email == input.email\n    output: User(id, email)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        email: email\n    process:\n        derive user = select User where email == input.email\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0203",
+        title: "unknown entity in select",
+        explanation: "A `select` names an entity that is not defined. The select would query a table that does not exist.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select Usr where id == input.id\n    output: User(id, email)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0204",
+        title: "unknown entity in mutate",
+        explanation: "A `mutate` block names an entity that is not defined.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction rename_user:\n    input:\n        id: uuid\n        name: string\n    process:\n        mutate Usr where id == input.id:\n            set name = input.name\n    output: User(id, name)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction rename_user:\n    input:\n        id: uuid\n        name: string\n    process:\n        mutate User where id == input.id:\n            set name = input.name\n    output: User(id, name)\n",
+    },
+    DiagnosticCode {
+        code: "E0205",
+        title: "unknown entity in delete",
+        explanation: "A `delete` statement names an entity that is not defined.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction delete_user:\n    input:\n        id: uuid\n    process:\n        delete Usr where id == input.id\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction delete_user:\n    input:\n        id: uuid\n    process:\n        delete User where id == input.id\n",
+    },
+    DiagnosticCode {
+        code: "E0206",
+        title: "unknown output type",
+        explanation: "The output section projects an entity that is not defined.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: Usr(id, name)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, name)\n",
+    },
+    DiagnosticCode {
+        code: "E0207",
+        title: "unknown entity field",
+        explanation: "A predicate, setter or field access names a field the entity does not have.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        email: email\n    process:\n        derive user = select User where mail == input.email\n    output: User(id, email)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        email: email\n    process:\n        derive user = select User where email == input.email\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0208",
+        title: "unknown input field",
+        explanation: "`input.<name>` refers to a parameter that is not declared in the action's input section.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction rename_user:\n    input:\n        id: uuid\n    process:\n        mutate User where id == input.id:\n            set name = input.name\n    output: User(id, name)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction rename_user:\n    input:\n        id: uuid\n        name: string\n    process:\n        mutate User where id == input.id:\n            set name = input.name\n    output: User(id, name)\n",
+    },
+    DiagnosticCode {
+        code: "E0209",
+        title: "unknown identifier",
+        explanation: "A process step uses a name that is neither an input parameter nor a variable derived by an earlier step. Variables are only visible after the `derive` that introduces them.",
+        bad: "entity Post:\n    id: uuid @primary\n    title: string\n    slug: string\n\naction create_post:\n    input:\n        title: string\n    process:\n        derive slug = compute slugify(headline)\n        mutate Post:\n            set title = input.title\n            set slug = slug\n    output: Post(id, slug)\n",
+        fixed: "entity Post:\n    id: uuid @primary\n    title: string\n    slug: string\n\naction create_post:\n    input:\n        title: string\n    process:\n        derive slug = compute slugify(input.title)\n        mutate Post:\n            set title = input.title\n            set slug = slug\n    output: Post(id, slug)\n",
+    },
+    DiagnosticCode {
+        code: "E0210",
+        title: "type mismatch in assignment",
+        explanation: "A `set` assigns a value whose type does not match the field type.",
+        bad: "entity Product:\n    id: uuid @primary\n    name: string\n    price: number\n    stock: number\n\naction restock:\n    input:\n        id: uuid\n        name: string\n    process:\n        mutate Product where id == input.id:\n            set stock = input.name\n    output: Product(id, stock)\n",
+        fixed: "entity Product:\n    id: uuid @primary\n    name: string\n    price: number\n    stock: number\n\naction restock:\n    input:\n        id: uuid\n        name: string\n        stock: number\n    process:\n        mutate Product where id == input.id:\n            set stock = input.stock\n    output: Product(id, stock)\n",
+    },
+    DiagnosticCode {
+        code: "E0211",
+        title: "incomparable predicate operands",
+        explanation: "The two sides of a `where` predicate have types that can never be equal, such as a number column and a boolean value.",
+        bad: "entity Product:\n    id: uuid @primary\n    name: string\n    price: number\n    stock: number\n\naction find_products:\n    input:\n        in_stock: boolean\n    process:\n        derive product = select Product where price == input.in_stock\n    output: Product(id, name)\n",
+        fixed: "entity Product:\n    id: uuid @primary\n    name: string\n    price: number\n    stock: number\n\naction find_products:\n    input:\n        price: number\n    process:\n        derive product = select Product where price == input.price\n    output: Product(id, name)\n",
+    },
+    DiagnosticCode {
+        code: "E0212",
+        title: "unsupported ordering comparison",
+        explanation: "`<` and `>` are only defined for number and datetime operands.",
+        bad: "entity Product:\n    id: uuid @primary\n    name: string\n    price: number\n    stock: number\n\naction find_products:\n    input:\n        name: string\n    process:\n        derive product = select Product where name > \"a\"\n    output: Product(id, name)\n",
+        fixed: "entity Product:\n    id: uuid @primary\n    name: string\n    price: number\n    stock: number\n\naction find_products:\n    input:\n        name: string\n    process:\n        derive product = select Product where price > 10\n    output: Product(id, name)\n",
+    },
+    DiagnosticCode {
+        code: "E0213",
+        title: "invalid enum value",
+        explanation: "A string literal compared with or assigned to an enum field is not one of the enum's values.",
+        bad: "entity Order:\n    id: uuid @primary\n    status: pending | shipped\n\naction ship_order:\n    input:\n        id: uuid\n    process:\n        mutate Order where id == input.id:\n            set status = \"sent\"\n    output: Order(id, status)\n",
+        fixed: "entity Order:\n    id: uuid @primary\n    status: pending | shipped\n\naction ship_order:\n    input:\n        id: uuid\n    process:\n        mutate Order where id == input.id:\n            set status = \"shipped\"\n    output: Order(id, status)\n",
+    },
+    DiagnosticCode {
+        code: "E0214",
+        title: "read-only field mutation",
+        explanation: "Fields marked `@primary` or `@auto` are managed by the database and cannot be assigned with `set`.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction update_user:\n    input:\n        id: uuid\n        new_id: uuid\n    process:\n        mutate User where id == input.id:\n            set id = input.new_id\n    output: User(id)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction rename_user:\n    input:\n        id: uuid\n        name: string\n    process:\n        mutate User where id == input.id:\n            set name = input.name\n    output: User(id, name)\n",
+    },
+    DiagnosticCode {
+        code: "E0215",
+        title: "invalid predicate left side",
+        explanation: "The left side of a `where` predicate must name a column of the selected entity; literals belong on the right.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n    process:\n        derive user = select User where \"a@b.c\" == email\n    output: User(id, email)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n    process:\n        derive user = select User where email == \"a@b.c\"\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0216",
+        title: "wrong number of arguments",
+        explanation: "A built-in compute function was called with the wrong number of arguments. `hash` and `slugify` take one, `verify_hash` takes two.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\naction check_password:\n    input:\n        email: email\n        password: string\n    process:\n        derive user = select User where email == input.email\n        derive valid = compute verify_hash(input.password)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\naction check_password:\n    input:\n        email: email\n        password: string\n    process:\n        derive user = select User where email == input.email\n        derive valid = compute verify_hash(input.password, user.password_hash)\n",
+    },
+    DiagnosticCode {
+        code: "E0217",
+        title: "unknown system capability",
+        explanation: "`system <namespace>.<capability>(...)` names a capability the runtime does not provide. Available: jwt.create, jwt.verify.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\naction check_password:\n    input:\n        email: email\n        password: string\n    process:\n        derive user = select User where email == input.email\n        derive token = system jwt.sign(user.email)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\naction check_password:\n    input:\n        email: email\n        password: string\n    process:\n        derive user = select User where email == input.email\n        derive token = system jwt.create(user.email)\n",
+    },
+    DiagnosticCode {
+        code: "E0218",
+        title: "field access on a scalar",
+        explanation: "Only entity records (select results, `current_user`) have fields. Scalars such as strings and numbers do not.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction greet:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n        derive first = user.name.first\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction greet:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n        derive first = user.name\n",
+    },
+    DiagnosticCode {
+        code: "E0220",
+        title: "unknown output field",
+        explanation: "Every projected output field must be a field of the output entity or a variable derived in the process section. A typo here would otherwise crash the generated service at runtime.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, emial)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0221",
+        title: "secret field in output",
+        explanation: "The output projects a credential-like field (password, secret or *_hash). Such fields must not leave the service unless the entity field is explicitly marked `@expose`.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, password_hash)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\naction get_user:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0222",
+        title: "output entity never produced",
+        explanation: "The output projects entity fields, but no process step selects, mutates or deletes a record of that entity, so there is nothing to read the fields from.",
+        bad: "entity Post:\n    id: uuid @primary\n    title: string\n    slug: string\n\naction preview_post:\n    input:\n        title: string\n    process:\n        derive slug = compute slugify(input.title)\n    output: Post(id, slug)\n",
+        fixed: "entity Post:\n    id: uuid @primary\n    title: string\n    slug: string\n\naction create_post:\n    input:\n        title: string\n    process:\n        derive slug = compute slugify(input.title)\n        mutate Post:\n            set title = input.title\n            set slug = slug\n    output: Post(id, slug)\n",
+    },
+    DiagnosticCode {
+        code: "E0223",
+        title: "undeclared path parameter",
+        explanation: "A `{param}` segment in an @api path has no matching input parameter.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\n@api GET /users/{id}\naction get_user:\n    output: User(id)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique\n    name: string\n\n@api GET /users/{id}\naction get_user:\n    input:\n        id: uuid\n    output: User(id)\n",
+    },
+    DiagnosticCode {
+        code: "E0224",
+        title: "unknown entity in @auth",
+        explanation: "`@auth(Name)` with a capitalized name must reference a defined entity.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@auth(Usr)\naction me:\n    output: User(id, email)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@auth(User)\naction me:\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0225",
+        title: "unknown action in @auth",
+        explanation: "`@auth(name)` with a lowercase name must reference a defined action.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\naction check_access:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, role)\n\n@auth(check_acess)\naction list_users:\n    output: User(id, email)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\naction check_access:\n    input:\n        id: uuid\n    process:\n        derive user = select User where id == input.id\n    output: User(id, role)\n\n@auth(check_access)\naction list_users:\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0226",
+        title: "unknown @auth argument",
+        explanation: "Arguments passed to an @auth action must be parameters of the decorated action.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\nentity Post:\n    id: uuid @primary\n    title: string\n\naction can_edit:\n    input:\n        id: uuid\n    process:\n        derive post = select Post where id == input.id\n    output: Post(id)\n\n@auth(can_edit(post_id))\naction edit_post:\n    input:\n        id: uuid\n        title: string\n    process:\n        mutate Post where id == input.id:\n            set title = input.title\n    output: Post(id, title)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\nentity Post:\n    id: uuid @primary\n    title: string\n\naction can_edit:\n    input:\n        id: uuid\n    process:\n        derive post = select Post where id == input.id\n    output: Post(id)\n\n@auth(can_edit(id))\naction edit_post:\n    input:\n        id: uuid\n        title: string\n    process:\n        mutate Post where id == input.id:\n            set title = input.title\n    output: Post(id, title)\n",
+    },
+    DiagnosticCode {
+        code: "E0227",
+        title: "@auth without auth entity",
+        explanation: "A bare `@auth` authenticates against the auth entity, but none is defined.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@auth\naction me:\n    output: User(id, email)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@auth\naction me:\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0228",
+        title: "unknown policy",
+        explanation: "`@policy(Name)` references a policy that is not defined globally or as `Entity.Policy`.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\n@auth\n@policy(AdminOnyl)\naction list_users:\n    output: User(id, email)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\n@auth\n@policy(AdminOnly)\naction list_users:\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "E0301",
+        title: "duplicate policy",
+        explanation: "Two policies share the same name in the same scope.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.email == \"root@example.com\"\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\npolicy RootOnly:\n    subject: @auth\n    require subject.email == \"root@example.com\"\n",
+    },
+    DiagnosticCode {
+        code: "E0302",
+        title: "unknown policy subject",
+        explanation: "A policy subject must be `@auth` or a defined entity.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: Usr\n    require subject.role == \"admin\"\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: User\n    require subject.role == \"admin\"\n",
+    },
+    DiagnosticCode {
+        code: "E0303",
+        title: "unknown field in expression",
+        explanation: "A rule or policy expression accesses a field the entity (or the `subject`) does not have.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.rol == \"admin\"\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n",
+    },
+    DiagnosticCode {
+        code: "E0304",
+        title: "unknown entity in expression",
+        explanation: "A rule or policy expression accesses a field of an entity that is not defined.",
+        bad: "entity User:\n    id: uuid @primary\n    age: number\n\nrule CheckAge:\n    when Usr.age < 18\n    then reject(\"User must be 18 or older\")\n",
+        fixed: "entity User:\n    id: uuid @primary\n    age: number\n\nrule CheckAge:\n    when User.age < 18\n    then reject(\"User must be 18 or older\")\n",
+    },
+    DiagnosticCode {
+        code: "E0305",
+        title: "unknown action in rule",
+        explanation: "A rule consequence calls an action that is not defined.",
+        bad: "entity User:\n    id: uuid @primary\n    age: number\n\nrule CheckAge:\n    when User.age < 18\n    then notify_admin(User.id)\n",
+        fixed: "entity User:\n    id: uuid @primary\n    age: number\n\naction notify_admin:\n    input:\n        id: uuid\n\nrule CheckAge:\n    when User.age < 18\n    then notify_admin(User.id)\n",
+    },
+    DiagnosticCode {
+        code: "E0306",
+        title: "empty rule message",
+        explanation: "`reject(...)` and `log(...)` need a non-empty message.",
+        bad: "entity User:\n    id: uuid @primary\n    age: number\n\nrule CheckAge:\n    when User.age < 18\n    then reject(\"\")\n",
+        fixed: "entity User:\n    id: uuid @primary\n    age: number\n\nrule CheckAge:\n    when User.age < 18\n    then reject(\"User must be 18 or older\")\n",
+    },
+    DiagnosticCode {
+        code: "E0901",
+        title: "code generation failed",
+        explanation: "The generator could not produce output, for example because the target language is not supported.",
+        bad: "intentc compile -i app.intent -t cobol\n",
+        fixed: "intentc compile -i app.intent -t python\n",
+    },
+    DiagnosticCode {
+        code: "E0902",
+        title: "I/O error",
+        explanation: "A file could not be read or written. Check that the input exists and the output directory is writable.",
+        bad: "intentc compile -i missing.intent\n",
+        fixed: "intentc compile -i app.intent\n",
+    },
+    DiagnosticCode {
+        code: "E0903",
+        title: "invalid project configuration",
+        explanation: "intent.toml could not be parsed, contains an unknown key, or names a lint that does not exist. Run `intentc lint --list` for the available lints.",
+        bad: "[lints]\nunused_entities = \"deny\"\n",
+        fixed: "[lints]\nunused_entity = \"deny\"\n",
+    },
+    DiagnosticCode {
+        code: "E0904",
+        title: "generated file edited by hand",
+        explanation: "A file listed in the output's .intentc-manifest.json changed since it was generated, and compiling would overwrite or delete it. Compiling also refuses to replace a file the last compilation did not generate, such as a user-owned service once `generation_gap` is turned off. Move the change into the intent file or a custom template, or pass `--force` to discard it.",
+        bad: "intentc compile -i app.intent -o out   # after editing out/main.py\n",
+        fixed: "intentc compile -i app.intent -o out --force\n",
+    },
+    DiagnosticCode {
+        code: "E0905",
+        title: "destructive schema change",
+        explanation: "Compared to the snapshot in intent.lock, the next migration would drop a table or column or narrow a column type, losing data. If the field or entity was renamed, mark it with `@renamed_from(\"old_name\")` to generate a rename instead; otherwise pass `--allow-destructive`.",
+        bad: "# `name` was renamed\nentity User:\n    id: uuid @primary\n    full_name: string\n",
+        fixed: "entity User:\n    id: uuid @primary\n    full_name: string @renamed_from(\"name\")\n",
+    },
+    DiagnosticCode {
+        code: "W0101",
+        title: "entity without primary key",
+        explanation: "The entity has no `@primary` field. Generated repositories look records up by primary key, so most entities should declare one.",
+        bad: "entity Tag:\n    name: string\n",
+        fixed: "entity Tag:\n    id: uuid @primary\n    name: string\n",
+    },
+    DiagnosticCode {
+        code: "W0102",
+        title: "unused entity",
+        explanation: "No action, entity reference, rule or policy mentions the entity, so it only adds an unused table. Lint: `unused_entity`.",
+        bad: "entity Draft:\n    id: uuid @primary\n",
+        fixed: "entity Draft:\n    id: uuid @primary\n\n@api GET /drafts\naction list_drafts:\n    output: Draft(id)\n",
+    },
+    DiagnosticCode {
+        code: "W0103",
+        title: "unused policy",
+        explanation: "The policy is never applied with `@policy(...)`, so it is never enforced. Entity-scoped policies are applied as `@policy(Entity.Name)`. Lint: `unused_policy`.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\nentity Post:\n    id: uuid @primary\n\n@api DELETE /posts/{id}\n@auth\naction delete_post:\n    input:\n        id: uuid\n    process:\n        delete Post where id == input.id\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\nentity Post:\n    id: uuid @primary\n\n@api DELETE /posts/{id}\n@auth\n@policy(AdminOnly)\naction delete_post:\n    input:\n        id: uuid\n    process:\n        delete Post where id == input.id\n",
+    },
+    DiagnosticCode {
+        code: "W0104",
+        title: "rule never runs",
+        explanation: "Rules are checked when an action creates or updates the entity named in their condition. No action writes that entity, so the rule never fires. Lint: `unused_rule`.",
+        bad: "entity User:\n    id: uuid @primary\n    age: number\n\nrule CheckAge:\n    when User.age < 18\n    then reject(\"Too young\")\n",
+        fixed: "entity User:\n    id: uuid @primary\n    age: number\n\n@api POST /users\naction create_user:\n    input:\n        age: number\n    process:\n        mutate User:\n            set age = input.age\n    output: User(id, age)\n\nrule CheckAge:\n    when User.age < 18\n    then reject(\"Too young\")\n",
+    },
+    DiagnosticCode {
+        code: "W0105",
+        title: "action without route",
+        explanation: "The action has no `@api` decorator and is not called by `@auth(...)` or a rule, so nothing can invoke it. Lint: `missing_api`.",
+        bad: "entity Post:\n    id: uuid @primary\n\naction list_posts:\n    output: Post(id)\n",
+        fixed: "entity Post:\n    id: uuid @primary\n\n@api GET /posts\naction list_posts:\n    output: Post(id)\n",
+    },
+    DiagnosticCode {
+        code: "W0106",
+        title: "unique field without index",
+        explanation: "Fields marked `@unique` are usually looked up by value. Adding `@index` keeps those lookups fast. Lint: `unique_without_index`.",
+        bad: "entity User:\n    id: uuid @primary\n    email: email @unique\n",
+        fixed: "entity User:\n    id: uuid @primary\n    email: email @unique @index\n",
+    },
+    DiagnosticCode {
+        code: "W0107",
+        title: "unused enum value",
+        explanation: "No rule mentions the enum value, which often means a state was added or retired without handling it. Allow the lint in intent.toml if your rules don't cover every state. Lint: `unused_enum_value`.",
+        bad: "entity Article:\n    id: uuid @primary\n    status: draft | published | archived\n\nrule NoDrafts:\n    when Article.status == \"draft\"\n    then reject(\"Publish the article first\")\n\nrule NotPublished:\n    when Article.status == \"published\"\n    then log(\"Article published\")\n",
+        fixed: "entity Article:\n    id: uuid @primary\n    status: draft | published\n\nrule NoDrafts:\n    when Article.status == \"draft\"\n    then reject(\"Publish the article first\")\n\nrule NotPublished:\n    when Article.status == \"published\"\n    then log(\"Article published\")\n",
+    },
+    DiagnosticCode {
+        code: "W0108",
+        title: "naming convention",
+        explanation: "Entities, policies and rules use PascalCase; fields and actions use snake_case. Generated Python follows the same conventions. Lint: `naming`.",
+        bad: "entity Post:\n    id: uuid @primary\n\n@api GET /posts\naction listPosts:\n    output: Post(id)\n",
+        fixed: "entity Post:\n    id: uuid @primary\n\n@api GET /posts\naction list_posts:\n    output: Post(id)\n",
+    },
+    DiagnosticCode {
+        code: "W0109",
+        title: "unindexed lookup",
+        explanation: "A `select`, `mutate ... where` or `delete ... where` filters on a column with no `@index`, `@unique` or `@primary`, so the generated query scans the whole table. Compile with `--auto-index` to add the index automatically. Lint: `unindexed_lookup`.",
+        bad: "entity Product:\n    id: uuid @primary\n    sku: string\n\n@api GET /products/by-sku/{sku}\naction get_product_by_sku:\n    input:\n        sku: string\n    process:\n        derive product = select Product where sku == input.sku\n    output: Product(id, sku)\n",
+        fixed: "entity Product:\n    id: uuid @primary\n    sku: string @index\n\n@api GET /products/by-sku/{sku}\naction get_product_by_sku:\n    input:\n        sku: string\n    process:\n        derive product = select Product where sku == input.sku\n    output: Product(id, sku)\n",
+    },
+    DiagnosticCode {
+        code: "W0110",
+        title: "missing composite index",
+        explanation: "The entity is looked up by two or more foreign keys, as association tables usually are. A single index over those columns serves lookups by the leading key and by the pair. Declare it with `@index(a, b)` on the line before `entity`, or compile with `--auto-index` to add it. Lint: `missing_composite_index`.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\nentity CartItem:\n    id: uuid @primary\n    user_id: uuid @index\n    product_id: uuid @index\n\n@api GET /cart\n@auth\naction list_cart:\n    process:\n        derive items = select CartItem where user_id == current_user.id\n    output: CartItem(id, product_id)\n\n@api DELETE /cart/{product_id}\n@auth\naction remove_from_cart:\n    input:\n        product_id: uuid\n    process:\n        delete CartItem where product_id == input.product_id\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@index(user_id, product_id)\nentity CartItem:\n    id: uuid @primary\n    user_id: uuid @index\n    product_id: uuid @index\n\n@api GET /cart\n@auth\naction list_cart:\n    process:\n        derive items = select CartItem where user_id == current_user.id\n    output: CartItem(id, product_id)\n\n@api DELETE /cart/{product_id}\n@auth\naction remove_from_cart:\n    input:\n        product_id: uuid\n    process:\n        delete CartItem where product_id == input.product_id\n",
+    },
+    DiagnosticCode {
+        code: "W0111",
+        title: "N+1 query in list output",
+        explanation: "A list endpoint projects a field that references another entity. Each returned row loads its related record with a separate query. Lint: `n_plus_one`.",
+        bad: "entity Product:\n    id: uuid @primary\n    name: string\n\nentity CartItem:\n    id: uuid @primary\n    product_id: uuid\n    product: Product\n\n@api GET /cart\naction list_cart:\n    output: CartItem(id, product)\n",
+        fixed: "entity Product:\n    id: uuid @primary\n    name: string\n\nentity CartItem:\n    id: uuid @primary\n    product_id: uuid\n    product: Product\n\n@api GET /cart\naction list_cart:\n    output: CartItem(id, product_id)\n",
+    },
+    DiagnosticCode {
+        code: "S0101",
+        title: "unauthenticated write",
+        explanation: "The action creates, updates or deletes records but has neither `@auth` nor `@policy`, so anyone who can reach the API can change data. Severity: high.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\nentity Post:\n    id: uuid @primary\n\n@api DELETE /posts/{id}\naction delete_post:\n    input:\n        id: uuid\n    process:\n        delete Post where id == input.id\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n    role: string\n\npolicy AdminOnly:\n    subject: @auth\n    require subject.role == \"admin\"\n\nentity Post:\n    id: uuid @primary\n\n@api DELETE /posts/{id}\n@auth\n@policy(AdminOnly)\naction delete_post:\n    input:\n        id: uuid\n    process:\n        delete Post where id == input.id\n",
+    },
+    DiagnosticCode {
+        code: "S0102",
+        title: "credential in output",
+        explanation: "The action returns a password, secret or hash field. Such fields are only allowed in outputs when marked `@expose`, which should be rare. Severity: high.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string @expose\n\n@api GET /me\n@auth\naction me:\n    output: User(id, password_hash)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@api GET /me\n@auth\naction me:\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "S0103",
+        title: "password stored without hashing",
+        explanation: "A password input is mapped with `@map(..., none)`, or a credential field is set from a value that is not passed through `hash(...)`. Severity: high.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@api POST /accounts\naction create_account:\n    input:\n        email: email\n        password: string\n    process:\n        mutate User:\n            set email = input.email\n            set password_hash = input.password\n    output: User(id, email)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\n@api POST /accounts\naction create_account:\n    input:\n        email: email\n        password: string\n    process:\n        mutate User:\n            set email = input.email\n            set password_hash = compute hash(input.password)\n    output: User(id, email)\n",
+    },
+    DiagnosticCode {
+        code: "S0104",
+        title: "auth entity without unique identifier",
+        explanation: "Login looks users up by identifier. Without a `@unique` field, two accounts can share an email and login becomes ambiguous. Severity: medium.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email\n    password_hash: string\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n",
+    },
+    DiagnosticCode {
+        code: "S0105",
+        title: "unscoped list endpoint",
+        explanation: "A GET endpoint lists an entity that belongs to a user or tenant without filtering by the caller, so every caller sees every owner's records. Severity: medium.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\nentity Order:\n    id: uuid @primary\n    user_id: uuid @index\n    total: number\n\n@api GET /orders\n@auth\naction list_orders:\n    output: Order(id, total)\n",
+        fixed: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n\nentity Order:\n    id: uuid @primary\n    user_id: uuid @index\n    total: number\n\n@api GET /orders\n@auth\naction list_orders:\n    process:\n        derive orders = select Order where user_id == current_user.id\n    output: Order(id, total)\n",
+    },
+    DiagnosticCode {
+        code: "S0106",
+        title: "default secret key",
+        explanation: "The generated config.py falls back to a hard-coded `secret_key` used to sign JWTs. Anyone with the source can forge tokens unless SECRET_KEY is set in the environment. Severity: medium, since deployments that set it are safe.",
+        bad: "auth entity User:\n    id: uuid @primary\n    email: email @unique\n    password_hash: string\n",
+        fixed: "export SECRET_KEY=$(openssl rand -hex 32)\npython main.py\n",
+    },
+    DiagnosticCode {
+        code: "W0901",
+        title: "code generation warning",
+        explanation: "The generator produced output but could not honour part of the definition, for example a schema change that the generated migration cannot express, such as moving the primary key to another column. The message describes what was skipped.",
+        bad: "# `id` was the primary key\nentity Tag:\n    id: uuid\n    name: string @primary\n",
+        fixed: "entity Tag:\n    id: uuid @primary\n    name: string @unique\n",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    use crate::ast::IntentFile;
+    use crate::codegen::manifest::Manifest;
+    use crate::codegen::{GeneratorOptions, TargetLanguage, create_generator, output};
+    use crate::config::ProjectConfig;
+    use crate::error::CompileError;
+    use crate::ir::Program;
+    use crate::lint::{self, LintLevel};
+    use crate::{audit, parser, preprocessor, validator};
+
+    /// Examples given as command lines: their errors come from the environment, not the source
+    const COMMAND_EXAMPLES: &[&str] = &["E0901", "E0902", "E0904"];
+
+    /// Fixes made outside the intent file, with a compile flag or in the deployment
+    const FIXED_OUTSIDE_SOURCE: &[&str] = &["S0106"];
+
+    /// The schema each migration example changes, compiled first to leave an intent.lock behind
+    const PREVIOUS: &[(&str, &str)] = &[
+        ("E0905", "entity User:\n    id: uuid @primary\n    name: string\n"),
+        ("W0901", "entity Tag:\n    id: uuid @primary\n    name: string\n"),
+    ];
+
+    fn error_codes(error: &CompileError, codes: &mut Vec<&'static str>) {
+        match error {
+            CompileError::ParseError { code, .. }
+            | CompileError::ValidationError { code, .. }
+            | CompileError::CodeGenError { code, .. }
+            | CompileError::ConfigError { code, .. } => codes.push(code),
+            CompileError::IoError(_) => codes.push("E0902"),
+            CompileError::MultipleErrors(errors) => errors.iter().for_each(|e| error_codes(e, codes)),
+        }
+    }
+
+    /// Codes reported for an example by `check` with every lint enabled, by `audit`,
+    /// and for migration examples by `compile`
+    fn emitted(code: &str, source: &str) -> Vec<&'static str> {
+        let mut codes = Vec::new();
+        if code == "E0903" {
+            let config = ProjectConfig::parse(source, Path::new("intent.toml"));
+            let result = config.and_then(|config| lint::run_lints(&IntentFile::new(), "", &config.lints).0);
+            if let Err(e) = result {
+                error_codes(&e, &mut codes);
+            }
+            return codes;
+        }
+
+        // The grammar cannot express some mistakes; those examples are ASTs for `compile --from-ast`
+        let parsed = if source.starts_with('{') {
+            Ok(serde_json::from_str(source).unwrap())
+        } else {
+            parser::parse_intent(source)
+        };
+        let mut file = match parsed {
+            Ok(file) => file,
+            Err(e) => {
+                error_codes(&e, &mut codes);
+                return codes;
+            }
+        };
+        codes.extend(audit::audit(&file).iter().map(|f| f.code));
+        preprocessor::inject_auth_actions(&mut file);
+        let (validation, warnings) = validator::validate(&file);
+        codes.extend(warnings.iter().map(|w| w.code));
+        if let Err(e) = validation {
+            error_codes(&e, &mut codes);
+            return codes;
+        }
+        let levels = lint::LINTS.iter().map(|l| (l.name.to_string(), LintLevel::Warn)).collect();
+        let (result, warnings) = lint::run_lints(&file, source, &levels);
+        codes.extend(warnings.iter().map(|w| w.code));
+        if let Err(e) = result {
+            error_codes(&e, &mut codes);
+        }
+
+        if let Some((_, previous)) = PREVIOUS.iter().find(|(c, _)| *c == code) {
+            let dir = tempfile::tempdir().unwrap();
+            let generator = create_generator(TargetLanguage::Python, GeneratorOptions::default());
+            let result = generator.generate(&Program::lower(&parser::parse_intent(previous).unwrap()), dir.path()).unwrap();
+            output::commit(dir.path(), &result, &[], &Manifest::record(&result)).unwrap();
+            match generator.generate(&Program::lower(&file), dir.path()) {
+                Ok(result) => codes.extend(result.warnings.iter().map(|_| "W0901")),
+                Err(e) => error_codes(&e, &mut codes),
+            }
+        }
+        codes
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        let mut seen = HashSet::new();
+        for code in CODES {
+            assert!(seen.insert(code.code), "duplicate code {}", code.code);
+        }
+    }
+
+    #[test]
+    fn test_emitted_codes_are_documented() {
+        let sources = [
+            include_str!("validator.rs"),
+            include_str!("error.rs"),
+            include_str!("report.rs"),
+            include_str!("lint.rs"),
+            include_str!("audit.rs"),
+        ];
+        for source in sources {
+            for (i, _) in ["\"E0", "\"W0", "\"S0"].iter().flat_map(|p| source.match_indices(p)) {
+                let code = &source[i + 1..i + 6];
+                assert!(lookup(code).is_some(), "undocumented diagnostic code {}", code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_examples_emit_their_code() {
+        let mut failures = Vec::new();
+        for entry in CODES.iter().filter(|c| !COMMAND_EXAMPLES.contains(&c.code)) {
+            let bad = emitted(entry.code, entry.bad);
+            if !bad.contains(&entry.code) {
+                failures.push(format!("{} example emits {:?}", entry.code, bad));
+            }
+            if !FIXED_OUTSIDE_SOURCE.contains(&entry.code) && emitted(entry.code, entry.fixed).contains(&entry.code) {
+                failures.push(format!("fixed {} example still emits it", entry.code));
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_lookup_is_case_insensitive() {
+        assert_eq!(lookup("e0203").map(|c| c.title), Some("unknown entity in select"));
+        assert!(lookup("E9999").is_none());
+    }
+}
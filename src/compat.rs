@@ -0,0 +1,263 @@
+// Intent Compiler - API Compatibility
+// Classifies the API changes between two versions of an intent file as breaking or not
+
+use std::fmt;
+
+use crate::ast::{Decorator, FieldType};
+use crate::ir::{ActionIr, Program, Route, VarType};
+
+/// Whether existing clients keep working after a change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Impact {
+    Breaking,
+    NonBreaking,
+}
+
+impl fmt::Display for Impact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Impact::Breaking => write!(f, "breaking"),
+            Impact::NonBreaking => write!(f, "non-breaking"),
+        }
+    }
+}
+
+/// A change to the API of one action
+#[derive(Debug, Clone)]
+pub struct ApiChange {
+    pub impact: Impact,
+    pub action: String,
+    pub message: String,
+}
+
+/// Which way values flow: clients send inputs and receive outputs
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Input,
+    Output,
+}
+
+/// Compare the actions of two programs. Breaking changes come first, each group in source order.
+pub fn compare(old: &Program, new: &Program) -> Vec<ApiChange> {
+    let mut changes = Vec::new();
+    for action in &old.actions {
+        match new.actions.iter().find(|a| a.name == action.name) {
+            Some(current) => compare_action(action, old, current, new, &mut changes),
+            None => push(&mut changes, Impact::Breaking, action, "action removed".to_string()),
+        }
+    }
+    for action in new.actions.iter().filter(|a| !old.actions.iter().any(|o| o.name == a.name)) {
+        push(&mut changes, Impact::NonBreaking, action, "action added".to_string());
+    }
+    changes.sort_by_key(|c| c.impact);
+    changes
+}
+
+fn push(changes: &mut Vec<ApiChange>, impact: Impact, action: &ActionIr, message: String) {
+    changes.push(ApiChange { impact, action: action.name.clone(), message });
+}
+
+fn compare_action(old: &ActionIr, old_program: &Program, new: &ActionIr, new_program: &Program, changes: &mut Vec<ApiChange>) {
+    match (&old.route, &new.route) {
+        (Some(before), None) => push(changes, Impact::Breaking, new, format!("no longer exposed at {}", route(before))),
+        (None, Some(after)) => push(changes, Impact::NonBreaking, new, format!("exposed at {}", route(after))),
+        (Some(before), Some(after)) if route_key(before) != route_key(after) => {
+            push(changes, Impact::Breaking, new, format!("route changed from {} to {}", route(before), route(after)));
+        }
+        _ => {}
+    }
+    match (old.requires_auth, new.requires_auth) {
+        (false, true) => push(changes, Impact::Breaking, new, "now requires authentication".to_string()),
+        (true, false) => push(changes, Impact::NonBreaking, new, "no longer requires authentication".to_string()),
+        _ => {}
+    }
+    compare_inputs(old, new, changes);
+    compare_outputs(old, old_program, new, new_program, changes);
+}
+
+fn route(route: &Route) -> String {
+    format!("{} {}", route.method, route.path)
+}
+
+/// Method and path with parameter names blanked: renaming `{id}` doesn't change the URL
+fn route_key(route: &Route) -> String {
+    let segments: Vec<_> = route
+        .path
+        .split('/')
+        .map(|s| if s.starts_with('{') && s.ends_with('}') { "{}" } else { s })
+        .collect();
+    format!("{} {}", route.method, segments.join("/"))
+}
+
+fn compare_inputs(old: &ActionIr, new: &ActionIr, changes: &mut Vec<ApiChange>) {
+    let params = |action: &ActionIr| action.def.input.iter().flat_map(|i| &i.fields).cloned().collect::<Vec<_>>();
+    let (before, after) = (params(old), params(new));
+    for param in &before {
+        match after.iter().find(|p| p.name == param.name) {
+            Some(current) => {
+                let what = format!("input '{}'", param.name);
+                compare_types(&what, Direction::Input, &param.param_type, &current.param_type, new, changes);
+            }
+            // Request models ignore unknown fields, so clients that still send it are unaffected
+            None => push(changes, Impact::NonBreaking, new, format!("input '{}' removed", param.name)),
+        }
+    }
+    for param in after.iter().filter(|p| !before.iter().any(|b| b.name == p.name)) {
+        if matches!(param.param_type, FieldType::Optional(_)) {
+            push(changes, Impact::NonBreaking, new, format!("optional input '{}' added", param.name));
+        } else {
+            push(changes, Impact::Breaking, new, format!("required input '{}' added", param.name));
+        }
+    }
+}
+
+fn compare_outputs(old: &ActionIr, old_program: &Program, new: &ActionIr, new_program: &Program, changes: &mut Vec<ApiChange>) {
+    for field in &old.output {
+        if !new.output.iter().any(|f| f.name == field.name) {
+            push(changes, Impact::Breaking, new, format!("output '{}' removed", field.name));
+            continue;
+        }
+        let what = format!("output '{}'", field.name);
+        if let (Some(before), Some(after)) = (output_type(old, old_program, &field.name), output_type(new, new_program, &field.name)) {
+            compare_types(&what, Direction::Output, &before, &after, new, changes);
+        }
+    }
+    for field in new.output.iter().filter(|f| !old.output.iter().any(|o| o.name == f.name)) {
+        push(changes, Impact::NonBreaking, new, format!("output '{}' added", field.name));
+    }
+}
+
+/// Type of an output field, with `@optional` folded into it; None when it can't be known
+fn output_type(action: &ActionIr, program: &Program, name: &str) -> Option<FieldType> {
+    let entity = action.def.output.as_ref().and_then(|o| program.find_entity(&o.entity));
+    if let Some(field) = entity.and_then(|e| e.fields.iter().find(|f| f.name == name)) {
+        let optional = field.decorators.contains(&Decorator::Optional) && !matches!(field.field_type, FieldType::Optional(_));
+        return Some(if optional { FieldType::Optional(Box::new(field.field_type.clone())) } else { field.field_type.clone() });
+    }
+    match action.variable(name).map(|v| &v.ty) {
+        Some(VarType::Value(ty)) => Some(ty.clone()),
+        _ => None,
+    }
+}
+
+/// Compare one value's type. Inputs may accept more and outputs may promise more; anything else breaks.
+fn compare_types(what: &str, direction: Direction, old: &FieldType, new: &FieldType, action: &ActionIr, changes: &mut Vec<ApiChange>) {
+    let (old_optional, old) = split_optional(old);
+    let (new_optional, new) = split_optional(new);
+
+    if let (FieldType::Enum(before), FieldType::Enum(after)) = (old, new) {
+        let removed: Vec<_> = before.iter().filter(|v| !after.contains(v)).map(String::as_str).collect();
+        let added: Vec<_> = after.iter().filter(|v| !before.contains(v)).map(String::as_str).collect();
+        if !removed.is_empty() {
+            let impact = match direction {
+                Direction::Input => Impact::Breaking,
+                Direction::Output => Impact::NonBreaking,
+            };
+            push(changes, impact, action, format!("{} no longer allows {}", what, removed.join(", ")));
+        }
+        if !added.is_empty() {
+            // Clients that decode the enum strictly reject values they don't know
+            let impact = match direction {
+                Direction::Input => Impact::NonBreaking,
+                Direction::Output => Impact::Breaking,
+            };
+            push(changes, impact, action, format!("{} now allows {}", what, added.join(", ")));
+        }
+    } else if old != new {
+        push(changes, Impact::Breaking, action, format!("{} changed type from {} to {}", what, old, new));
+    }
+
+    if old_optional != new_optional {
+        let (impact, message) = match (direction, new_optional) {
+            (Direction::Input, true) => (Impact::NonBreaking, "is now optional"),
+            (Direction::Input, false) => (Impact::Breaking, "is now required"),
+            (Direction::Output, true) => (Impact::Breaking, "may now be null"),
+            (Direction::Output, false) => (Impact::NonBreaking, "is never null anymore"),
+        };
+        push(changes, impact, action, format!("{} {}", what, message));
+    }
+}
+
+fn split_optional(ty: &FieldType) -> (bool, &FieldType) {
+    match ty {
+        FieldType::Optional(inner) => (true, inner),
+        other => (false, other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+
+    fn program(source: &str) -> Program {
+        Program::lower(&parse_intent(source).unwrap())
+    }
+
+    fn summary(old: &str, new: &str) -> Vec<String> {
+        compare(&program(old), &program(new))
+            .into_iter()
+            .map(|c| format!("{} {}: {}", c.impact, c.action, c.message))
+            .collect()
+    }
+
+    const BASE: &str = r#"entity Task:
+    id: uuid @primary
+    title: string
+    status: open | done | archived
+
+@api POST /tasks
+action create_task:
+    input:
+        title: string
+        note: string?
+    output: Task(id, title, status)
+
+@api GET /tasks/{id}
+action get_task:
+    output: Task(id, title, status)
+"#;
+
+    #[test]
+    fn test_identical_files_are_compatible() {
+        assert!(summary(BASE, BASE).is_empty());
+    }
+
+    #[test]
+    fn test_breaking_changes_come_first() {
+        let new = BASE
+            .replace("status: open | done | archived", "status: open | done | archived | blocked")
+            .replace("        note: string?\n", "        note: string\n        priority: number\n")
+            .replace("output: Task(id, title, status)\n\n@api GET /tasks/{id}", "output: Task(id, status)\n\n@api GET /tasks/{task_id}");
+        assert_eq!(summary(BASE, &new), vec![
+            "breaking create_task: input 'note' is now required",
+            "breaking create_task: required input 'priority' added",
+            "breaking create_task: output 'title' removed",
+            "breaking create_task: output 'status' now allows blocked",
+            "breaking get_task: output 'status' now allows blocked",
+        ]);
+    }
+
+    #[test]
+    fn test_additions_and_relaxations_are_not_breaking() {
+        let new = BASE
+            .replace("status: open | done | archived", "status: open | done")
+            .replace("        title: string\n        note", "        title: string?\n        note")
+            + "\n@api DELETE /tasks/{id}\naction delete_task:\n    output: Task(id)\n";
+        assert_eq!(summary(BASE, &new), vec![
+            "non-breaking create_task: input 'title' is now optional",
+            "non-breaking create_task: output 'status' no longer allows archived",
+            "non-breaking get_task: output 'status' no longer allows archived",
+            "non-breaking delete_task: action added",
+        ]);
+    }
+
+    #[test]
+    fn test_route_changes_break_clients() {
+        let new = BASE.replace("@api POST /tasks\n", "").replace("GET /tasks/{id}", "PUT /tasks/{id}");
+        assert_eq!(summary(BASE, &new), vec![
+            "breaking create_task: no longer exposed at POST /tasks",
+            "breaking get_task: route changed from GET /tasks/{id} to PUT /tasks/{id}",
+        ]);
+    }
+}
@@ -11,6 +11,7 @@ use crate::ast::SourceLocation;
 pub enum CompileError {
     #[error("Parse error: {message}")]
     ParseError {
+        code: &'static str,
         message: String,
         location: SourceLocation,
         snippet: Option<String>,
@@ -18,13 +19,17 @@ pub enum CompileError {
 
     #[error("Validation error: {message}")]
     ValidationError {
+        code: &'static str,
         message: String,
         location: SourceLocation,
         hint: Option<String>,
     },
 
     #[error("Code generation error: {message}")]
-    CodeGenError { message: String },
+    CodeGenError { code: &'static str, message: String },
+
+    #[error("Configuration error: {message}")]
+    ConfigError { code: &'static str, message: String },
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -37,6 +42,7 @@ impl CompileError {
     /// Create a parse error with location
     pub fn parse(message: impl Into<String>, line: usize, column: usize) -> Self {
         CompileError::ParseError {
+            code: "E0002",
             message: message.into(),
             location: SourceLocation::new(line, column),
             snippet: None,
@@ -51,15 +57,17 @@ impl CompileError {
         snippet: impl Into<String>,
     ) -> Self {
         CompileError::ParseError {
+            code: "E0001",
             message: message.into(),
             location: SourceLocation::new(line, column),
             snippet: Some(snippet.into()),
         }
     }
 
-    /// Create a validation error
-    pub fn validation(message: impl Into<String>, location: SourceLocation) -> Self {
+    /// Create a validation error with its diagnostic code
+    pub fn validation(code: &'static str, message: impl Into<String>, location: SourceLocation) -> Self {
         CompileError::ValidationError {
+            code,
             message: message.into(),
             location,
             hint: None,
@@ -68,11 +76,13 @@ impl CompileError {
 
     /// Create a validation error with a hint
     pub fn validation_with_hint(
+        code: &'static str,
         message: impl Into<String>,
         location: SourceLocation,
         hint: impl Into<String>,
     ) -> Self {
         CompileError::ValidationError {
+            code,
             message: message.into(),
             location,
             hint: Some(hint.into()),
@@ -82,57 +92,82 @@ impl CompileError {
     /// Create a code generation error
     pub fn codegen(message: impl Into<String>) -> Self {
         CompileError::CodeGenError {
+            code: "E0901",
             message: message.into(),
         }
     }
 
+    /// Create an error for generated files that were edited since the last compilation
+    pub fn edited_output(message: impl Into<String>) -> Self {
+        CompileError::CodeGenError {
+            code: "E0904",
+            message: message.into(),
+        }
+    }
 
-    /// Format error for terminal output with colors
-    pub fn format_colored(&self, source: Option<&str>) -> String {
+    /// Create an error for a migration that would lose data without acknowledgement
+    pub fn destructive_migration(message: impl Into<String>) -> Self {
+        CompileError::CodeGenError {
+            code: "E0905",
+            message: message.into(),
+        }
+    }
+
+    /// Create a project configuration error
+    pub fn config(message: impl Into<String>) -> Self {
+        CompileError::ConfigError {
+            code: "E0903",
+            message: message.into(),
+        }
+    }
+
+    /// Source location of the error, if it has one
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            CompileError::ParseError { location, .. } | CompileError::ValidationError { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Format error for terminal output with colors, positioned within its input file
+    pub fn format_colored(&self, source: Option<&str>, sources: &SourceMap) -> String {
         match self {
             CompileError::ParseError {
+                code,
                 message,
                 location,
                 snippet,
             } => {
                 let mut output = format!(
                     "{}: {}\n",
-                    "error".red().bold(),
+                    format!("error[{}]", code).red().bold(),
                     message.white().bold()
                 );
-                output.push_str(&format!(
-                    "  {} {}:{}\n",
-                    "-->".blue().bold(),
-                    location.line,
-                    location.column
-                ));
+                let (file, location) = sources.locate(location);
+                output.push_str(&format_location(file, &location));
 
                 if let Some(snip) = snippet {
                     output.push_str(&format_snippet(snip, location.line, location.column));
-                } else if let Some(src) = source {
-                    if let Some(line_content) = src.lines().nth(location.line.saturating_sub(1)) {
-                        output.push_str(&format_snippet(line_content, location.line, location.column));
-                    }
+                } else if let Some(src) = source
+                    && let Some(line_content) = src.lines().nth(location.line.saturating_sub(1)) {
+                    output.push_str(&format_snippet(line_content, location.line, location.column));
                 }
 
                 output
             }
             CompileError::ValidationError {
+                code,
                 message,
                 location,
                 hint,
             } => {
                 let mut output = format!(
                     "{}: {}\n",
-                    "error".red().bold(),
+                    format!("error[{}]", code).red().bold(),
                     message.white().bold()
                 );
-                output.push_str(&format!(
-                    "  {} {}:{}\n",
-                    "-->".blue().bold(),
-                    location.line,
-                    location.column
-                ));
+                let (file, location) = sources.locate(location);
+                output.push_str(&format_location(file, &location));
 
                 if let Some(h) = hint {
                     output.push_str(&format!(
@@ -144,10 +179,10 @@ impl CompileError {
 
                 output
             }
-            CompileError::CodeGenError { message } => {
+            CompileError::CodeGenError { code, message } | CompileError::ConfigError { code, message } => {
                 format!(
                     "{}: {}\n",
-                    "error".red().bold(),
+                    format!("error[{}]", code).red().bold(),
                     message.white().bold()
                 )
             }
@@ -164,7 +199,7 @@ impl CompileError {
                     if i > 0 {
                         output.push('\n');
                     }
-                    output.push_str(&err.format_colored(source));
+                    output.push_str(&err.format_colored(source, sources));
                 }
                 output.push_str(&format!(
                     "\n{}: {} errors generated\n",
@@ -177,6 +212,15 @@ impl CompileError {
     }
 }
 
+/// Format the `--> file:line:col` line of a diagnostic
+pub fn format_location(file: Option<&str>, location: &SourceLocation) -> String {
+    let position = match file {
+        Some(file) => format!("{}:{}:{}", file, location.line, location.column),
+        None => format!("{}:{}", location.line, location.column),
+    };
+    format!("  {} {}\n", "-->".blue().bold(), position)
+}
+
 /// Format a code snippet with line number and pointer
 fn format_snippet(line_content: &str, line_num: usize, column: usize) -> String {
     let line_num_str = line_num.to_string();
@@ -206,32 +250,35 @@ pub type CompileResult<T> = Result<T, CompileError>;
 /// Validation warning (non-fatal)
 #[derive(Debug, Clone)]
 pub struct Warning {
+    pub code: &'static str,
     pub message: String,
     pub location: SourceLocation,
     pub hint: Option<String>,
 }
 
 impl Warning {
-    pub fn with_hint(message: impl Into<String>, location: SourceLocation, hint: impl Into<String>) -> Self {
+    pub fn with_hint(
+        code: &'static str,
+        message: impl Into<String>,
+        location: SourceLocation,
+        hint: impl Into<String>,
+    ) -> Self {
         Self {
+            code,
             message: message.into(),
             location,
             hint: Some(hint.into()),
         }
     }
 
-    pub fn format_colored(&self) -> String {
+    pub fn format_colored(&self, sources: &SourceMap) -> String {
         let mut output = format!(
             "{}: {}\n",
-            "warning".yellow().bold(),
+            format!("warning[{}]", self.code).yellow().bold(),
             self.message.white()
         );
-        output.push_str(&format!(
-            "  {} {}:{}\n",
-            "-->".blue().bold(),
-            self.location.line,
-            self.location.column
-        ));
+        let (file, location) = sources.locate(&self.location);
+        output.push_str(&format_location(file, &location));
 
         if let Some(h) = &self.hint {
             output.push_str(&format!(
@@ -244,3 +291,126 @@ impl Warning {
         output
     }
 }
+
+/// Input files concatenated into one compilation unit, for mapping positions back to them
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug, Clone)]
+struct SourceFile {
+    name: String,
+    first_line: usize,
+    first_byte: usize,
+}
+
+impl SourceMap {
+    /// Append a file's text to the compilation unit `source`, remembering where it starts
+    pub fn push(&mut self, name: impl Into<String>, text: &str, source: &mut String) {
+        if !source.is_empty() && !source.ends_with('\n') {
+            source.push('\n');
+        }
+        self.files.push(SourceFile {
+            name: name.into(),
+            first_line: source.lines().count() + 1,
+            first_byte: source.len(),
+        });
+        source.push_str(text);
+    }
+
+    /// The file containing a position in the compilation unit, and the position within that file.
+    /// Unpositioned diagnostics (line 0) belong to the input only when there is exactly one.
+    pub fn locate(&self, location: &SourceLocation) -> (Option<&str>, SourceLocation) {
+        let file = if location.line == 0 {
+            self.files.first().filter(|_| self.files.len() == 1)
+        } else {
+            self.files.iter().rev().find(|f| f.first_line <= location.line)
+        };
+        let Some(file) = file else { return (None, location.clone()) };
+        let local = SourceLocation {
+            line: location.line.saturating_sub(file.first_line - 1),
+            column: location.column,
+            span: location
+                .span
+                .map(|(start, end)| (start.saturating_sub(file.first_byte), end.saturating_sub(file.first_byte))),
+        };
+        (Some(&file.name), local)
+    }
+}
+
+/// Sink collecting every error and warning produced by a compiler pass
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    errors: Vec<CompileError>,
+    warnings: Vec<Warning>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an error, flattening nested multi-error results
+    pub fn error(&mut self, error: CompileError) {
+        match error {
+            CompileError::MultipleErrors(errors) => {
+                for error in errors {
+                    self.error(error);
+                }
+            }
+            other => self.errors.push(other),
+        }
+    }
+
+    pub fn warning(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Record the error of a failed check, returning the value of a successful one
+    pub fn check<T>(&mut self, result: CompileResult<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.error(error);
+                None
+            }
+        }
+    }
+
+    /// Take the collected warnings, deduplicated and sorted by location
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        let mut seen = std::collections::HashSet::new();
+        let mut warnings: Vec<Warning> = std::mem::take(&mut self.warnings)
+            .into_iter()
+            .filter(|w| seen.insert((w.message.clone(), w.location.line, w.location.column)))
+            .collect();
+        warnings.sort_by_key(|w| (w.location.line, w.location.column));
+        warnings
+    }
+
+    /// Finish the pass: `Ok(value)` if no errors were recorded, otherwise the
+    /// deduplicated errors sorted by location
+    pub fn into_result<T>(self, value: T) -> CompileResult<T> {
+        let mut seen = std::collections::HashSet::new();
+        let mut errors: Vec<CompileError> = self
+            .errors
+            .into_iter()
+            .filter(|e| {
+                let position = e.location().map(|l| (l.line, l.column));
+                seen.insert((e.to_string(), position))
+            })
+            .collect();
+        // Errors without a location (codegen, io) go last
+        errors.sort_by_key(|e| match e.location() {
+            Some(l) => (0, l.line, l.column),
+            None => (1, 0, 0),
+        });
+
+        match errors.len() {
+            0 => Ok(value),
+            1 => Err(errors.remove(0)),
+            _ => Err(CompileError::MultipleErrors(errors)),
+        }
+    }
+}
@@ -0,0 +1,473 @@
+// Intent Compiler - Formatter
+// Prints a parsed intent file back in canonical style, keeping its comments
+
+use std::collections::VecDeque;
+
+use crate::ast::{
+    Action, Consequence, Decorator, DeriveValue, Entity, Expression, Field, FieldReference, FieldType, FunctionArg,
+    IntentFile, LiteralValue, LogicalOperator, MapTransform, Policy, Predicate, ProcessStep, Rule,
+    ValidationConstraints,
+};
+use crate::error::CompileResult;
+use crate::parser::parse_intent;
+
+const INDENT: &str = "    ";
+const INDENT2: &str = "        ";
+const INDENT3: &str = "            ";
+
+/// Format intent source canonically. Formatting formatted source changes nothing.
+pub fn format_source(source: &str) -> CompileResult<String> {
+    let file = parse_intent(source)?;
+    Ok(Formatter::new(source).file(&file))
+}
+
+/// A `#` comment and the line it was found on
+struct Comment {
+    line: usize,
+    text: String,
+    /// Indented or following code, so it belongs inside the block above it
+    indented: bool,
+}
+
+/// Comments outside string literals, in source order
+fn comments(source: &str) -> VecDeque<Comment> {
+    let mut comments = VecDeque::new();
+    // String literals may span lines
+    let mut in_string = false;
+    for (index, line) in source.lines().enumerate() {
+        for (column, c) in line.char_indices() {
+            match c {
+                '"' => in_string = !in_string,
+                '#' if !in_string => {
+                    let text = line[column..].trim_end().to_string();
+                    comments.push_back(Comment { line: index + 1, text, indented: column > 0 });
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+    comments
+}
+
+/// What was last written at the top level, with its source line
+#[derive(Clone, Copy)]
+enum Item {
+    Comment(usize),
+    Definition,
+}
+
+/// A top-level definition, so all kinds can be written in source order
+enum Definition<'a> {
+    Entity(&'a Entity),
+    Action(&'a Action),
+    Rule(&'a Rule),
+    Policy(&'a Policy),
+}
+
+/// Fields and nested policies may be interleaved in an entity
+enum EntityItem<'a> {
+    Field(&'a Field),
+    Policy(&'a Policy),
+}
+
+impl Definition<'_> {
+    fn line(&self) -> usize {
+        match self {
+            Definition::Entity(e) => e.location.line,
+            Definition::Action(a) => a.location.line,
+            Definition::Rule(r) => r.location.line,
+            Definition::Policy(p) => p.location.line,
+        }
+    }
+}
+
+struct Formatter<'a> {
+    lines: Vec<&'a str>,
+    comments: VecDeque<Comment>,
+    out: String,
+    last: Option<Item>,
+    /// Line of the definition after the one being written
+    next: usize,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { lines: source.lines().collect(), comments: comments(source), out: String::new(), last: None, next: usize::MAX }
+    }
+
+    fn file(mut self, file: &IntentFile) -> String {
+        let mut definitions: Vec<_> = file
+            .entities
+            .iter()
+            .map(Definition::Entity)
+            .chain(file.actions.iter().map(Definition::Action))
+            .chain(file.rules.iter().map(Definition::Rule))
+            .chain(file.policies.iter().map(Definition::Policy))
+            .collect();
+        definitions.sort_by_key(Definition::line);
+
+        for (i, definition) in definitions.iter().enumerate() {
+            let line = definition.line();
+            self.top_level_comments(line);
+            self.separate(Item::Definition, line);
+            self.next = definitions.get(i + 1).map_or(usize::MAX, Definition::line);
+            match definition {
+                Definition::Entity(entity) => self.entity(entity),
+                Definition::Action(action) => self.action(action),
+                Definition::Rule(rule) => self.rule(rule),
+                Definition::Policy(policy) => self.policy(policy, ""),
+            }
+        }
+        self.top_level_comments(usize::MAX);
+        self.out
+    }
+
+    fn write(&mut self, indent: &str, line: &str) {
+        self.out.push_str(indent);
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    /// Comments up to `line` (including trailing ones on it), written before a block item
+    fn comments_before(&mut self, line: usize, indent: &str) {
+        while self.comments.front().is_some_and(|c| c.line <= line) {
+            let comment = self.comments.pop_front().unwrap();
+            self.write(indent, &comment.text);
+        }
+    }
+
+    /// Indented comments after the last item of a block stay at its end; unindented ones
+    /// introduce the next definition
+    fn block_end(&mut self, indent: &str) {
+        while self.comments.front().is_some_and(|c| c.indented && c.line < self.next) {
+            let comment = self.comments.pop_front().unwrap();
+            self.write(indent, &comment.text);
+        }
+    }
+
+    /// Top-level comments up to `line`, keeping the blank lines between them
+    fn top_level_comments(&mut self, line: usize) {
+        while self.comments.front().is_some_and(|c| c.line <= line) {
+            let comment = self.comments.pop_front().unwrap();
+            self.separate(Item::Comment(comment.line), comment.line);
+            self.write("", &comment.text);
+        }
+    }
+
+    /// Blank line before a top-level item: always after a definition, after a comment only
+    /// if the source had one
+    fn separate(&mut self, item: Item, line: usize) {
+        let blank = match self.last {
+            None => false,
+            Some(Item::Definition) => true,
+            Some(Item::Comment(previous)) => {
+                previous + 1 < line && self.lines[previous..line - 1].iter().any(|l| l.trim().is_empty())
+            }
+        };
+        if blank {
+            self.out.push('\n');
+        }
+        self.last = Some(item);
+    }
+
+    fn entity(&mut self, entity: &Entity) {
+        if let Some(old) = &entity.renamed_from {
+            self.write("", &format!("@renamed_from(\"{}\")", old));
+        }
+        for columns in &entity.indexes {
+            self.write("", &format!("@index({})", columns.join(", ")));
+        }
+        let keyword = if entity.is_auth { "auth entity" } else { "entity" };
+        self.write("", &format!("{} {}:", keyword, entity.name));
+
+        let mut items: Vec<_> = entity
+            .fields
+            .iter()
+            .map(|f| (f.location.line, EntityItem::Field(f)))
+            .chain(entity.policies.iter().map(|p| (p.location.line, EntityItem::Policy(p))))
+            .collect();
+        items.sort_by_key(|(line, _)| *line);
+        for (line, item) in items {
+            self.comments_before(line, INDENT);
+            match item {
+                EntityItem::Field(field) => {
+                    self.write(INDENT, &declaration(&field.name, &field.field_type, &field.decorators));
+                }
+                EntityItem::Policy(policy) => {
+                    self.policy(policy, INDENT);
+                }
+            }
+        }
+        self.block_end(INDENT);
+    }
+
+    fn action(&mut self, action: &Action) {
+        for decorator in sorted(&action.decorators) {
+            self.write("", &decorator_source(decorator, None));
+        }
+        self.write("", &format!("action {}:", action.name));
+
+        if let Some(input) = &action.input {
+            self.write(INDENT, "input:");
+            for param in &input.fields {
+                self.comments_before(param.location.line, INDENT2);
+                self.write(INDENT2, &declaration(&param.name, &param.param_type, &param.decorators));
+            }
+        }
+        if let Some(process) = &action.process {
+            self.write(INDENT, "process:");
+            for step in &process.steps {
+                match step {
+                    ProcessStep::Derive(derive) => {
+                        self.comments_before(derive.location.line, INDENT2);
+                        self.write(INDENT2, &format!("derive {} = {}", derive.name, derive_value(&derive.value)));
+                    }
+                    ProcessStep::Mutate(mutate) => {
+                        self.comments_before(mutate.location.line, INDENT2);
+                        let filter = mutate.predicate.as_ref().map(|p| format!(" where {}", predicate(p))).unwrap_or_default();
+                        self.write(INDENT2, &format!("mutate {}{}:", mutate.entity, filter));
+                        for setter in &mutate.setters {
+                            self.comments_before(setter.location.line, INDENT3);
+                            self.write(INDENT3, &format!("set {} = {}", setter.field, derive_value(&setter.value)));
+                        }
+                    }
+                    ProcessStep::Delete(delete) => {
+                        self.comments_before(delete.location.line, INDENT2);
+                        self.write(INDENT2, &format!("delete {} where {}", delete.entity, predicate(&delete.predicate)));
+                    }
+                }
+            }
+        }
+        // Comments can't precede `output:`, so they end the section before it
+        if action.input.is_some() || action.process.is_some() {
+            self.block_end(INDENT2);
+        }
+        if let Some(output) = &action.output {
+            self.write(INDENT, &format!("output: {}({})", output.entity, output.fields.join(", ")));
+        }
+    }
+
+    fn rule(&mut self, rule: &Rule) {
+        self.write("", &format!("rule {}:", rule.name));
+        self.write(INDENT, &format!("when {}", expression(&rule.condition)));
+        self.write(INDENT, &format!("then {}", consequence(&rule.consequence)));
+    }
+
+    /// A top-level policy, or one nested in an entity at `indent`
+    fn policy(&mut self, policy: &Policy, indent: &str) {
+        let body = format!("{}{}", indent, INDENT);
+        self.write(indent, &format!("policy {}:", policy.name));
+        self.write(&body, &format!("subject: {}", policy.subject));
+        self.write(&body, &format!("require {}", expression(&policy.require)));
+    }
+}
+
+/// Decorators in canonical order: routing and access first, then keys, then the rest
+fn sorted(decorators: &[Decorator]) -> Vec<&Decorator> {
+    let rank = |d: &Decorator| match d {
+        Decorator::Api { .. } => 0,
+        Decorator::Auth { .. } => 1,
+        Decorator::Policy(_) => 2,
+        Decorator::Primary => 3,
+        Decorator::Unique => 4,
+        Decorator::Index => 5,
+        Decorator::Optional => 6,
+        Decorator::Auto => 7,
+        Decorator::Default(_) => 8,
+        Decorator::Validate(_) => 9,
+        Decorator::Map { .. } => 10,
+        Decorator::Expose => 11,
+        Decorator::RenamedFrom(_) => 12,
+    };
+    let mut sorted: Vec<_> = decorators.iter().collect();
+    sorted.sort_by_key(|d| rank(d));
+    sorted
+}
+
+/// `name: type @decorators` of a field or input
+pub fn declaration(name: &str, ty: &FieldType, decorators: &[Decorator]) -> String {
+    let decorators: String = sorted(decorators)
+        .into_iter()
+        .map(|d| decorator_source(d, Some(ty)))
+        .filter(|d| !d.is_empty())
+        .map(|d| format!(" {}", d))
+        .collect();
+    format!("{}: {}{}", name, ty, decorators)
+}
+
+/// Source of one decorator; empty for a `@validate` whose arguments were all invalid
+pub fn decorator_source(decorator: &Decorator, ty: Option<&FieldType>) -> String {
+    match decorator {
+        Decorator::Primary => "@primary".to_string(),
+        Decorator::Unique => "@unique".to_string(),
+        Decorator::Optional => "@optional".to_string(),
+        Decorator::Auto => "@auto".to_string(),
+        Decorator::Index => "@index".to_string(),
+        Decorator::Expose => "@expose".to_string(),
+        Decorator::Default(value) => format!("@default({})", default_value(value, ty)),
+        Decorator::Validate(constraints) => validate(constraints),
+        Decorator::Api { method, path } => format!("@api {} {}", method, path),
+        Decorator::Auth { name: None, .. } => "@auth".to_string(),
+        Decorator::Auth { name: Some(name), args } if args.is_empty() => format!("@auth({})", name),
+        Decorator::Auth { name: Some(name), args } => format!("@auth({}({}))", name, args.join(", ")),
+        Decorator::Map { target, transform: MapTransform::None } => format!("@map({})", target),
+        Decorator::Map { target, transform: MapTransform::Hash } => format!("@map({}, hash)", target),
+        Decorator::Policy(name) => format!("@policy({})", name),
+        Decorator::RenamedFrom(old) => format!("@renamed_from(\"{}\")", old),
+    }
+}
+
+/// The parser drops the quotes of `@default("...")`, so quote whatever isn't a keyword of the type
+fn default_value(value: &str, ty: Option<&FieldType>) -> String {
+    let ty = match ty {
+        Some(FieldType::Optional(inner)) => Some(inner.as_ref()),
+        other => other,
+    };
+    let bare = match ty {
+        Some(FieldType::Number) => is_number(value),
+        Some(FieldType::Boolean) => value == "true" || value == "false",
+        Some(FieldType::DateTime) => value == "now",
+        Some(FieldType::Uuid) => value == "uuid",
+        Some(FieldType::Enum(values)) => values.iter().any(|v| v == value),
+        _ => false,
+    };
+    if bare { value.to_string() } else { format!("\"{}\"", value) }
+}
+
+/// Whether `value` is a number literal of the grammar
+fn is_number(value: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    match unsigned.split_once('.') {
+        Some((whole, fraction)) => digits(whole) && digits(fraction),
+        None => digits(unsigned),
+    }
+}
+
+fn validate(constraints: &ValidationConstraints) -> String {
+    let mut args = Vec::new();
+    if let Some(min) = constraints.min {
+        args.push(format!("min: {}", min));
+    }
+    if let Some(max) = constraints.max {
+        args.push(format!("max: {}", max));
+    }
+    if let Some(pattern) = &constraints.pattern {
+        args.push(format!("pattern: \"{}\"", pattern));
+    }
+    if let Some(required) = constraints.required {
+        args.push(format!("required: {}", required));
+    }
+    if args.is_empty() { String::new() } else { format!("@validate({})", args.join(", ")) }
+}
+
+fn literal(value: &LiteralValue) -> String {
+    match value {
+        LiteralValue::String(s) => format!("\"{}\"", s),
+        LiteralValue::Number(n) => n.to_string(),
+        LiteralValue::Boolean(b) => b.to_string(),
+    }
+}
+
+fn derive_value(value: &DeriveValue) -> String {
+    let args = |args: &[FunctionArg]| args.iter().map(function_arg).collect::<Vec<_>>().join(", ");
+    match value {
+        DeriveValue::Literal(value) => literal(value),
+        DeriveValue::FieldAccess { path } => path.join("."),
+        DeriveValue::Identifier(name) => name.clone(),
+        DeriveValue::Compute { function, args: a } => format!("compute {}({})", function, args(a)),
+        DeriveValue::Select { entity, predicate: p } => format!("select {} where {}", entity, predicate(p)),
+        DeriveValue::SystemCall { namespace, capability, args: a } => {
+            format!("system {}.{}({})", namespace, capability, args(a))
+        }
+    }
+}
+
+fn function_arg(arg: &FunctionArg) -> String {
+    match arg {
+        FunctionArg::TypeName(name) | FunctionArg::Identifier(name) => name.clone(),
+        FunctionArg::FieldAccess { path } => path.join("."),
+        FunctionArg::Literal(value) => literal(value),
+    }
+}
+
+/// `column == value`; a bare input name on the right is spelled `input.name`
+fn predicate(predicate: &Predicate) -> String {
+    let reference = |reference: &FieldReference, value_side: bool| match reference {
+        FieldReference::InputField(name) if value_side => format!("input.{}", name),
+        FieldReference::InputField(name) => name.clone(),
+        FieldReference::DerivedField { name, field } => format!("{}.{}", name, field),
+        FieldReference::Literal(value) => literal(value),
+    };
+    format!("{} {} {}", reference(&predicate.field, false), predicate.operator, reference(&predicate.value, true))
+}
+
+fn consequence(consequence: &Consequence) -> String {
+    match consequence {
+        Consequence::Reject(message) => format!("reject(\"{}\")", message),
+        Consequence::Log(message) => format!("log(\"{}\")", message),
+        Consequence::ActionCall { action, args } => {
+            format!("{}({})", action, args.iter().map(expression).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+pub fn expression(expr: &Expression) -> String {
+    precedence_expression(expr, 0)
+}
+
+/// Binding strength: or < and < not < comparison < operand. Parentheses are only kept
+/// where `expr` binds looser than its position requires.
+fn precedence_expression(expr: &Expression, min: u8) -> String {
+    let (level, source) = match expr {
+        Expression::Logical { left, operator: LogicalOperator::Or, right } => {
+            (0, format!("{} or {}", precedence_expression(left, 0), precedence_expression(right, 1)))
+        }
+        Expression::Logical { left, operator: LogicalOperator::And, right } => {
+            (1, format!("{} and {}", precedence_expression(left, 1), precedence_expression(right, 2)))
+        }
+        Expression::Not(inner) => (2, format!("not {}", precedence_expression(inner, 3))),
+        Expression::Binary { left, operator, right } => {
+            (3, format!("{} {} {}", precedence_expression(left, 4), operator, precedence_expression(right, 4)))
+        }
+        Expression::FieldAccess { entity, field } => (4, format!("{}.{}", entity, field)),
+        Expression::Literal(value) => (4, literal(value)),
+        Expression::Identifier(name) => (4, name.clone()),
+    };
+    if level < min { format!("({})", source) } else { source }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatting_is_idempotent() {
+        let source = std::fs::read_to_string("examples/app.intent").unwrap();
+        let formatted = format_source(&source).unwrap();
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+        // The example is already canonical
+        assert_eq!(formatted, source);
+    }
+
+    #[test]
+    fn test_canonical_spacing_and_decorator_order() {
+        let source = "entity Task:\n\n    id: uuid @default(uuid)   @primary\n    status: open|done @default(\"open\")\n\n\n\
+            @auth\n@api GET /tasks\naction list_tasks:\n    output: Task(id,status)\nrule R:\n    when (Task.status == open and not Task.id == \"x\") or Task.id != \"y\"\n    then reject(\"no\")\n";
+        assert_eq!(format_source(source).unwrap(), "entity Task:\n    id: uuid @primary @default(uuid)\n    status: open | done @default(open)\n\n\
+            @api GET /tasks\n@auth\naction list_tasks:\n    output: Task(id, status)\n\nrule R:\n    when Task.status == open and not Task.id == \"x\" or Task.id != \"y\"\n    then reject(\"no\")\n");
+    }
+
+    #[test]
+    fn test_comments_are_kept_in_place() {
+        let source = "# Header\n\n# Tasks\nentity Task:#trailing\n# the key\n    id: uuid @primary\n    # end of fields\n\n\
+            action close:\n    input:\n        # which one\n        id: uuid\n    process:\n        mutate Task where id == input.id:\n\
+            # new state\n            set id = input.id\n        # done\n    output: Task(id)\n# Footer\n";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, "# Header\n\n# Tasks\n#trailing\nentity Task:\n    # the key\n    id: uuid @primary\n    # end of fields\n\n\
+            action close:\n    input:\n        # which one\n        id: uuid\n    process:\n        mutate Task where id == input.id:\n\
+            \x20           # new state\n            set id = input.id\n        # done\n    output: Task(id)\n\n# Footer\n");
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+}
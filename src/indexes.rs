@@ -0,0 +1,280 @@
+// Intent Compiler - Index Advisor
+// Finds query predicates on unindexed columns, composite index candidates and N+1 list outputs
+
+use std::collections::HashMap;
+
+use crate::ast::*;
+
+/// A column lookup performed by generated code
+#[derive(Debug, Clone)]
+struct Lookup {
+    entity: String,
+    column: String,
+    action: String,
+    location: SourceLocation,
+}
+
+/// An index the advisor recommends
+#[derive(Debug, Clone)]
+pub struct IndexSuggestion {
+    pub entity: String,
+    pub columns: Vec<String>,
+    /// Action whose query needs the index
+    pub action: String,
+    pub location: SourceLocation,
+}
+
+/// A list endpoint that resolves a related entity per row
+#[derive(Debug, Clone)]
+pub struct NPlusOne {
+    pub action: String,
+    pub entity: String,
+    pub field: String,
+    pub related: String,
+    pub location: SourceLocation,
+}
+
+/// Every predicate column used by select, mutate and delete, in source order
+fn lookups(file: &IntentFile) -> Vec<Lookup> {
+    let mut found = Vec::new();
+    for action in &file.actions {
+        for step in action.process.iter().flat_map(|p| &p.steps) {
+            let (entity, predicate, location) = match step {
+                ProcessStep::Derive(DeriveStatement { value: DeriveValue::Select { entity, predicate }, location, .. }) => {
+                    (entity, predicate, location)
+                }
+                ProcessStep::Mutate(MutateBlock { entity, predicate: Some(predicate), location, .. }) => {
+                    (entity, predicate, location)
+                }
+                ProcessStep::Delete(DeleteStatement { entity, predicate, location }) => (entity, predicate, location),
+                _ => continue,
+            };
+            let column = match &predicate.field {
+                FieldReference::InputField(name) | FieldReference::DerivedField { field: name, .. } => name,
+                FieldReference::Literal(_) => continue,
+            };
+            found.push(Lookup {
+                entity: entity.clone(),
+                column: column.clone(),
+                action: action.name.clone(),
+                location: location.clone(),
+            });
+        }
+    }
+    found
+}
+
+/// Whether a lookup on `column` can use an existing index
+fn is_indexed(entity: &Entity, column: &str) -> bool {
+    let declared = entity.fields.iter().any(|f| {
+        f.name == column
+            && f.decorators
+                .iter()
+                .any(|d| matches!(d, Decorator::Primary | Decorator::Unique | Decorator::Index))
+    });
+    // A composite index serves lookups on its leading column
+    declared || entity.indexes.iter().any(|cols| cols.first().is_some_and(|c| c == column))
+}
+
+/// Columns filtered by a predicate that have no index, one suggestion per column
+pub fn unindexed_lookups(file: &IntentFile) -> Vec<IndexSuggestion> {
+    let mut suggestions: Vec<IndexSuggestion> = Vec::new();
+    for lookup in lookups(file) {
+        let Some(entity) = file.find_entity(&lookup.entity) else { continue };
+        let exists = entity.fields.iter().any(|f| f.name == lookup.column);
+        let seen = suggestions
+            .iter()
+            .any(|s| s.entity == lookup.entity && s.columns == [lookup.column.clone()]);
+        if exists && !seen && !is_indexed(entity, &lookup.column) {
+            suggestions.push(IndexSuggestion {
+                entity: lookup.entity,
+                columns: vec![lookup.column],
+                action: lookup.action,
+                location: lookup.location,
+            });
+        }
+    }
+    suggestions
+}
+
+/// Whether a field points at another record
+fn is_foreign_key(field: &Field) -> bool {
+    matches!(field.field_type, FieldType::Reference(_) | FieldType::Ref(_))
+        || (field.name.ends_with("_id") && !field.decorators.contains(&Decorator::Primary))
+}
+
+/// Association-style entities looked up by two or more foreign keys benefit from one
+/// composite index over them, e.g. CartItem(user_id, product_id)
+pub fn composite_suggestions(file: &IntentFile) -> Vec<IndexSuggestion> {
+    let mut by_entity: HashMap<&str, Vec<Lookup>> = HashMap::new();
+    let all = lookups(file);
+    for lookup in &all {
+        let columns = by_entity.entry(lookup.entity.as_str()).or_default();
+        if !columns.iter().any(|l| l.column == lookup.column) {
+            columns.push(lookup.clone());
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    for entity in &file.entities {
+        let Some(used) = by_entity.get(entity.name.as_str()) else { continue };
+        let keys: Vec<&Lookup> = used
+            .iter()
+            .filter(|l| entity.fields.iter().any(|f| f.name == l.column && is_foreign_key(f)))
+            .collect();
+        if keys.len() < 2 {
+            continue;
+        }
+        let columns: Vec<String> = keys.iter().map(|l| l.column.clone()).collect();
+        // Equality lookups on every column are served whatever order the index lists them in
+        if entity.indexes.iter().any(|index| index.len() == columns.len() && columns.iter().all(|c| index.contains(c))) {
+            continue;
+        }
+        suggestions.push(IndexSuggestion {
+            entity: entity.name.clone(),
+            columns,
+            action: keys[1].action.clone(),
+            location: keys[1].location.clone(),
+        });
+    }
+    suggestions
+}
+
+/// The entity a field type points at, looking through optional and collection wrappers
+fn related_entity(field_type: &FieldType) -> Option<&str> {
+    match field_type {
+        FieldType::Reference(name) | FieldType::Ref(name) => Some(name),
+        FieldType::Optional(inner) | FieldType::Array(inner) | FieldType::List(inner) => related_entity(inner),
+        _ => None,
+    }
+}
+
+/// List endpoints whose output projects a relation, loading it once per returned row
+pub fn n_plus_one(file: &IntentFile) -> Vec<NPlusOne> {
+    let mut found = Vec::new();
+    for action in &file.actions {
+        let is_list = action.decorators.iter().any(|d| {
+            matches!(d, Decorator::Api { method: HttpMethod::Get, path } if !path.contains('{'))
+        });
+        let Some(output) = action.output.as_ref().filter(|_| is_list) else { continue };
+        let Some(entity) = file.find_entity(&output.entity) else { continue };
+        for name in &output.fields {
+            let Some(field) = entity.fields.iter().find(|f| &f.name == name) else { continue };
+            if let Some(related) = related_entity(&field.field_type) {
+                found.push(NPlusOne {
+                    action: action.name.clone(),
+                    entity: entity.name.clone(),
+                    field: field.name.clone(),
+                    related: related.to_string(),
+                    location: action.location.clone(),
+                });
+            }
+        }
+    }
+    found
+}
+
+/// Add every suggested index to the AST so the ORM and migrations create it.
+/// Returns the indexes that were added.
+pub fn apply_suggestions(file: &mut IntentFile) -> Vec<IndexSuggestion> {
+    // Composite indexes first: they also serve lookups on their leading column
+    let composite = composite_suggestions(file);
+    for suggestion in &composite {
+        if let Some(entity) = file.entities.iter_mut().find(|e| e.name == suggestion.entity) {
+            entity.indexes.push(suggestion.columns.clone());
+        }
+    }
+
+    let single = unindexed_lookups(file);
+    for suggestion in &single {
+        let field = file
+            .entities
+            .iter_mut()
+            .filter(|e| e.name == suggestion.entity)
+            .flat_map(|e| e.fields.iter_mut())
+            .find(|f| f.name == suggestion.columns[0]);
+        if let Some(field) = field {
+            field.decorators.push(Decorator::Index);
+        }
+    }
+
+    composite.into_iter().chain(single).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+
+    const SHOP: &str = r#"auth entity User:
+    id: uuid @primary
+    email: email @unique
+    password_hash: string
+
+entity Product:
+    id: uuid @primary
+    sku: string
+
+entity CartItem:
+    id: uuid @primary
+    user_id: uuid
+    product_id: uuid
+    product: Product
+    quantity: number
+
+@api GET /products/by-sku
+action find_product:
+    input:
+        sku: string
+    process:
+        derive product = select Product where sku == input.sku
+    output: Product(id, sku)
+
+@api POST /cart/remove
+@auth
+action remove_item:
+    input:
+        product_id: uuid
+    process:
+        delete CartItem where user_id == current_user.id
+        delete CartItem where product_id == input.product_id
+    output: CartItem(id)
+
+@api GET /cart
+@auth
+action list_cart:
+    output: CartItem(id, product, quantity)
+"#;
+
+    #[test]
+    fn test_unindexed_and_composite_suggestions() {
+        let file = parse_intent(SHOP).unwrap();
+        let single: Vec<_> = unindexed_lookups(&file)
+            .into_iter()
+            .map(|s| format!("{}.{}", s.entity, s.columns.join(",")))
+            .collect();
+        assert_eq!(single, vec!["Product.sku", "CartItem.user_id", "CartItem.product_id"]);
+
+        let composite = composite_suggestions(&file);
+        assert_eq!(composite.len(), 1);
+        assert_eq!(composite[0].columns, vec!["user_id", "product_id"]);
+    }
+
+    #[test]
+    fn test_n_plus_one_on_list_outputs() {
+        let file = parse_intent(SHOP).unwrap();
+        let found = n_plus_one(&file);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].action.as_str(), found[0].field.as_str()), ("list_cart", "product"));
+    }
+
+    #[test]
+    fn test_apply_suggestions_clears_findings() {
+        let mut file = parse_intent(SHOP).unwrap();
+        // The composite index covers user_id, leaving product_id and sku
+        assert_eq!(apply_suggestions(&mut file).len(), 3);
+        assert!(unindexed_lookups(&file).is_empty());
+        assert!(composite_suggestions(&file).is_empty());
+        assert_eq!(file.entities[2].indexes, vec![vec!["user_id".to_string(), "product_id".to_string()]]);
+    }
+}
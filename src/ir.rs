@@ -0,0 +1,521 @@
+// Intent Compiler - Intermediate Representation
+// Resolved program model lowered from a validated AST and consumed by every code generator
+
+use serde::Serialize;
+
+use crate::ast::*;
+use crate::validator::{self, ValidationContext, ValueType};
+
+/// A validated intent file with every action's semantics resolved once
+#[derive(Debug, Clone, Serialize)]
+pub struct Program {
+    pub entities: Vec<Entity>,
+    pub actions: Vec<ActionIr>,
+    pub rules: Vec<Rule>,
+    pub policies: Vec<Policy>,
+    pub auth_entity: Option<String>,
+}
+
+/// An action with its owner, route, cardinality and data flow resolved
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionIr {
+    pub name: String,
+    /// Entity whose service and router own the action
+    pub entity: Option<String>,
+    pub route: Option<Route>,
+    pub cardinality: Cardinality,
+    pub kind: ActionKind,
+    pub requires_auth: bool,
+    /// Whether the process reads `current_user`
+    pub uses_current_user: bool,
+    /// @policy decorators that name a defined policy
+    pub policies: Vec<PolicyRef>,
+    /// Variables introduced by derive steps, in order
+    pub variables: Vec<Variable>,
+    /// Record the output projection reads from
+    pub result: ResultSource,
+    /// Output fields with their resolved source
+    pub output: Vec<OutputField>,
+    /// The action as written: input, process steps and decorators
+    pub def: Action,
+}
+
+/// HTTP route from an @api decorator
+#[derive(Debug, Clone, Serialize)]
+pub struct Route {
+    pub method: HttpMethod,
+    pub path: String,
+    /// `{name}` segments of the path, in order
+    pub params: Vec<String>,
+}
+
+impl Route {
+    /// Whether the request carries a JSON body
+    pub fn has_body(&self) -> bool {
+        matches!(self.method, HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch)
+    }
+}
+
+/// How many records an action returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Cardinality {
+    One,
+    Many,
+}
+
+/// How an action is implemented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ActionKind {
+    /// No process block: handled by the entity's repository
+    Crud,
+    /// Process that selects records and never writes
+    Query,
+    /// Any other process: writes, or only computes values
+    Command,
+}
+
+/// A resolved @policy reference
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyRef {
+    /// Owning entity for `@policy(Entity.Name)`, None for global policies
+    pub entity: Option<String>,
+    pub name: String,
+}
+
+/// A derived variable and its type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Variable {
+    pub name: String,
+    pub ty: VarType,
+}
+
+/// Type of a derived variable
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum VarType {
+    /// A record of the named entity
+    Record(String),
+    /// A scalar value
+    Value(FieldType),
+    /// A custom computation whose result type is not known
+    Unknown,
+}
+
+impl From<ValueType> for VarType {
+    fn from(ty: ValueType) -> Self {
+        match ty {
+            ValueType::Entity(entity) => VarType::Record(entity),
+            ValueType::Field(field_type) => VarType::Value(field_type),
+            ValueType::Unknown => VarType::Unknown,
+        }
+    }
+}
+
+/// Where an action's output record comes from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ResultSource {
+    /// A derived variable
+    Variable(String),
+    /// The record created by a create-mode mutate of the entity
+    Created(String),
+    /// The record changed by an update-mode mutate, re-read by primary key
+    Updated { entity: String, key: Option<FieldReference> },
+    /// The authenticated user
+    CurrentUser,
+    /// The record(s) loaded by the repository for a CRUD action
+    Repository,
+    /// No record is available
+    None,
+}
+
+/// An output field and where its value is read from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutputField {
+    pub name: String,
+    /// True when the field is a derived variable rather than a field of the result record
+    pub is_variable: bool,
+}
+
+impl Program {
+    /// Lower a validated (and preprocessed) intent file
+    pub fn lower(file: &IntentFile) -> Self {
+        let ctx = ValidationContext::from_file(file);
+        let actions = file.actions.iter().map(|a| lower_action(a, file, &ctx)).collect();
+        Self {
+            entities: file.entities.clone(),
+            actions,
+            rules: file.rules.clone(),
+            policies: file.policies.clone(),
+            auth_entity: file.auth_entity.clone(),
+        }
+    }
+
+    /// Find an entity by name
+    pub fn find_entity(&self, name: &str) -> Option<&Entity> {
+        self.entities.iter().find(|e| e.name == name)
+    }
+
+    /// Actions owned by an entity, in source order
+    pub fn actions_for<'a>(&'a self, entity: &'a str) -> impl Iterator<Item = &'a ActionIr> {
+        self.actions.iter().filter(move |a| a.entity.as_deref() == Some(entity))
+    }
+}
+
+impl ActionIr {
+    /// Look up a derived variable by name
+    pub fn variable(&self, name: &str) -> Option<&Variable> {
+        self.variables.iter().find(|v| v.name == name)
+    }
+
+    /// Path parameters of the route, empty without @api
+    pub fn path_params(&self) -> &[String] {
+        self.route.as_ref().map(|r| r.params.as_slice()).unwrap_or_default()
+    }
+}
+
+fn lower_action(action: &Action, file: &IntentFile, ctx: &ValidationContext) -> ActionIr {
+    let route = action.decorators.iter().find_map(|d| match d {
+        Decorator::Api { method, path } => Some(Route {
+            method: method.clone(),
+            path: path.clone(),
+            params: path
+                .split('/')
+                .filter_map(|s| s.strip_prefix('{').and_then(|s| s.strip_suffix('}')))
+                .map(str::to_string)
+                .collect(),
+        }),
+        _ => None,
+    });
+    let steps: &[ProcessStep] = action.process.as_ref().map(|p| p.steps.as_slice()).unwrap_or_default();
+
+    let writes = steps.iter().any(|s| !matches!(s, ProcessStep::Derive(_)));
+    let selects = steps
+        .iter()
+        .any(|s| matches!(s, ProcessStep::Derive(DeriveStatement { value: DeriveValue::Select { .. }, .. })));
+    let kind = if action.process.is_none() {
+        ActionKind::Crud
+    } else if selects && !writes {
+        ActionKind::Query
+    } else {
+        ActionKind::Command
+    };
+
+    // GET without path parameters lists records, except `get_*` actions, which fetch one
+    let cardinality = match &route {
+        Some(r) if matches!(r.method, HttpMethod::Get) && r.params.is_empty() && !action.name.starts_with("get_") => {
+            Cardinality::Many
+        }
+        _ => Cardinality::One,
+    };
+
+    let requires_auth = action.decorators.iter().any(|d| matches!(d, Decorator::Auth { .. }));
+    let uses_current_user = steps.iter().any(step_uses_current_user);
+    let variables: Vec<Variable> = validator::derived_types(action, ctx)
+        .into_iter()
+        .map(|(name, ty)| Variable { name, ty: ty.into() })
+        .collect();
+
+    let policies = action
+        .decorators
+        .iter()
+        .filter_map(|d| match d {
+            Decorator::Policy(name) => resolve_policy(name, file),
+            _ => None,
+        })
+        .collect();
+
+    let result = result_source(action, kind, file, requires_auth, route.as_ref(), &variables);
+    let output = action
+        .output
+        .iter()
+        .flat_map(|o| &o.fields)
+        .map(|name| OutputField {
+            name: name.clone(),
+            is_variable: variables.iter().any(|v| &v.name == name),
+        })
+        .collect();
+
+    ActionIr {
+        name: action.name.clone(),
+        entity: owning_entity(action, file),
+        route,
+        cardinality,
+        kind,
+        requires_auth,
+        uses_current_user,
+        policies,
+        variables,
+        result,
+        output,
+        def: action.clone(),
+    }
+}
+
+/// The entity an action belongs to: its output, else the first entity its process touches,
+/// else the entity named by its route, else the auth entity for auth routes and actions
+fn owning_entity(action: &Action, file: &IntentFile) -> Option<String> {
+    if let Some(output) = &action.output {
+        return Some(output.entity.clone());
+    }
+
+    for step in action.process.iter().flat_map(|p| &p.steps) {
+        match step {
+            ProcessStep::Mutate(m) => return Some(m.entity.clone()),
+            ProcessStep::Delete(d) => return Some(d.entity.clone()),
+            ProcessStep::Derive(DeriveStatement { value: DeriveValue::Select { entity, .. }, .. }) => {
+                return Some(entity.clone());
+            }
+            ProcessStep::Derive(_) => {}
+        }
+    }
+
+    let path = action.decorators.iter().find_map(|d| match d {
+        Decorator::Api { path, .. } => Some(path),
+        _ => None,
+    });
+    if let Some(path) = path {
+        for entity in &file.entities {
+            let lower = entity.name.to_lowercase();
+            if path.starts_with(&format!("/{}s", lower)) || path.starts_with(&format!("/{}", lower)) {
+                return Some(entity.name.clone());
+            }
+        }
+        if path.starts_with("/auth") && file.auth_entity.is_some() {
+            return file.auth_entity.clone();
+        }
+    }
+
+    const AUTH_ACTIONS: &[&str] = &[
+        "register", "login", "logout", "get_me", "get_my_auth", "forgot_password", "reset_password", "signup",
+    ];
+    file.auth_entity.clone().filter(|_| AUTH_ACTIONS.contains(&action.name.as_str()))
+}
+
+fn resolve_policy(name: &str, file: &IntentFile) -> Option<PolicyRef> {
+    if file.policies.iter().any(|p| p.name == name) {
+        return Some(PolicyRef { entity: None, name: name.to_string() });
+    }
+    let (entity, policy) = name.split_once('.')?;
+    file.find_entity(entity)
+        .filter(|e| e.policies.iter().any(|p| p.name == policy))
+        .map(|_| PolicyRef { entity: Some(entity.to_string()), name: policy.to_string() })
+}
+
+fn mentions_current_user(path: &[String]) -> bool {
+    path.first().is_some_and(|root| root == "current_user")
+}
+
+fn reference_uses_current_user(reference: &FieldReference) -> bool {
+    matches!(reference, FieldReference::DerivedField { name, .. } if name == "current_user")
+}
+
+fn value_uses_current_user(value: &DeriveValue) -> bool {
+    match value {
+        DeriveValue::FieldAccess { path } => mentions_current_user(path),
+        DeriveValue::Identifier(id) => id == "current_user" || id.starts_with("current_user."),
+        DeriveValue::Select { predicate, .. } => reference_uses_current_user(&predicate.value),
+        DeriveValue::Compute { args, .. } | DeriveValue::SystemCall { args, .. } => args.iter().any(|arg| match arg {
+            FunctionArg::FieldAccess { path } => mentions_current_user(path),
+            FunctionArg::Identifier(id) => id == "current_user",
+            _ => false,
+        }),
+        DeriveValue::Literal(_) => false,
+    }
+}
+
+fn step_uses_current_user(step: &ProcessStep) -> bool {
+    match step {
+        ProcessStep::Derive(d) => value_uses_current_user(&d.value),
+        ProcessStep::Mutate(m) => {
+            m.predicate.as_ref().is_some_and(|p| reference_uses_current_user(&p.value))
+                || m.setters.iter().any(|s| value_uses_current_user(&s.value))
+        }
+        ProcessStep::Delete(d) => reference_uses_current_user(&d.predicate.value),
+    }
+}
+
+fn result_source(
+    action: &Action,
+    kind: ActionKind,
+    file: &IntentFile,
+    requires_auth: bool,
+    route: Option<&Route>,
+    variables: &[Variable],
+) -> ResultSource {
+    let steps: &[ProcessStep] = action.process.as_ref().map(|p| p.steps.as_slice()).unwrap_or_default();
+    match kind {
+        // Read-only processes project the first record they select
+        ActionKind::Query => steps
+            .iter()
+            .find_map(|s| match s {
+                ProcessStep::Derive(d) if matches!(d.value, DeriveValue::Select { .. }) => {
+                    Some(ResultSource::Variable(d.name.clone()))
+                }
+                _ => None,
+            })
+            .unwrap_or(ResultSource::None),
+        // Writing processes project the last record of the output entity they derive or write,
+        // else the last record they write, else a custom computation that may return one
+        ActionKind::Command => {
+            let output = action.output.as_ref().map(|o| &o.entity);
+            let written = |m: &MutateBlock| match &m.predicate {
+                None => ResultSource::Created(m.entity.clone()),
+                Some(p) => ResultSource::Updated {
+                    entity: m.entity.clone(),
+                    key: Some(p)
+                        .filter(|p| matches!(&p.field, FieldReference::InputField(f) | FieldReference::DerivedField { field: f, .. } if f == "id"))
+                        .map(|p| p.value.clone()),
+                },
+            };
+            let last = steps
+                .iter()
+                .rev()
+                .find_map(|s| match s {
+                    ProcessStep::Derive(d) => variables
+                        .iter()
+                        .rfind(|v| v.name == d.name)
+                        .filter(|v| matches!(&v.ty, VarType::Record(entity) if output.is_none_or(|o| o == entity)))
+                        .map(|_| ResultSource::Variable(d.name.clone())),
+                    ProcessStep::Mutate(m) if output.is_none_or(|o| *o == m.entity) => Some(written(m)),
+                    _ => None,
+                })
+                .or_else(|| {
+                    steps.iter().rev().find_map(|s| match s {
+                        ProcessStep::Mutate(m) => Some(written(m)),
+                        _ => None,
+                    })
+                })
+                .or_else(|| {
+                    variables
+                        .iter()
+                        .rfind(|v| v.ty == VarType::Unknown)
+                        .map(|v| ResultSource::Variable(v.name.clone()))
+                });
+            let is_self = requires_auth
+                && action.output.as_ref().map(|o| &o.entity) == file.auth_entity.as_ref()
+                && action.output.is_some();
+            match last {
+                Some(source) => source,
+                None if is_self => ResultSource::CurrentUser,
+                None => ResultSource::None,
+            }
+        }
+        ActionKind::Crud => {
+            let reads_self = route.is_some_and(|r| matches!(r.method, HttpMethod::Get) && r.params.is_empty())
+                && action.name.starts_with("get_")
+                && requires_auth;
+            if reads_self { ResultSource::CurrentUser } else { ResultSource::Repository }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+
+    const BLOG: &str = r#"auth entity User:
+    id: uuid @primary
+    email: email @unique
+    password_hash: string
+
+entity Post:
+    id: uuid @primary
+    title: string
+    author_id: uuid
+
+@api GET /posts
+action list_posts:
+    output: Post(id, title)
+
+@api GET /profile
+@auth
+action get_profile:
+    output: User(id, email)
+
+@api POST /posts/{id}/rename
+@auth
+action rename_post:
+    input:
+        id: uuid
+        title: string
+    process:
+        derive author = current_user.id
+        mutate Post where id == input.id:
+            set title = input.title
+    output: Post(id, title)
+
+@api POST /posts
+action publish_post:
+    input:
+        title: string
+    process:
+        mutate Post:
+            set title = input.title
+        derive note = compute slugify(input.title)
+    output: Post(id, title, note)
+
+@api POST /posts/search
+action find_post:
+    input:
+        title: string
+    process:
+        derive post = select Post where title == input.title
+        derive heading = post.title
+    output: Post(id, heading)
+"#;
+
+    fn program() -> Program {
+        Program::lower(&parse_intent(BLOG).unwrap())
+    }
+
+    fn action<'a>(program: &'a Program, name: &str) -> &'a ActionIr {
+        program.actions.iter().find(|a| a.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_cardinality_and_result() {
+        let program = program();
+        let list = action(&program, "list_posts");
+        assert_eq!((list.cardinality, list.kind), (Cardinality::Many, ActionKind::Crud));
+        assert_eq!(list.result, ResultSource::Repository);
+
+        let profile = action(&program, "get_profile");
+        assert_eq!(profile.cardinality, Cardinality::One);
+        assert_eq!(profile.result, ResultSource::CurrentUser);
+        assert_eq!(profile.entity.as_deref(), Some("User"));
+    }
+
+    #[test]
+    fn test_command_resolves_updated_record_and_route() {
+        let program = program();
+        let rename = action(&program, "rename_post");
+        assert_eq!(rename.kind, ActionKind::Command);
+        assert!(rename.uses_current_user);
+        assert_eq!(rename.path_params(), ["id"]);
+        assert_eq!(
+            rename.result,
+            ResultSource::Updated { entity: "Post".to_string(), key: Some(FieldReference::InputField("id".to_string())) }
+        );
+        assert_eq!(rename.variable("author").map(|v| &v.ty), Some(&VarType::Value(FieldType::Uuid)));
+    }
+
+    #[test]
+    fn test_trailing_scalar_derive_keeps_written_record() {
+        let program = program();
+        let publish = action(&program, "publish_post");
+        assert_eq!(publish.result, ResultSource::Created("Post".to_string()));
+        assert_eq!(publish.variable("note").map(|v| &v.ty), Some(&VarType::Value(FieldType::String)));
+    }
+
+    #[test]
+    fn test_query_types_variables_and_output_sources() {
+        let program = program();
+        let find = action(&program, "find_post");
+        assert_eq!(find.kind, ActionKind::Query);
+        assert_eq!(find.result, ResultSource::Variable("post".to_string()));
+        assert_eq!(find.variable("post").map(|v| &v.ty), Some(&VarType::Record("Post".to_string())));
+        assert_eq!(find.variable("heading").map(|v| &v.ty), Some(&VarType::Value(FieldType::String)));
+        let sources: Vec<_> = find.output.iter().map(|f| (f.name.as_str(), f.is_variable)).collect();
+        assert_eq!(sources, vec![("id", false), ("heading", true)]);
+    }
+}
@@ -0,0 +1,659 @@
+// Intent Compiler - Linter
+// Named, configurable hygiene and style lints over a validated AST
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::ast::*;
+use crate::error::{CompileError, CompileResult, Diagnostics, Warning};
+use crate::indexes;
+
+/// How a lint finding is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// Not reported
+    Allow,
+    /// Reported as a warning
+    Warn,
+    /// Reported as an error
+    Deny,
+}
+
+/// A named lint
+pub struct Lint {
+    pub name: &'static str,
+    /// Diagnostic code shared with `--allow` and `intentc explain`
+    pub code: &'static str,
+    pub default_level: LintLevel,
+    pub description: &'static str,
+    check: fn(&IntentFile, &'static str) -> Vec<Warning>,
+}
+
+/// Every available lint
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "missing_primary_key",
+        code: "W0101",
+        default_level: LintLevel::Warn,
+        description: "entity has no @primary field",
+        check: check_missing_primary_key,
+    },
+    Lint {
+        name: "unused_entity",
+        code: "W0102",
+        default_level: LintLevel::Warn,
+        description: "entity is not referenced by any action, entity, rule or policy",
+        check: check_unused_entity,
+    },
+    Lint {
+        name: "unused_policy",
+        code: "W0103",
+        default_level: LintLevel::Warn,
+        description: "policy is never applied with @policy",
+        check: check_unused_policy,
+    },
+    Lint {
+        name: "unused_rule",
+        code: "W0104",
+        default_level: LintLevel::Warn,
+        description: "rule constrains an entity that no action writes",
+        check: check_unused_rule,
+    },
+    Lint {
+        name: "missing_api",
+        code: "W0105",
+        default_level: LintLevel::Warn,
+        description: "action has no @api route and is not called by @auth or a rule",
+        check: check_missing_api,
+    },
+    Lint {
+        name: "unique_without_index",
+        code: "W0106",
+        default_level: LintLevel::Warn,
+        description: "@unique field is not marked @index",
+        check: check_unique_without_index,
+    },
+    Lint {
+        name: "unused_enum_value",
+        code: "W0107",
+        default_level: LintLevel::Warn,
+        description: "enum value is never mentioned by a rule",
+        check: check_unused_enum_value,
+    },
+    Lint {
+        name: "naming",
+        code: "W0108",
+        default_level: LintLevel::Warn,
+        description: "entities, policies and rules use PascalCase; fields and actions use snake_case",
+        check: check_naming,
+    },
+    Lint {
+        name: "unindexed_lookup",
+        code: "W0109",
+        default_level: LintLevel::Warn,
+        description: "select, mutate or delete filters on a column without an index",
+        check: check_unindexed_lookup,
+    },
+    Lint {
+        name: "missing_composite_index",
+        code: "W0110",
+        default_level: LintLevel::Warn,
+        description: "entity is looked up by several foreign keys without a composite index",
+        check: check_missing_composite_index,
+    },
+    Lint {
+        name: "n_plus_one",
+        code: "W0111",
+        default_level: LintLevel::Warn,
+        description: "list endpoint returns a related entity for every row",
+        check: check_n_plus_one,
+    },
+];
+
+/// Look up a lint by name
+pub fn find_lint(name: &str) -> Option<&'static Lint> {
+    LINTS.iter().find(|l| l.name == name)
+}
+
+/// Run every lint over a file. `levels` overrides default levels by lint name;
+/// `# intentc: allow(<lint>, ...)` comment lines suppress findings on the next line.
+/// Findings of `deny` lints become errors; the rest are returned as warnings.
+pub fn run_lints(
+    file: &IntentFile,
+    source: &str,
+    levels: &HashMap<String, LintLevel>,
+) -> (CompileResult<()>, Vec<Warning>) {
+    let mut diags = Diagnostics::new();
+    for name in levels.keys() {
+        if find_lint(name).is_none() {
+            diags.error(CompileError::config(format!(
+                "Unknown lint '{}' in [lints]. Available lints: {:?}",
+                name,
+                LINTS.iter().map(|l| l.name).collect::<Vec<_>>()
+            )));
+        }
+    }
+
+    let suppressions = inline_suppressions(source);
+    for lint in LINTS {
+        let level = levels.get(lint.name).copied().unwrap_or(lint.default_level);
+        if level == LintLevel::Allow {
+            continue;
+        }
+        for finding in (lint.check)(file, lint.code) {
+            let suppressed = suppressions
+                .get(&finding.location.line)
+                .is_some_and(|names| names.contains(lint.name));
+            if suppressed {
+                continue;
+            }
+            if level == LintLevel::Deny {
+                diags.error(CompileError::validation_with_hint(
+                    finding.code,
+                    format!("{} (denied by lint '{}')", finding.message, lint.name),
+                    finding.location,
+                    finding.hint.unwrap_or_default(),
+                ));
+            } else {
+                diags.warning(finding);
+            }
+        }
+    }
+
+    let warnings = diags.take_warnings();
+    (diags.into_result(()), warnings)
+}
+
+/// Map each line number to the lints allowed on it by directive comments directly above
+fn inline_suppressions(source: &str) -> HashMap<usize, HashSet<String>> {
+    let mut suppressions: HashMap<usize, HashSet<String>> = HashMap::new();
+    let mut pending: HashSet<String> = HashSet::new();
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(names) = trimmed
+            .strip_prefix('#')
+            .map(str::trim)
+            .and_then(|rest| rest.strip_prefix("intentc:"))
+            .map(str::trim)
+            .and_then(|rest| rest.strip_prefix("allow("))
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            pending.extend(names.split(',').map(|n| n.trim().to_string()));
+        } else if !trimmed.is_empty() && !trimmed.starts_with('#') && !pending.is_empty() {
+            suppressions.insert(index + 1, std::mem::take(&mut pending));
+        }
+    }
+    suppressions
+}
+
+fn finding(code: &'static str, message: String, location: &SourceLocation, hint: &str) -> Warning {
+    Warning::with_hint(code, message, location.clone(), hint)
+}
+
+fn check_missing_primary_key(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    file.entities
+        .iter()
+        .filter(|e| !e.fields.iter().any(|f| f.decorators.contains(&Decorator::Primary)))
+        .map(|e| {
+            finding(
+                code,
+                format!("Entity '{}' has no @primary field", e.name),
+                &e.location,
+                "Consider adding @primary to an id field",
+            )
+        })
+        .collect()
+}
+
+/// Names of entities referenced anywhere other than their own definition
+fn referenced_entities(file: &IntentFile) -> HashSet<String> {
+    let mut used = HashSet::new();
+    if let Some(auth) = &file.auth_entity {
+        used.insert(auth.clone());
+    }
+
+    for entity in &file.entities {
+        for field in &entity.fields {
+            collect_type_references(&field.field_type, &mut used);
+        }
+    }
+
+    for action in &file.actions {
+        if let Some(output) = &action.output {
+            used.insert(output.entity.clone());
+        }
+        for param in action.input.iter().flat_map(|i| &i.fields) {
+            collect_type_references(&param.param_type, &mut used);
+        }
+        for decorator in &action.decorators {
+            if let Decorator::Auth { name: Some(name), .. } = decorator {
+                used.insert(name.clone());
+            }
+        }
+        for step in action.process.iter().flat_map(|p| &p.steps) {
+            match step {
+                ProcessStep::Derive(d) => match &d.value {
+                    DeriveValue::Select { entity, .. } => {
+                        used.insert(entity.clone());
+                    }
+                    DeriveValue::Compute { args, .. } | DeriveValue::SystemCall { args, .. } => {
+                        for arg in args {
+                            if let FunctionArg::TypeName(name) = arg {
+                                used.insert(name.clone());
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                ProcessStep::Mutate(m) => {
+                    used.insert(m.entity.clone());
+                }
+                ProcessStep::Delete(d) => {
+                    used.insert(d.entity.clone());
+                }
+            }
+        }
+    }
+
+    for rule in &file.rules {
+        collect_expression_entities(&rule.condition, &mut used);
+    }
+    for policy in &file.policies {
+        used.insert(policy.subject.clone());
+        collect_expression_entities(&policy.require, &mut used);
+    }
+    used
+}
+
+fn collect_type_references(field_type: &FieldType, used: &mut HashSet<String>) {
+    match field_type {
+        FieldType::Reference(name) | FieldType::Ref(name) => {
+            used.insert(name.clone());
+        }
+        FieldType::Array(inner) | FieldType::List(inner) | FieldType::Optional(inner) => {
+            collect_type_references(inner, used);
+        }
+        _ => {}
+    }
+}
+
+fn collect_expression_entities(expr: &Expression, used: &mut HashSet<String>) {
+    match expr {
+        Expression::FieldAccess { entity, .. } => {
+            used.insert(entity.clone());
+        }
+        Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => {
+            collect_expression_entities(left, used);
+            collect_expression_entities(right, used);
+        }
+        Expression::Not(inner) => collect_expression_entities(inner, used),
+        Expression::Literal(_) | Expression::Identifier(_) => {}
+    }
+}
+
+fn check_unused_entity(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    let used = referenced_entities(file);
+    file.entities
+        .iter()
+        .filter(|e| !used.contains(&e.name))
+        .map(|e| {
+            finding(
+                code,
+                format!("Entity '{}' is never used", e.name),
+                &e.location,
+                "Reference it from an action, or remove it",
+            )
+        })
+        .collect()
+}
+
+fn check_unused_policy(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    let applied: HashSet<&String> = file
+        .actions
+        .iter()
+        .flat_map(|a| &a.decorators)
+        .filter_map(|d| match d {
+            Decorator::Policy(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+
+    let global = file.policies.iter().map(|p| (p.name.clone(), p));
+    let scoped = file
+        .entities
+        .iter()
+        .flat_map(|e| e.policies.iter().map(move |p| (format!("{}.{}", e.name, p.name), p)));
+    global
+        .chain(scoped)
+        .filter(|(name, _)| !applied.contains(name))
+        .map(|(name, policy)| {
+            finding(
+                code,
+                format!("Policy '{}' is never applied", name),
+                &policy.location,
+                &format!("Apply it to an action with @policy({})", name),
+            )
+        })
+        .collect()
+}
+
+/// Entities written by some action: mutate steps, or process-less create/update endpoints
+fn written_entities(file: &IntentFile) -> HashSet<String> {
+    let mut written = HashSet::new();
+    for action in &file.actions {
+        match &action.process {
+            Some(process) => {
+                for step in &process.steps {
+                    if let ProcessStep::Mutate(m) = step {
+                        written.insert(m.entity.clone());
+                    }
+                }
+            }
+            None => {
+                let writes = action.decorators.iter().any(|d| {
+                    matches!(
+                        d,
+                        Decorator::Api { method: HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch, .. }
+                    )
+                });
+                if let (true, Some(output)) = (writes, &action.output) {
+                    written.insert(output.entity.clone());
+                }
+            }
+        }
+    }
+    written
+}
+
+fn check_unused_rule(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    let written = written_entities(file);
+    file.rules
+        .iter()
+        .filter(|rule| {
+            let mut entities = HashSet::new();
+            collect_expression_entities(&rule.condition, &mut entities);
+            !entities.iter().any(|e| written.contains(e))
+        })
+        .map(|rule| {
+            finding(
+                code,
+                format!("Rule '{}' never runs: no action writes the entities it checks", rule.name),
+                &rule.location,
+                "Rules run when an action creates or updates the entity named in their condition",
+            )
+        })
+        .collect()
+}
+
+fn check_missing_api(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    let mut called: HashSet<&String> = HashSet::new();
+    for action in &file.actions {
+        for decorator in &action.decorators {
+            if let Decorator::Auth { name: Some(name), .. } = decorator {
+                called.insert(name);
+            }
+        }
+    }
+    for rule in &file.rules {
+        if let Consequence::ActionCall { action, .. } = &rule.consequence {
+            called.insert(action);
+        }
+    }
+
+    file.actions
+        .iter()
+        .filter(|a| !a.decorators.iter().any(|d| matches!(d, Decorator::Api { .. })))
+        .filter(|a| !called.contains(&a.name))
+        .map(|a| {
+            finding(
+                code,
+                format!("Action '{}' has no @api route and is never called", a.name),
+                &a.location,
+                "Add an @api decorator, e.g. @api POST /path",
+            )
+        })
+        .collect()
+}
+
+fn check_unique_without_index(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    file.entities
+        .iter()
+        .flat_map(|e| e.fields.iter().map(move |f| (e, f)))
+        .filter(|(_, f)| f.decorators.contains(&Decorator::Unique) && !f.decorators.contains(&Decorator::Index))
+        .map(|(e, f)| {
+            finding(
+                code,
+                format!("Field '{}.{}' is @unique but not @index", e.name, f.name),
+                &f.location,
+                "Add @index so lookups by this field are fast",
+            )
+        })
+        .collect()
+}
+
+/// Identifiers and string literals mentioned by rule conditions
+fn rule_mentions(file: &IntentFile) -> HashSet<String> {
+    fn collect(expr: &Expression, mentions: &mut HashSet<String>) {
+        match expr {
+            Expression::Identifier(name) | Expression::Literal(LiteralValue::String(name)) => {
+                mentions.insert(name.clone());
+            }
+            Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => {
+                collect(left, mentions);
+                collect(right, mentions);
+            }
+            Expression::Not(inner) => collect(inner, mentions),
+            _ => {}
+        }
+    }
+
+    let mut mentions = HashSet::new();
+    for rule in &file.rules {
+        collect(&rule.condition, &mut mentions);
+    }
+    mentions
+}
+
+fn check_unused_enum_value(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    let mentions = rule_mentions(file);
+    let mut findings = Vec::new();
+    for entity in &file.entities {
+        for field in &entity.fields {
+            let FieldType::Enum(values) = strip_optional(&field.field_type) else {
+                continue;
+            };
+            for value in values.iter().filter(|v| !mentions.contains(*v)) {
+                findings.push(finding(
+                    code,
+                    format!("Enum value '{}' of '{}.{}' is never used in a rule", value, entity.name, field.name),
+                    &field.location,
+                    "Remove the value, or add a rule that handles it",
+                ));
+            }
+        }
+    }
+    findings
+}
+
+fn strip_optional(field_type: &FieldType) -> &FieldType {
+    match field_type {
+        FieldType::Optional(inner) => strip_optional(inner),
+        other => other,
+    }
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && !name.contains('_')
+}
+
+fn is_snake_case(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn check_naming(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    let mut findings = Vec::new();
+    let mut check = |kind: &str, name: &str, ok: bool, style: &str, location: &SourceLocation| {
+        if !ok {
+            findings.push(finding(
+                code,
+                format!("{} '{}' should be {}", kind, name, style),
+                location,
+                &format!("Rename it using {}", style),
+            ));
+        }
+    };
+
+    for entity in &file.entities {
+        check("Entity", &entity.name, is_pascal_case(&entity.name), "PascalCase", &entity.location);
+        for field in &entity.fields {
+            check("Field", &field.name, is_snake_case(&field.name), "snake_case", &field.location);
+        }
+        for policy in &entity.policies {
+            check("Policy", &policy.name, is_pascal_case(&policy.name), "PascalCase", &policy.location);
+        }
+    }
+    for action in &file.actions {
+        check("Action", &action.name, is_snake_case(&action.name), "snake_case", &action.location);
+    }
+    for policy in &file.policies {
+        check("Policy", &policy.name, is_pascal_case(&policy.name), "PascalCase", &policy.location);
+    }
+    for rule in &file.rules {
+        check("Rule", &rule.name, is_pascal_case(&rule.name), "PascalCase", &rule.location);
+    }
+    findings
+}
+
+fn check_unindexed_lookup(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    indexes::unindexed_lookups(file)
+        .iter()
+        .map(|s| {
+            finding(
+                code,
+                format!("Action '{}' filters {} by unindexed column '{}'", s.action, s.entity, s.columns[0]),
+                &s.location,
+                &format!("Add @index to '{}.{}', or compile with --auto-index", s.entity, s.columns[0]),
+            )
+        })
+        .collect()
+}
+
+fn check_missing_composite_index(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    indexes::composite_suggestions(file)
+        .iter()
+        .map(|s| {
+            finding(
+                code,
+                format!("{} is looked up by ({}) without a composite index", s.entity, s.columns.join(", ")),
+                &s.location,
+                &format!("Declare @index({}) on {}, or compile with --auto-index", s.columns.join(", "), s.entity),
+            )
+        })
+        .collect()
+}
+
+fn check_n_plus_one(file: &IntentFile, code: &'static str) -> Vec<Warning> {
+    indexes::n_plus_one(file)
+        .iter()
+        .map(|n| {
+            finding(
+                code,
+                format!(
+                    "Action '{}' returns '{}.{}' for every row, loading each {} with a separate query",
+                    n.action, n.entity, n.field, n.related
+                ),
+                &n.location,
+                &format!("Return the '{}' id instead, or fetch the {} records in one query", n.field, n.related),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_intent;
+
+    fn lint(source: &str, levels: &[(&str, LintLevel)]) -> (CompileResult<()>, Vec<Warning>) {
+        let file = parse_intent(source).unwrap();
+        let levels = levels.iter().map(|(n, l)| (n.to_string(), *l)).collect();
+        run_lints(&file, source, &levels)
+    }
+
+    fn codes(warnings: &[Warning]) -> Vec<&'static str> {
+        warnings.iter().map(|w| w.code).collect()
+    }
+
+    #[test]
+    fn test_default_lints() {
+        let (result, warnings) = lint(
+            r#"entity Tag:
+    name: string @unique
+
+entity Post:
+    id: uuid @primary
+    title: string
+
+@api GET /posts
+action list_posts:
+    output: Post(id, title)
+
+action helper:
+    output: Post(id)
+"#,
+            &[],
+        );
+        assert!(result.is_ok());
+        let found = codes(&warnings);
+        for code in ["W0101", "W0102", "W0105", "W0106"] {
+            assert!(found.contains(&code), "{} missing from {:?}", code, found);
+        }
+    }
+
+    #[test]
+    fn test_levels_override_defaults() {
+        let source = r#"entity Post:
+    id: uuid @primary
+    status: draft | published
+
+@api POST /posts
+action createPost:
+    output: Post(id)
+"#;
+        let (result, warnings) = lint(source, &[("naming", LintLevel::Deny)]);
+        match result {
+            Err(CompileError::ValidationError { code, .. }) => assert_eq!(code, "W0108"),
+            other => panic!("expected denied naming lint, got {:?}", other),
+        }
+        assert_eq!(codes(&warnings), vec!["W0107", "W0107"]);
+
+        let (result, warnings) = lint(source, &[("naming", LintLevel::Allow), ("unused_enum_value", LintLevel::Allow)]);
+        assert!(result.is_ok());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_inline_suppression() {
+        let (_, warnings) = lint(
+            r#"# intentc: allow(unused_entity, missing_primary_key)
+entity Tag:
+    name: string
+
+entity Label:
+    name: string
+"#,
+            &[],
+        );
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert!(messages.iter().all(|m| !m.contains("'Tag'")), "{:?}", messages);
+        assert!(messages.iter().any(|m| m.contains("'Label'")), "{:?}", messages);
+    }
+
+    #[test]
+    fn test_unknown_lint_in_config() {
+        let (result, _) = lint("entity Tag:\n    id: uuid @primary\n", &[("unused_entities", LintLevel::Deny)]);
+        assert!(matches!(result, Err(CompileError::ConfigError { .. })));
+    }
+}
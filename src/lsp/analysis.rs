@@ -0,0 +1,866 @@
+// Intent Compiler - Language Server Analysis
+// Resolves the names in an intent document for diagnostics, navigation, hover, completion and rename
+
+use std::collections::HashMap;
+
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity, DocumentSymbol, NumberOrString, Position,
+    Range, SymbolKind, TextEdit,
+};
+
+use crate::ast::{Action, DeriveValue, Entity, IntentFile, Policy, ProcessStep, SourceLocation};
+use crate::error::CompileError;
+use crate::fmt;
+use crate::lint::{self, LintLevel};
+use crate::parser::parse_intent;
+use crate::preprocessor::inject_auth_actions;
+use crate::validator::{self, SYSTEM_CAPABILITIES};
+
+/// Types a field or input can have besides entity names
+const PRIMITIVE_TYPES: &[&str] = &["string", "number", "boolean", "datetime", "uuid", "email"];
+
+const FIELD_DECORATORS: &[(&str, &str)] = &[
+    ("primary", "primary key"),
+    ("unique", "unique values"),
+    ("index", "database index"),
+    ("optional", "may be null"),
+    ("auto", "generated value"),
+    ("default", "@default(value)"),
+    ("validate", "@validate(min: _, max: _, pattern: \"_\")"),
+    ("map", "@map(target, hash)"),
+    ("expose", "allow in action outputs"),
+    ("renamed_from", "@renamed_from(\"old_name\")"),
+];
+
+const DEFINITION_DECORATORS: &[(&str, &str)] = &[
+    ("api", "@api METHOD /path"),
+    ("auth", "requires authentication"),
+    ("policy", "@policy(Name)"),
+    ("renamed_from", "@renamed_from(\"OldName\")"),
+    ("index", "@index(column, column)"),
+];
+
+/// What a name in the document refers to
+#[derive(Debug, Clone, PartialEq)]
+enum Symbol {
+    Entity(String),
+    Field { entity: String, field: String },
+    Policy(String),
+    Action(String),
+    Input { action: String, name: String },
+}
+
+/// A name in the document, outside strings and comments
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    start: usize,
+    end: usize,
+}
+
+/// Kinds of top-level definitions
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Entity,
+    Action,
+    Rule,
+    Policy,
+}
+
+/// A top-level definition as written: from its first decorator to the next definition
+struct Block<'a> {
+    kind: Kind,
+    name: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// An open document and the declarations of the last version of it that parsed
+pub struct Document {
+    text: String,
+    /// Syntax tree of the last version that parsed, so completion keeps working while typing
+    file: Option<IntentFile>,
+    /// Whether `file` was parsed from `text`
+    current: bool,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let mut document = Self { text: String::new(), file: None, current: false };
+        document.update(text);
+        document
+    }
+
+    pub fn update(&mut self, text: String) {
+        match parse_intent(&text) {
+            Ok(file) => {
+                self.file = Some(file);
+                self.current = true;
+            }
+            Err(_) => self.current = false,
+        }
+        self.text = text;
+    }
+
+    /// Parser, validator and lint findings, as `intentc check` reports them
+    pub fn diagnostics(&self, lints: &HashMap<String, LintLevel>) -> Vec<Diagnostic> {
+        let mut file = match parse_intent(&self.text) {
+            Ok(file) => file,
+            Err(error) => return self.errors(&error),
+        };
+        inject_auth_actions(&mut file);
+        let (validation, mut warnings) = validator::validate(&file);
+        // Lints assume a well-formed file
+        let result = validation.and_then(|_| {
+            let (result, lint_warnings) = lint::run_lints(&file, &self.text, lints);
+            warnings.extend(lint_warnings);
+            result
+        });
+        let mut diagnostics = result.err().map(|e| self.errors(&e)).unwrap_or_default();
+        for warning in &warnings {
+            let hint = warning.hint.as_deref();
+            diagnostics.push(self.diagnostic(DiagnosticSeverity::WARNING, warning.code, &warning.message, hint, &warning.location));
+        }
+        diagnostics
+    }
+
+    fn errors(&self, error: &CompileError) -> Vec<Diagnostic> {
+        let (code, message, hint, location) = match error {
+            CompileError::MultipleErrors(errors) => return errors.iter().flat_map(|e| self.errors(e)).collect(),
+            CompileError::ParseError { code, message, location, .. } => (*code, message, None, location.clone()),
+            CompileError::ValidationError { code, message, location, hint } => (*code, message, hint.as_deref(), location.clone()),
+            // Only parsing and validation run here
+            other => ("E0902", &other.to_string(), None, SourceLocation::default()),
+        };
+        vec![self.diagnostic(DiagnosticSeverity::ERROR, code, message, hint, &location)]
+    }
+
+    fn diagnostic(&self, severity: DiagnosticSeverity, code: &str, message: &str, hint: Option<&str>, location: &SourceLocation) -> Diagnostic {
+        let message = match hint {
+            Some(hint) => format!("{}\nhint: {}", message, hint),
+            None => message.to_string(),
+        };
+        Diagnostic {
+            range: self.location_range(location),
+            severity: Some(severity),
+            code: Some(NumberOrString::String(code.to_string())),
+            source: Some("intentc".to_string()),
+            message,
+            ..Default::default()
+        }
+    }
+
+    /// From the reported column to the end of its span, or of the line if the span is longer
+    fn location_range(&self, location: &SourceLocation) -> Range {
+        // Injected auth actions have no position
+        if location.line == 0 {
+            return Range::default();
+        }
+        let line_start = self.text.split_inclusive('\n').take(location.line - 1).map(str::len).sum::<usize>().min(self.text.len());
+        let line = self.text[line_start..].lines().next().unwrap_or_default();
+        let column = line.char_indices().nth(location.column.saturating_sub(1)).map_or(line.len(), |(i, _)| i);
+        let (start, line_end) = (line_start + column, line_start + line.len());
+        let end = match location.span {
+            Some((_, end)) if end > start && end <= line_end => end,
+            _ => line_end,
+        };
+        Range::new(self.position(start), self.position(end))
+    }
+
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let analysis = self.analysis()?;
+        let (symbol, _) = analysis.resolve(analysis.token_at(self.offset(position))?)?;
+        analysis.declaration(&symbol).map(|token| self.range(token))
+    }
+
+    /// Markdown describing the name at `position`, and its range
+    pub fn hover(&self, position: Position) -> Option<(String, Range)> {
+        let analysis = self.analysis()?;
+        let token = analysis.token_at(self.offset(position))?;
+        let (symbol, _) = analysis.resolve(token)?;
+        let file = analysis.file;
+        let (code, note) = match &symbol {
+            Symbol::Entity(name) => {
+                let entity = file.entities.iter().find(|e| &e.name == name)?;
+                (entity_source(entity), None)
+            }
+            Symbol::Field { entity, field } => {
+                let f = find_entity(file, entity)?.fields.iter().find(|f| &f.name == field)?;
+                (fmt::declaration(&f.name, &f.field_type, &f.decorators), Some(format!("Field of `{}`", entity)))
+            }
+            Symbol::Input { action, name } => {
+                let param = find_action(file, action)?.input.as_ref()?.fields.iter().find(|p| &p.name == name)?;
+                (fmt::declaration(&param.name, &param.param_type, &param.decorators), Some(format!("Input of `{}`", action)))
+            }
+            Symbol::Policy(name) => (policy_source(find_policy(file, name)?), None),
+            Symbol::Action(name) => (action_source(find_action(file, name)?), None),
+        };
+        let mut markdown = format!("```intent\n{}\n```", code);
+        if let Some(note) = note {
+            markdown.push_str("\n\n");
+            markdown.push_str(&note);
+        }
+        Some((markdown, self.range(token)))
+    }
+
+    /// Entities with their fields and nested policies, actions, rules and policies
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        let Some(analysis) = self.analysis().filter(|_| self.current) else {
+            return Vec::new();
+        };
+        let file = analysis.file;
+        let mut symbols: Vec<_> = file
+            .entities
+            .iter()
+            .map(|entity| {
+                let mut children: Vec<_> = entity
+                    .fields
+                    .iter()
+                    .map(|f| self.symbol(&f.name, Some(f.field_type.to_string()), SymbolKind::FIELD, &f.location, Vec::new()))
+                    .collect();
+                children.extend(entity.policies.iter().map(|p| self.symbol(&p.name, None, SymbolKind::OBJECT, &p.location, Vec::new())));
+                let detail = if entity.is_auth { "auth entity" } else { "entity" };
+                self.symbol(&entity.name, Some(detail.to_string()), SymbolKind::STRUCT, &entity.location, children)
+            })
+            .collect();
+        symbols.extend(file.actions.iter().map(|action| {
+            let route = action.decorators.iter().find_map(|d| match d {
+                crate::ast::Decorator::Api { method, path } => Some(format!("{} {}", method, path)),
+                _ => None,
+            });
+            self.symbol(&action.name, route, SymbolKind::FUNCTION, &action.location, Vec::new())
+        }));
+        symbols.extend(file.rules.iter().map(|r| self.symbol(&r.name, None, SymbolKind::EVENT, &r.location, Vec::new())));
+        symbols.extend(file.policies.iter().map(|p| self.symbol(&p.name, None, SymbolKind::OBJECT, &p.location, Vec::new())));
+        symbols.sort_by_key(|s| s.range.start);
+        symbols
+    }
+
+    #[allow(deprecated)] // `DocumentSymbol::deprecated` has to be set
+    fn symbol(&self, name: &str, detail: Option<String>, kind: SymbolKind, location: &SourceLocation, children: Vec<DocumentSymbol>) -> DocumentSymbol {
+        let (start, end) = location.span.unwrap_or_default();
+        // Spans run on over the blank lines and comments after a definition
+        let end = start + self.text[start..end].trim_end().len();
+        let name_token = tokens(&self.text[start..end])
+            .into_iter()
+            .find(|t| &self.text[start + t.start..start + t.end] == name && self.text[start + t.end..].starts_with(':'))
+            .map_or(Token { start: 0, end: 0 }, |t| Token { start: start + t.start, end: start + t.end });
+        DocumentSymbol {
+            name: name.to_string(),
+            detail,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: Range::new(self.position(start), self.position(end)),
+            selection_range: self.range(name_token),
+            children: Some(children),
+        }
+    }
+
+    /// Edits renaming the entity or field at `position` everywhere it is used
+    pub fn rename(&self, position: Position, new_name: &str) -> Result<Vec<TextEdit>, String> {
+        let analysis = self.analysis().filter(|_| self.current).ok_or("fix the syntax errors before renaming")?;
+        let token = analysis.token_at(self.offset(position)).ok_or("nothing to rename here")?;
+        let (symbol, _) = analysis.resolve(token).ok_or("only entities and fields can be renamed")?;
+        let file = analysis.file;
+        match &symbol {
+            Symbol::Entity(_) => {
+                if !is_name(new_name, |c| c.is_ascii_uppercase()) {
+                    return Err(format!("'{}' is not an entity name: it must start with an uppercase letter", new_name));
+                }
+                if find_entity(file, new_name).is_some() {
+                    return Err(format!("entity {} already exists", new_name));
+                }
+            }
+            Symbol::Field { entity, .. } => {
+                if !is_name(new_name, |c| c.is_ascii_lowercase()) {
+                    return Err(format!("'{}' is not a field name: it must start with a lowercase letter", new_name));
+                }
+                if find_entity(file, entity).is_some_and(|e| e.fields.iter().any(|f| f.name == new_name)) {
+                    return Err(format!("{} already has a field {}", entity, new_name));
+                }
+            }
+            _ => return Err("only entities and fields can be renamed".to_string()),
+        }
+        Ok(analysis
+            .references(&symbol)
+            .into_iter()
+            .map(|token| TextEdit::new(self.range(token), new_name.to_string()))
+            .collect())
+    }
+
+    /// Completions for what is being typed at `position`; works on text that doesn't parse
+    pub fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let empty = IntentFile::new();
+        let file = self.file.as_ref().unwrap_or(&empty);
+        let analysis = Analysis::new(&self.text, file);
+        let offset = self.offset(position);
+        let line_start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let before = &self.text[line_start..offset];
+        // The word being typed is replaced by the client; what comes before it sets the context
+        let head = before.trim_end_matches(is_name_char);
+        let block = analysis.block_at(offset);
+
+        if let Some(decorator) = head.strip_suffix('@') {
+            let decorators = if decorator.is_empty() { DEFINITION_DECORATORS } else { FIELD_DECORATORS };
+            return decorators.iter().map(|(name, detail)| item(name, CompletionItemKind::KEYWORD, Some(detail))).collect();
+        }
+
+        if let Some(qualified) = head.strip_suffix('.') {
+            let qualifier = &qualified[qualified.trim_end_matches(is_name_char).len()..];
+            let rest = qualified[..qualified.len() - qualifier.len()].trim_end();
+            if rest.ends_with("system") {
+                return SYSTEM_CAPABILITIES
+                    .iter()
+                    .filter(|(namespace, _, _)| *namespace == qualifier)
+                    .map(|(_, capability, _)| item(capability, CompletionItemKind::FUNCTION, None))
+                    .collect();
+            }
+            if head.trim_start().starts_with("@policy(") {
+                let policies = find_entity(file, qualifier).map(|e| e.policies.as_slice()).unwrap_or_default();
+                return policies.iter().map(|p| item(&p.name, CompletionItemKind::REFERENCE, None)).collect();
+            }
+            if qualifier == "input" {
+                let inputs = block.and_then(|b| find_action(file, b.name)).and_then(|a| a.input.as_ref());
+                return inputs
+                    .map(|i| i.fields.iter().map(|p| item(&p.name, CompletionItemKind::FIELD, Some(&p.param_type.to_string()))).collect())
+                    .unwrap_or_default();
+            }
+            let entity = analysis.qualified_entity(qualifier, block, offset);
+            return entity
+                .and_then(|name| find_entity(file, &name))
+                .map(|e| e.fields.iter().map(|f| item(&f.name, CompletionItemKind::FIELD, Some(&f.field_type.to_string()))).collect())
+                .unwrap_or_default();
+        }
+
+        let entities = || file.entities.iter().map(|e| item(&e.name, CompletionItemKind::CLASS, Some("entity")));
+        let context = head.trim_end();
+        let last_word = context.rsplit(|c: char| c.is_whitespace()).next().unwrap_or_default();
+        match last_word {
+            "system" if head.ends_with(char::is_whitespace) => {
+                let mut namespaces: Vec<_> = SYSTEM_CAPABILITIES.iter().map(|(namespace, _, _)| *namespace).collect();
+                namespaces.dedup();
+                return namespaces.into_iter().map(|n| item(n, CompletionItemKind::MODULE, None)).collect();
+            }
+            "select" | "mutate" | "delete" | "output:" | "subject:" => return entities().collect(),
+            _ => {}
+        }
+        if context.ends_with("ref<") || context.ends_with("@auth(") {
+            return entities().collect();
+        }
+        if context.ends_with("@policy(") {
+            return file.policies.iter().map(|p| item(&p.name, CompletionItemKind::REFERENCE, None)).chain(entities()).collect();
+        }
+        let declares_type = context.ends_with("list<")
+            || context.ends_with('[')
+            || (context.ends_with(':')
+                && context.trim_start().starts_with(|c: char| c.is_ascii_lowercase())
+                && is_name(context.trim_start().trim_end_matches(':'), |c| c.is_ascii_lowercase())
+                && !matches!(context.trim(), "input:" | "process:" | "output:" | "subject:"));
+        if declares_type {
+            let composites = ["ref", "list"].into_iter().map(|c| item(c, CompletionItemKind::KEYWORD, None));
+            let primitives = PRIMITIVE_TYPES.iter().map(|t| item(t, CompletionItemKind::TYPE_PARAMETER, None));
+            return primitives.chain(composites).chain(entities()).collect();
+        }
+        if context.ends_with('=') {
+            return ["select", "compute", "system"].into_iter().map(|k| item(k, CompletionItemKind::KEYWORD, None)).collect();
+        }
+        if context.is_empty() {
+            let keywords: &[&str] = match (indent_level(before), block.map(|b| b.kind)) {
+                (0, _) => &["entity", "auth entity", "action", "rule", "policy"],
+                (1, Some(Kind::Entity)) => &["policy"],
+                (1, Some(Kind::Action)) => &["input:", "process:", "output:"],
+                (2, Some(Kind::Action)) => &["derive", "mutate", "delete"],
+                (3, Some(Kind::Action)) => &["set"],
+                _ => &[],
+            };
+            return keywords.iter().map(|k| item(k, CompletionItemKind::KEYWORD, None)).collect();
+        }
+        Vec::new()
+    }
+
+    /// Name resolution over the text, if some version of it parsed
+    fn analysis(&self) -> Option<Analysis<'_>> {
+        self.file.as_ref().map(|file| Analysis::new(&self.text, file))
+    }
+
+    fn range(&self, token: Token) -> Range {
+        Range::new(self.position(token.start), self.position(token.end))
+    }
+
+    /// Byte offset to an LSP position, whose character counts UTF-16 code units
+    fn position(&self, offset: usize) -> Position {
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count();
+        Position::new(line as u32, before[line_start..].encode_utf16().count() as u32)
+    }
+
+    /// LSP position to a byte offset, clamped to the end of its line
+    fn offset(&self, position: Position) -> usize {
+        let line_start: usize = self.text.split_inclusive('\n').take(position.line as usize).map(str::len).sum();
+        let line = self.text[line_start..].lines().next().unwrap_or_default();
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return line_start + i;
+            }
+            units += c.len_utf16();
+        }
+        line_start + line.len()
+    }
+}
+
+fn item(label: &str, kind: CompletionItemKind, detail: Option<&str>) -> CompletionItem {
+    CompletionItem { label: label.to_string(), kind: Some(kind), detail: detail.map(str::to_string), ..Default::default() }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Whether `name` is an identifier starting with a character `first` accepts
+fn is_name(name: &str, first: impl Fn(char) -> bool) -> bool {
+    name.starts_with(first) && name.chars().all(is_name_char)
+}
+
+/// Indentation depth, counting four spaces or a tab as one level
+fn indent_level(line: &str) -> usize {
+    let mut spaces = 0;
+    let mut level = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => spaces += 1,
+            '\t' => level += 1,
+            _ => break,
+        }
+    }
+    level + spaces / 4
+}
+
+fn find_entity<'a>(file: &'a IntentFile, name: &str) -> Option<&'a Entity> {
+    file.entities.iter().find(|e| e.name == name)
+}
+
+fn find_action<'a>(file: &'a IntentFile, name: &str) -> Option<&'a Action> {
+    file.actions.iter().find(|a| a.name == name)
+}
+
+/// A top-level policy or one nested in an entity
+fn find_policy<'a>(file: &'a IntentFile, name: &str) -> Option<&'a Policy> {
+    file.policies.iter().chain(file.entities.iter().flat_map(|e| &e.policies)).find(|p| p.name == name)
+}
+
+fn entity_source(entity: &Entity) -> String {
+    let keyword = if entity.is_auth { "auth entity" } else { "entity" };
+    let mut lines = vec![format!("{} {}:", keyword, entity.name)];
+    lines.extend(entity.fields.iter().map(|f| format!("    {}", fmt::declaration(&f.name, &f.field_type, &f.decorators))));
+    lines.extend(entity.policies.iter().map(|p| format!("    policy {}", p.name)));
+    lines.join("\n")
+}
+
+fn policy_source(policy: &Policy) -> String {
+    format!("policy {}:\n    subject: {}\n    require {}", policy.name, policy.subject, fmt::expression(&policy.require))
+}
+
+fn action_source(action: &Action) -> String {
+    let mut lines: Vec<_> = action.decorators.iter().map(|d| fmt::decorator_source(d, None)).collect();
+    lines.push(format!("action {}:", action.name));
+    if let Some(output) = &action.output {
+        lines.push(format!("    output: {}({})", output.entity, output.fields.join(", ")));
+    }
+    lines.join("\n")
+}
+
+/// Names outside strings and comments
+fn tokens(text: &str) -> Vec<Token> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => i += text[i + 1..].find('"').map_or(bytes.len() - i, |n| n + 2),
+            b'#' => i += text[i..].find('\n').unwrap_or(bytes.len() - i),
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                tokens.push(Token { start, end: i });
+            }
+            // Numbers, so their digits aren't taken for names
+            b if b.is_ascii_digit() => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    tokens
+}
+
+/// `entity User:` and similar definition headers at the start of a line
+fn header(line: &str) -> Option<(Kind, &str)> {
+    let (kind, rest) = [
+        ("auth entity ", Kind::Entity),
+        ("entity ", Kind::Entity),
+        ("action ", Kind::Action),
+        ("rule ", Kind::Rule),
+        ("policy ", Kind::Policy),
+    ]
+    .into_iter()
+    .find_map(|(keyword, kind)| line.strip_prefix(keyword).map(|rest| (kind, rest.trim_start())))?;
+    let name = &rest[..rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len())];
+    Some((kind, name))
+}
+
+/// Name resolution over one version of the text, with the declarations of a syntax tree
+struct Analysis<'a> {
+    text: &'a str,
+    file: &'a IntentFile,
+    tokens: Vec<Token>,
+    blocks: Vec<Block<'a>>,
+}
+
+impl<'a> Analysis<'a> {
+    fn new(text: &'a str, file: &'a IntentFile) -> Self {
+        Self { text, file, tokens: tokens(text), blocks: blocks(text) }
+    }
+
+    fn token_at(&self, offset: usize) -> Option<Token> {
+        self.tokens.iter().copied().find(|t| t.start <= offset && offset <= t.end)
+    }
+
+    fn block_at(&self, offset: usize) -> Option<&Block<'a>> {
+        self.blocks.iter().find(|b| b.start <= offset && offset < b.end)
+    }
+
+    fn declaration(&self, symbol: &Symbol) -> Option<Token> {
+        self.tokens.iter().copied().find(|&t| self.resolve(t).is_some_and(|(s, declares)| declares && &s == symbol))
+    }
+
+    fn references(&self, symbol: &Symbol) -> Vec<Token> {
+        self.tokens.iter().copied().filter(|&t| self.resolve(t).is_some_and(|(s, _)| &s == symbol)).collect()
+    }
+
+    /// What `token` refers to, and whether it declares it
+    fn resolve(&self, token: Token) -> Option<(Symbol, bool)> {
+        let text = self.text;
+        let word = &text[token.start..token.end];
+        let line_start = text[..token.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[token.end..].find('\n').map_or(text.len(), |i| token.end + i);
+        let before = &text[line_start..token.start];
+        let after = &text[token.end..line_end];
+        let lead = before.trim();
+        let block = self.block_at(token.start);
+        let file = self.file;
+
+        // Route paths are not names
+        if lead.starts_with("@api") {
+            return None;
+        }
+        if after.starts_with(':') {
+            match lead {
+                "entity" | "auth entity" if find_entity(file, word).is_some() => return Some((Symbol::Entity(word.to_string()), true)),
+                "policy" if find_policy(file, word).is_some() => return Some((Symbol::Policy(word.to_string()), true)),
+                "action" if find_action(file, word).is_some() => return Some((Symbol::Action(word.to_string()), true)),
+                "rule" => return None,
+                "" => {
+                    let block = block?;
+                    return match (block.kind, indent_level(before)) {
+                        (Kind::Entity, 1) => self.field(block.name, word).map(|s| (s, true)),
+                        (Kind::Action, 2) if self.section(block, token.start) == Some("input") => {
+                            self.input(block.name, word).map(|s| (s, true))
+                        }
+                        _ => None,
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(qualified) = before.strip_suffix('.') {
+            let qualifier = &qualified[qualified.trim_end_matches(is_name_char).len()..];
+            if lead.starts_with("@policy(") {
+                return find_policy(file, word).map(|_| (Symbol::Policy(word.to_string()), false));
+            }
+            if qualifier == "input" {
+                return self.input(block?.name, word).map(|s| (s, false));
+            }
+            let entity = self.qualified_entity(qualifier, block, token.start)?;
+            return self.field(&entity, word).map(|s| (s, false));
+        }
+
+        if word.starts_with(|c: char| c.is_ascii_uppercase()) {
+            if find_entity(file, word).is_some() {
+                return Some((Symbol::Entity(word.to_string()), false));
+            }
+            return find_policy(file, word).map(|_| (Symbol::Policy(word.to_string()), false));
+        }
+
+        // `set field = ...` in a mutate block
+        if lead == "set" {
+            let mutate = self.lines_back(block?, token.start).find_map(|line| line.trim_start().strip_prefix("mutate "))?;
+            let entity = &mutate[..mutate.find(|c: char| !is_name_char(c)).unwrap_or(mutate.len())];
+            return self.field(entity, word).map(|s| (s, false));
+        }
+        // `select Entity where field == ...` and the same for mutate and delete
+        if lead.ends_with(" where") {
+            let words: Vec<_> = lead.split_whitespace().collect();
+            let keyword = words.iter().position(|w| matches!(*w, "select" | "mutate" | "delete"))?;
+            return self.field(words.get(keyword + 1)?, word).map(|s| (s, false));
+        }
+        // `then action(...)` in a rule
+        if lead == "then" && after.starts_with('(') {
+            return find_action(file, word).map(|_| (Symbol::Action(word.to_string()), false));
+        }
+        // Projections: `Entity(field, ...)`
+        let open = before.rfind('(').filter(|&i| !before[i..].contains(')'))?;
+        let owner = before[..open].trim_end();
+        let owner = &owner[owner.trim_end_matches(is_name_char).len()..];
+        self.field(owner, word).map(|s| (s, false))
+    }
+
+    /// The entity whose fields `qualifier.` refers to: an entity name, the policy subject,
+    /// the current user or a record selected into a process variable
+    fn qualified_entity(&self, qualifier: &str, block: Option<&Block>, offset: usize) -> Option<String> {
+        let file = self.file;
+        if qualifier.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return Some(qualifier.to_string());
+        }
+        let auth_entity = || file.auth_entity.clone().or_else(|| file.entities.iter().find(|e| e.is_auth).map(|e| e.name.clone()));
+        let block = block?;
+        match qualifier {
+            "current_user" => auth_entity(),
+            "subject" => {
+                let policy = match block.kind {
+                    Kind::Policy => block.name,
+                    _ => {
+                        let line = self.lines_back(block, offset).find_map(|l| l.trim_start().strip_prefix("policy "))?;
+                        &line[..line.find(|c: char| !is_name_char(c)).unwrap_or(line.len())]
+                    }
+                };
+                match find_policy(file, policy)?.subject.as_str() {
+                    "@auth" => auth_entity(),
+                    subject => Some(subject.to_string()),
+                }
+            }
+            variable => {
+                let steps = &find_action(file, block.name)?.process.as_ref()?.steps;
+                steps.iter().find_map(|step| match step {
+                    ProcessStep::Derive(derive) if derive.name == variable => match &derive.value {
+                        DeriveValue::Select { entity, .. } => Some(entity.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+            }
+        }
+    }
+
+    fn field(&self, entity: &str, field: &str) -> Option<Symbol> {
+        find_entity(self.file, entity)?
+            .fields
+            .iter()
+            .any(|f| f.name == field)
+            .then(|| Symbol::Field { entity: entity.to_string(), field: field.to_string() })
+    }
+
+    fn input(&self, action: &str, name: &str) -> Option<Symbol> {
+        find_action(self.file, action)?
+            .input
+            .as_ref()?
+            .fields
+            .iter()
+            .any(|p| p.name == name)
+            .then(|| Symbol::Input { action: action.to_string(), name: name.to_string() })
+    }
+
+    /// Lines of `block` from the one holding `offset` back to its start
+    fn lines_back(&self, block: &Block, offset: usize) -> impl Iterator<Item = &'a str> {
+        let end = self.text[offset..].find('\n').map_or(self.text.len(), |i| offset + i);
+        self.text[block.start..end].lines().rev()
+    }
+
+    /// The action section (`input`, `process` or `output`) that `offset` is in
+    fn section(&self, block: &Block, offset: usize) -> Option<&'a str> {
+        let line = self.lines_back(block, offset).skip(1).find(|l| indent_level(l) == 1 && !l.trim().is_empty())?;
+        line.trim().split(':').next()
+    }
+}
+
+/// Top-level definitions in source order. Decorator lines belong to the definition below them.
+fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut decorators = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let starts_definition = line.starts_with(|c: char| c.is_ascii_alphabetic() || c == '@');
+        if starts_definition {
+            if let Some(last) = blocks.last_mut().filter(|b| b.end == text.len()) {
+                last.end = decorators.unwrap_or(offset);
+            }
+            if line.starts_with('@') {
+                decorators.get_or_insert(offset);
+            } else if let Some((kind, name)) = header(line) {
+                blocks.push(Block { kind, name, start: decorators.take().unwrap_or(offset), end: text.len() });
+            }
+        }
+        offset += line.len();
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHOP: &str = r#"auth entity User:
+    id: uuid @primary
+    email: email @unique
+    role: string
+    password_hash: string
+
+entity Order:
+    id: uuid @primary
+    buyer: ref<User>
+    total: number
+    policy OwnOrder:
+        subject: @auth
+        require subject.role == "admin"
+
+@api POST /orders
+@auth
+action create_order:
+    input:
+        total: number
+    process:
+        derive buyer = select User where email == current_user.email
+        mutate Order:
+            set total = input.total
+    output: Order(id, total)
+
+rule BigOrder:
+    when Order.total > 1000
+    then create_order(Order.total)
+"#;
+
+    /// Position of the `nth` occurrence of `needle`, plus `shift` characters
+    fn at(text: &str, needle: &str, nth: usize, shift: u32) -> Position {
+        let offset = text.match_indices(needle).nth(nth).unwrap().0;
+        let document = Document::new(text.to_string());
+        let mut position = document.position(offset);
+        position.character += shift;
+        position
+    }
+
+    fn line_text(text: &str, range: Range) -> &str {
+        let line = text.lines().nth(range.start.line as usize).unwrap();
+        &line[range.start.character as usize..range.end.character as usize]
+    }
+
+    #[test]
+    fn test_diagnostics_from_parser_and_validator() {
+        let broken = Document::new("entity A:\n    id: uuid @primary\n    x: str ing\n".to_string());
+        let diagnostics = broken.diagnostics(&HashMap::new());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some(NumberOrString::String("E0001".to_string())));
+        assert_eq!(diagnostics[0].range.start.line, 2);
+
+        let invalid = Document::new(SHOP.replace("buyer: ref<User>", "buyer: ref<Customer>"));
+        let diagnostics = invalid.diagnostics(&HashMap::new());
+        assert!(diagnostics.iter().any(|d| d.severity == Some(DiagnosticSeverity::ERROR) && d.message.contains("Customer")));
+        assert!(Document::new(SHOP.to_string()).diagnostics(&HashMap::new()).iter().all(|d| d.severity != Some(DiagnosticSeverity::ERROR)));
+    }
+
+    #[test]
+    fn test_definition_and_hover() {
+        let document = Document::new(SHOP.to_string());
+        let definition = |needle, nth| document.definition(at(SHOP, needle, nth, 1)).map(|r| (r.start.line, line_text(SHOP, r)));
+        assert_eq!(definition("User>", 0), Some((0, "User")));
+        assert_eq!(definition("create_order(", 0), Some((16, "create_order")));
+        assert_eq!(definition("total", 5), Some((9, "total")), "Order.total in the rule");
+        assert_eq!(definition("total", 3), Some((18, "total")), "input.total");
+        assert_eq!(definition("role ==", 0), Some((3, "role")), "subject is the auth entity");
+        assert_eq!(definition("email == current", 0), Some((2, "email")));
+
+        let (hover, range) = document.hover(at(SHOP, "total)", 0, 1)).unwrap();
+        assert_eq!(hover, "```intent\ntotal: number\n```\n\nField of `Order`");
+        assert_eq!(line_text(SHOP, range), "total");
+        let (hover, _) = document.hover(at(SHOP, "OwnOrder", 0, 0)).unwrap();
+        assert!(hover.contains("subject: @auth\n    require subject.role == \"admin\""));
+    }
+
+    #[test]
+    fn test_completion_while_typing() {
+        let labels = |text: &str, needle: &str| {
+            let document = Document::new(SHOP.to_string());
+            let mut document = document;
+            document.update(text.to_string());
+            let position = at(text, needle, 0, needle.len() as u32);
+            document.completion(position).into_iter().map(|i| i.label).collect::<Vec<_>>()
+        };
+        // The edited text doesn't parse; names come from the last version that did
+        let text = SHOP.replace("output: Order(id, total)", "output: Order(id, total)\n        derive x = buyer.");
+        assert_eq!(labels(&text, "buyer."), ["id", "email", "role", "password_hash"]);
+        let text = SHOP.replace("set total = input.total", "set total = input.");
+        assert_eq!(labels(&text, "input."), ["total"]);
+        let text = SHOP.replace("total: number\n    policy", "total: number @\n    policy");
+        assert!(labels(&text, "number @").contains(&"validate".to_string()));
+        let text = SHOP.replace("derive buyer = select", "derive token = system ");
+        assert_eq!(labels(&text, "system "), ["jwt"]);
+        let text = SHOP.replace("derive buyer = select", "derive token = system jwt.");
+        assert_eq!(labels(&text, "jwt."), ["create", "verify"]);
+        let text = SHOP.replace("    role: string", "    role: ");
+        assert!(labels(&text, "role: ").iter().any(|l| l == "uuid") && labels(&text, "role: ").contains(&"Order".to_string()));
+    }
+
+    #[test]
+    fn test_rename_entity_and_field() {
+        let document = Document::new(SHOP.to_string());
+        let apply = |edits: Vec<TextEdit>| {
+            let renamed = Document::new(SHOP.to_string());
+            let mut text = SHOP.to_string();
+            let mut offsets: Vec<_> = edits.iter().map(|e| (renamed.offset(e.range.start), renamed.offset(e.range.end), e.new_text.clone())).collect();
+            offsets.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+            for (start, end, new_text) in offsets {
+                text.replace_range(start..end, &new_text);
+            }
+            text
+        };
+
+        let renamed = apply(document.rename(at(SHOP, "User", 0, 0), "Customer").unwrap());
+        assert_eq!(renamed.matches("Customer").count(), 3);
+        assert!(!renamed.contains("User"));
+
+        let renamed = apply(document.rename(at(SHOP, "email:", 0, 0), "mail").unwrap());
+        assert!(renamed.contains("    mail: email @unique") && renamed.contains("where mail == current_user.mail"));
+        let renamed = apply(document.rename(at(SHOP, "total", 0, 0), "amount").unwrap());
+        assert!(renamed.contains("set amount = input.total") && renamed.contains("Order(id, amount)") && renamed.contains("when Order.amount"));
+        assert!(renamed.contains("        total: number"), "the input keeps its name");
+        assert!(Document::new(renamed).diagnostics(&HashMap::new()).iter().all(|d| d.severity != Some(DiagnosticSeverity::ERROR)));
+
+        assert!(document.rename(at(SHOP, "User", 0, 0), "Order").is_err());
+        assert!(document.rename(at(SHOP, "User", 0, 0), "customer").is_err());
+        assert!(document.rename(at(SHOP, "create_order:", 0, 0), "place_order").is_err());
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let symbols = Document::new(SHOP.to_string()).symbols();
+        let names: Vec<_> = symbols.iter().map(|s| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(names, [
+            ("User", SymbolKind::STRUCT),
+            ("Order", SymbolKind::STRUCT),
+            ("create_order", SymbolKind::FUNCTION),
+            ("BigOrder", SymbolKind::EVENT),
+        ]);
+        let order = &symbols[1];
+        assert_eq!(order.range, Range::new(Position::new(6, 0), Position::new(12, 39)));
+        assert_eq!(order.selection_range, Range::new(Position::new(6, 7), Position::new(6, 12)));
+        let children: Vec<_> = order.children.as_ref().unwrap().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(children, ["id", "buyer", "total", "OwnOrder"]);
+        assert_eq!(symbols[2].detail.as_deref(), Some("POST /orders"));
+        assert_eq!(symbols[2].range.start, Position::new(14, 0));
+    }
+}
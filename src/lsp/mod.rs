@@ -0,0 +1,176 @@
+// Intent Compiler - Language Server
+// Serves diagnostics, navigation, hover, completion and rename for editors over LSP on stdio
+
+mod analysis;
+
+use std::collections::HashMap;
+use std::io;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, PublishDiagnostics};
+use lsp_types::request::{Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Rename};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, DocumentSymbolResponse, GotoDefinitionResponse, Hover, HoverContents,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url, WorkspaceEdit,
+};
+
+use crate::config;
+use crate::error::{CompileError, CompileResult};
+use crate::lint::LintLevel;
+use analysis::Document;
+
+/// Serve one editor session on stdin/stdout until it exits
+pub fn run() -> CompileResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+    serve(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+fn protocol_error(error: impl ToString) -> CompileError {
+    CompileError::IoError(io::Error::other(error.to_string()))
+}
+
+fn serve(connection: &Connection) -> CompileResult<()> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some([".", "@", " ", "<"].map(String::from).to_vec()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let capabilities = serde_json::to_value(capabilities).map_err(protocol_error)?;
+    connection.initialize(capabilities).map_err(protocol_error)?;
+
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request).map_err(protocol_error)? {
+                    return Ok(());
+                }
+                connection.sender.send(server.request(request).into()).map_err(protocol_error)?;
+            }
+            Message::Notification(notification) => {
+                if let Some(diagnostics) = server.notification(notification) {
+                    let notification = Notification::new(
+                        <PublishDiagnostics as lsp_types::notification::Notification>::METHOD.to_string(),
+                        diagnostics,
+                    );
+                    connection.sender.send(notification.into()).map_err(protocol_error)?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Lint levels from the intent.toml governing a file; defaults for unsaved or unconfigured files
+fn lint_levels(uri: &Url) -> HashMap<String, LintLevel> {
+    let config = uri.to_file_path().ok().and_then(|path| config::load_config(&path).ok());
+    config.map(|config| config.lints).unwrap_or_default()
+}
+
+/// Open documents, keyed by URI
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    /// Track document changes; returns the diagnostics to publish for the document
+    fn notification(&mut self, notification: Notification) -> Option<PublishDiagnosticsParams> {
+        use lsp_types::notification::Notification as _;
+        let (uri, document) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD).ok()?;
+                let uri = params.text_document.uri;
+                let document = self.documents.entry(uri.clone()).or_insert_with(|| Document::new(String::new()));
+                document.update(params.text_document.text);
+                (uri, Some(&*document))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidChangeTextDocumentParams>(DidChangeTextDocument::METHOD).ok()?;
+                let uri = params.text_document.uri;
+                let document = self.documents.get_mut(&uri)?;
+                // Full sync: the last change holds the whole text
+                document.update(params.content_changes.into_iter().last()?.text);
+                (uri, Some(&*document))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidCloseTextDocumentParams>(DidCloseTextDocument::METHOD).ok()?;
+                self.documents.remove(&params.text_document.uri);
+                // Clear the closed document's diagnostics
+                (params.text_document.uri, None)
+            }
+            _ => return None,
+        };
+        let diagnostics = document.map(|d| d.diagnostics(&lint_levels(&uri))).unwrap_or_default();
+        Some(PublishDiagnosticsParams::new(uri, diagnostics, None))
+    }
+
+    fn request(&self, request: Request) -> Response {
+        use lsp_types::request::Request as _;
+        match request.method.as_str() {
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, |server, params| {
+                let position = params.text_document_position_params;
+                let uri = position.text_document.uri;
+                let range = server.document(&uri)?.definition(position.position);
+                Ok(range.map(|range| GotoDefinitionResponse::Scalar(Location::new(uri, range))))
+            }),
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, |server, params| {
+                let position = params.text_document_position_params;
+                let hover = server.document(&position.text_document.uri)?.hover(position.position);
+                Ok(hover.map(|(value, range)| Hover {
+                    contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value }),
+                    range: Some(range),
+                }))
+            }),
+            Completion::METHOD => self.respond::<Completion>(request, |server, params| {
+                let position = params.text_document_position;
+                let items = server.document(&position.text_document.uri)?.completion(position.position);
+                Ok(Some(CompletionResponse::Array(items)))
+            }),
+            DocumentSymbolRequest::METHOD => self.respond::<DocumentSymbolRequest>(request, |server, params| {
+                let symbols = server.document(&params.text_document.uri)?.symbols();
+                Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+            }),
+            Rename::METHOD => self.respond::<Rename>(request, |server, params| {
+                let position = params.text_document_position;
+                let uri = position.text_document.uri;
+                let edits = server.document(&uri)?.rename(position.position, &params.new_name)?;
+                Ok(Some(WorkspaceEdit::new(HashMap::from([(uri, edits)]))))
+            }),
+            _ => Response::new_err(request.id, ErrorCode::MethodNotFound as i32, format!("unsupported request {}", request.method)),
+        }
+    }
+
+    /// Decode the parameters of `R`, run `handler` and encode its result or error
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: impl FnOnce(&Self, R::Params) -> Result<R::Result, String>,
+    ) -> Response {
+        let id = request.id.clone();
+        let params = match request.extract::<R::Params>(R::METHOD) {
+            Ok((_, params)) => params,
+            Err(e) => return Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+        };
+        match handler(self, params) {
+            Ok(result) => Response::new_ok(id, result),
+            Err(message) => Response::new_err(id, ErrorCode::RequestFailed as i32, message),
+        }
+    }
+
+    fn document(&self, uri: &Url) -> Result<&Document, String> {
+        self.documents.get(uri).ok_or_else(|| format!("{} is not open", uri))
+    }
+}
@@ -2,67 +2,446 @@
 // Orchestrates parsing, validation, and code generation
 
 mod ast;
+mod audit;
 mod cli;
 mod codegen;
+mod codes;
+mod compat;
+mod config;
 mod error;
+mod fmt;
+mod indexes;
+mod ir;
+mod lint;
+mod lsp;
 mod parser;
 mod validator;
 mod preprocessor;
+mod report;
+mod watch;
 
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 use colored::Colorize;
 
-use cli::{Cli, Commands};
-use codegen::create_generator;
-use error::CompileResult;
+use cli::{AstFormat, Cli, Commands, DocumentFormat, MessageFormat};
+use codegen::diff;
+use codegen::manifest::Manifest;
+use codegen::output::{self, Change};
+use codegen::{GenerationResult, GeneratorOptions, create_generator};
+use config::ProjectConfig;
+use error::{CompileError, CompileResult, SourceMap};
+use report::Reporter;
 
 fn main() -> ExitCode {
     let cli = Cli::parse_args();
 
+    let mut reporter = match &cli.command {
+        Commands::Compile { allow, message_format, .. }
+        | Commands::Check { allow, message_format, .. }
+        | Commands::Audit { allow, message_format, .. }
+        | Commands::Lint { allow, message_format, .. } => Reporter::new(*message_format, allow.clone()),
+        Commands::Diff { allow, .. } => Reporter::new(MessageFormat::Human, allow.clone()),
+        _ => Reporter::new(MessageFormat::Human, Vec::new()),
+    };
+    // Progress output would corrupt machine-readable documents on stdout
+    let verbose = cli.verbose && reporter.is_human();
+
     let result = match cli.command {
-        Commands::Compile { input, output, target, tests: _ } => {
-            compile_intent(&input, &output, target.as_deref(), cli.verbose)
+        Commands::Compile {
+            input, from_ast, output, target, tests, no_tests, database_url, auto_index, force, dry_run, allow_destructive,
+            watch: true, allow, message_format,
+        } => {
+            let flags = CompileFlags { from_ast, output, target, tests, no_tests, database_url, auto_index, force, dry_run, allow_destructive };
+            watch_builds(message_format, allow, |reporter, files| {
+                let settings = CompileSettings::resolve(input.clone(), flags.clone())?;
+                *files = watched_files(&settings.config, &settings.inputs, settings.options.template_dir.as_deref());
+                compile_intent(&settings, reporter, verbose)
+            })
+        }
+        Commands::Compile { input, from_ast, output, target, tests, no_tests, database_url, auto_index, force, dry_run, allow_destructive, .. } => {
+            let flags = CompileFlags { from_ast, output, target, tests, no_tests, database_url, auto_index, force, dry_run, allow_destructive };
+            CompileSettings::resolve(input, flags)
+                .and_then(|settings| compile_intent(&settings, &mut reporter, verbose))
+        }
+        Commands::Diff { input, output, target, tests, no_tests, database_url, auto_index, stat, exit_code, .. } => {
+            // A preview reports data loss as warnings rather than refusing
+            let flags = CompileFlags {
+                from_ast: None, output, target, tests, no_tests, database_url, auto_index,
+                force: false, dry_run: false, allow_destructive: true,
+            };
+            match CompileSettings::resolve(input, flags).and_then(|settings| diff_intent(&settings, &mut reporter, verbose, stat)) {
+                Ok(changed) => {
+                    reporter.finish();
+                    return if changed && exit_code { ExitCode::FAILURE } else { ExitCode::SUCCESS };
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Commands::Check { input, watch: true, allow, message_format } => {
+            watch_builds(message_format, allow, |reporter, files| {
+                let (config, inputs) = project_inputs(input.clone())?;
+                *files = watched_files(&config, &inputs, None);
+                check_intent(&config, &inputs, reporter, verbose)
+            })
+        }
+        Commands::Check { input, .. } => {
+            project_inputs(input).and_then(|(config, inputs)| check_intent(&config, &inputs, &mut reporter, verbose))
+        }
+        Commands::Fmt { input, check } => {
+            match project_inputs(input).and_then(|(_, inputs)| format_files(&inputs, check, &mut reporter)) {
+                Ok(formatted) => {
+                    reporter.finish();
+                    return if formatted { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Commands::Audit { input, fail_on, .. } => match audit_file(&input, fail_on, &mut reporter) {
+            Ok(passed) => {
+                reporter.finish();
+                return if passed { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+            }
+            Err(e) => Err(e),
+        },
+        Commands::Openapi { input, output, format } => {
+            project_inputs(input).and_then(|(config, inputs)| {
+                let program = load_program(&inputs, &mut reporter)?;
+                let title = config.project.name.as_deref().unwrap_or(codegen::DEFAULT_TITLE);
+                write_document(&codegen::openapi::document(&program, title), output.as_deref(), format)
+            })
+        }
+        Commands::Ast { input, output, format: AstFormat::Json } => {
+            project_inputs(input).and_then(|(_, inputs)| {
+                let source = read_sources(&inputs, &mut reporter)?;
+                let mut ast = parser::parse_intent(&source)?;
+                preprocessor::inject_auth_actions(&mut ast);
+                write_document(&ast, output.as_deref(), Some(DocumentFormat::Json))
+            })
         }
-        Commands::Check { input } => {
-            check_intent(&input, cli.verbose)
+        Commands::Schema { input, output } => {
+            project_inputs(input).and_then(|(config, inputs)| {
+                let program = load_program(&inputs, &mut reporter)?;
+                let title = config.project.name.as_deref().unwrap_or(codegen::DEFAULT_TITLE);
+                write_document(&codegen::jsonschema::bundle(&program, title), output.as_deref(), Some(DocumentFormat::Json))
+            })
+        }
+        Commands::Compat { old, new } => match compat_files(&old, &new, &mut reporter) {
+            Ok(compatible) => {
+                reporter.finish();
+                return if compatible { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+            }
+            Err(e) => Err(e),
+        },
+        Commands::Lsp => lsp::run(),
+        Commands::Lint { list: true, .. } => {
+            list_lints();
+            return ExitCode::SUCCESS;
+        }
+        Commands::Lint { input, .. } => {
+            // clap guarantees an input unless --list was given
+            let input = input.expect("input is required without --list");
+            lint_file(&input, &mut reporter)
+        }
+        Commands::Explain { code } => {
+            return explain_code(&code);
         }
         Commands::Init { name, example } => {
-            init_project(&name, example, cli.verbose)
+            init_project(&name, example, verbose)
         }
     };
 
+    if let Err(e) = &result {
+        reporter.error(e);
+    }
+    reporter.finish();
+
     match result {
         Ok(_) => ExitCode::SUCCESS,
-        Err(e) => {
-            eprintln!("{}", e.format_colored(None));
-            ExitCode::FAILURE
+        Err(_) => ExitCode::FAILURE,
+    }
+}
+
+/// CLI flags of `compile` that override intent.toml
+#[derive(Clone)]
+struct CompileFlags {
+    from_ast: Option<PathBuf>,
+    output: Option<PathBuf>,
+    target: Option<String>,
+    tests: bool,
+    no_tests: bool,
+    database_url: Option<String>,
+    auto_index: bool,
+    force: bool,
+    dry_run: bool,
+    allow_destructive: bool,
+}
+
+/// Compile settings: intent.toml values with CLI flags applied on top
+struct CompileSettings {
+    config: ProjectConfig,
+    inputs: Vec<PathBuf>,
+    /// The single input is an AST dump rather than intent source
+    from_ast: bool,
+    output: PathBuf,
+    target: Option<String>,
+    auto_index: bool,
+    /// Overwrite files that were edited by hand or are user-owned
+    force: bool,
+    /// Report the changes to the output without writing them
+    dry_run: bool,
+    options: GeneratorOptions,
+}
+
+impl CompileSettings {
+    fn resolve(input: Option<PathBuf>, flags: CompileFlags) -> CompileResult<Self> {
+        let from_ast = flags.from_ast.is_some();
+        let (config, inputs) = match flags.from_ast {
+            Some(ast) => (config::load_config(&ast)?, vec![ast]),
+            None => project_inputs(input)?,
+        };
+        let output = flags
+            .output
+            .or_else(|| config.project.output.as_deref().map(|o| config.resolve(o)))
+            .unwrap_or_else(|| PathBuf::from("./output"));
+        let target = flags.target.or_else(|| config.project.target.clone());
+        let tests = if flags.no_tests {
+            false
+        } else {
+            flags.tests || config.features.tests
+        };
+        let database_name = config.project.name.as_deref().unwrap_or("app");
+        let options = GeneratorOptions {
+            package_name: config.project.name.clone(),
+            dialect: config.database.dialect,
+            database_url: flags.database_url.unwrap_or_else(|| config.database.url(database_name)),
+            tests,
+            migrations: config.features.migrations,
+            generation_gap: config.features.generation_gap,
+            template_dir: Some(config.template_dir(&inputs)),
+            allow_destructive: flags.allow_destructive,
+        };
+        Ok(Self {
+            auto_index: flags.auto_index || config.features.auto_index,
+            force: flags.force,
+            dry_run: flags.dry_run,
+            config,
+            inputs,
+            from_ast,
+            output,
+            target,
+            options,
+        })
+    }
+}
+
+/// Load intent.toml for `input`, or for the working directory when no input is given,
+/// and return it with the files to compile
+fn project_inputs(input: Option<PathBuf>) -> CompileResult<(ProjectConfig, Vec<PathBuf>)> {
+    match input {
+        Some(input) => Ok((config::load_config(&input)?, vec![input])),
+        None => {
+            let config = config::load_config(Path::new("."))?;
+            let inputs = config.inputs();
+            if inputs.is_empty() {
+                return Err(CompileError::config(format!(
+                    "no input file: pass --input or list [project] inputs in {}",
+                    config::CONFIG_FILE
+                )));
+            }
+            Ok((config, inputs))
+        }
+    }
+}
+
+/// The files a build reads: its inputs, the intent.toml configuring them and, for builds
+/// that generate code, the directory of template overrides
+fn watched_files(config: &ProjectConfig, inputs: &[PathBuf], template_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut files = inputs.to_vec();
+    if config.loaded {
+        files.push(config.root.join(config::CONFIG_FILE));
+    }
+    files.extend(template_dir.map(Path::to_path_buf));
+    files
+}
+
+/// Rerun `build` on every change to the files it reads, reporting each run's diagnostics
+/// as it finishes. `build` lists the files to watch as soon as it knows them.
+fn watch_builds(
+    format: MessageFormat,
+    allow: Vec<String>,
+    mut build: impl FnMut(&mut Reporter, &mut Vec<PathBuf>) -> CompileResult<()>,
+) -> CompileResult<()> {
+    watch::watch(|files| {
+        let mut reporter = Reporter::new(format, allow.clone());
+        if let Err(e) = build(&mut reporter, files) {
+            reporter.error(&e);
         }
+        reporter.finish();
+    })
+}
+
+/// Read input files into one compilation unit and register them with the reporter
+fn read_sources(inputs: &[PathBuf], reporter: &mut Reporter) -> CompileResult<String> {
+    let mut sources = SourceMap::default();
+    let mut source = String::new();
+    for input in inputs {
+        sources.push(input.display().to_string(), &fs::read_to_string(input)?, &mut source);
     }
+    reporter.set_sources(sources);
+    Ok(source)
+}
+
+/// Display a list of input files
+fn display_inputs(inputs: &[PathBuf]) -> String {
+    inputs.iter().map(|i| i.display().to_string()).collect::<Vec<_>>().join(", ")
 }
 
-/// Compile an intent file to target language
-fn compile_intent(input: &Path, output: &Path, target: Option<&str>, verbose: bool) -> CompileResult<()> {
+/// Compile intent files to target language
+fn compile_intent(settings: &CompileSettings, reporter: &mut Reporter, verbose: bool) -> CompileResult<()> {
     use std::time::Instant;
     
     let total_start = Instant::now();
+    let output = settings.output.as_path();
     
     if verbose {
-        println!("{} {} → {}", "Compiling".green().bold(), input.display(), output.display());
+        println!("{} {} → {}", "Compiling".green().bold(), display_inputs(&settings.inputs), output.display());
     }
 
-    // Read source file
-    let source = fs::read_to_string(input)?;
+    let result = generate_output(settings, reporter, verbose)?;
 
-    // Parse
+    // Refuse to clobber hand edits to files generated last time
+    let previous = Manifest::load(output)?;
+    let edited = previous.edited_files(output)?;
+    if !edited.is_empty() && !settings.force {
+        return Err(CompileError::edited_output(format!(
+            "generated files were edited since the last compilation: {} (pass --force to overwrite them)",
+            edited.join(", ")
+        )));
+    }
+    for path in &edited {
+        reporter.codegen_warning(&format!("overwriting hand-edited file {}", path));
+    }
+    // Nor files it didn't generate, such as user-owned files when generation_gap is turned off
+    let unowned = previous.unowned_files(output, &result)?;
+    if !unowned.is_empty() && !settings.force {
+        return Err(CompileError::edited_output(format!(
+            "files not generated by the last compilation would be overwritten: {} (pass --force to overwrite them)",
+            unowned.join(", ")
+        )));
+    }
+    for path in &unowned {
+        reporter.codegen_warning(&format!("overwriting file {}, which was not generated", path));
+    }
+
+    // Files the previous compilation generated but this one did not are removed
+    let manifest = Manifest::record(&result);
+    let orphans = previous.orphans(&manifest, &result);
+
+    if settings.dry_run {
+        let changes = output::plan(output, &result, &orphans)?;
+        if reporter.is_human() {
+            for (change, path) in &changes {
+                println!("  {:>6} {}", change.to_string().yellow(), path);
+            }
+            println!("{} {} changes to {} (dry run, nothing written)", "✓".green().bold(), changes.len(), output.display());
+        }
+        return Ok(());
+    }
+
+    output::commit(output, &result, &orphans, &manifest)?;
+
+    if verbose {
+        for file in result.files.keys() {
+            println!("      {} {}", "→".blue(), file);
+        }
+        for file in &orphans {
+            println!("      {} {}", "-".red(), file);
+        }
+    }
+
+    if reporter.is_human() {
+        let total_time = total_start.elapsed();
+        println!("{} Compilation complete!", "✓".green().bold());
+        println!("  Output: {}", output.display());
+        println!("  Build time: {}ms", total_time.as_millis());
+    }
+
+    Ok(())
+}
+
+/// Print what recompiling would change in the output directory, returning whether it
+/// would change anything
+fn diff_intent(settings: &CompileSettings, reporter: &mut Reporter, verbose: bool, stat: bool) -> CompileResult<bool> {
+    let output = settings.output.as_path();
+    if verbose {
+        println!("{} {} → {}", "Diffing".green().bold(), display_inputs(&settings.inputs), output.display());
+    }
+
+    let result = generate_output(settings, reporter, verbose)?;
+    let previous = Manifest::load(output)?;
+    let orphans = previous.orphans(&Manifest::record(&result), &result);
+    let diffs = diff::diff_output(output, &result, &orphans)?;
+
+    if !stat {
+        for file in &diffs {
+            for line in file.patch.lines() {
+                println!("{}", color_patch_line(line));
+            }
+        }
+    }
+
+    let width = diffs.iter().map(|d| d.path.len()).max().unwrap_or(0);
+    for file in &diffs {
+        println!(" {:<width$} | {} {}", file.path, format!("+{}", file.added).green(), format!("-{}", file.removed).red());
+    }
+    let count = |change| diffs.iter().filter(|d| d.change == change).count();
+    println!(
+        " {} files changed ({} added, {} removed, {} changed), {} insertions(+), {} deletions(-)",
+        diffs.len(),
+        count(Change::Create),
+        count(Change::Delete),
+        count(Change::Update),
+        diffs.iter().map(|d| d.added).sum::<usize>(),
+        diffs.iter().map(|d| d.removed).sum::<usize>(),
+    );
+
+    Ok(!diffs.is_empty())
+}
+
+/// Color a line of a unified diff the way `git diff` does
+fn color_patch_line(line: &str) -> colored::ColoredString {
+    if line.starts_with("+++") || line.starts_with("---") {
+        line.bold()
+    } else if line.starts_with('+') {
+        line.green()
+    } else if line.starts_with('-') {
+        line.red()
+    } else if line.starts_with("@@") {
+        line.cyan()
+    } else {
+        line.normal()
+    }
+}
+
+/// Parse, validate and lower the inputs and generate the output tree in memory
+fn generate_output(settings: &CompileSettings, reporter: &mut Reporter, verbose: bool) -> CompileResult<GenerationResult> {
+    use std::time::Instant;
+
+    // Parse, or load a dumped AST; its locations refer to the original source, which isn't available
     let parse_start = Instant::now();
     if verbose {
         println!("  {} Parsing...", "→".blue());
     }
-    let mut ast = parser::parse_intent(&source)?;
-    
+    let (mut ast, source) = if settings.from_ast {
+        (load_ast(&settings.inputs[0], reporter)?, String::new())
+    } else {
+        let source = read_sources(&settings.inputs, reporter)?;
+        (parser::parse_intent(&source)?, source)
+    };
+
     // Inject default auth actions if applicable
     preprocessor::inject_auth_actions(&mut ast);
     let parse_time = parse_start.elapsed();
@@ -82,21 +461,28 @@ fn compile_intent(input: &Path, output: &Path, target: Option<&str>, verbose: bo
     if verbose {
         println!("  {} Validating...", "→".blue());
     }
-    let ctx = validator::validate(&ast)?;
-    let validate_time = validate_start.elapsed();
-
-    // Print warnings
-    for warning in &ctx.warnings {
-        eprintln!("{}", warning.format_colored());
+    let (validation, warnings) = validator::validate(&ast);
+    for warning in &warnings {
+        reporter.warning(warning);
+    }
+    validation?;
+    if settings.auto_index {
+        for added in indexes::apply_suggestions(&mut ast) {
+            if verbose {
+                println!("    {} Added index on {}({})", "+".green(), added.entity, added.columns.join(", "));
+            }
+        }
     }
+    run_lints(&settings.config, &ast, &source, reporter)?;
+    let validate_time = validate_start.elapsed();
 
     if verbose {
         println!("    {} Validation passed ({}ms)", "✓".green(), validate_time.as_millis());
     }
 
     // Parse target language (defaults to python)
-    let target_lang = cli::parse_target_language(target)
-        .map_err(|e| error::CompileError::codegen(e))?;
+    let target_lang = cli::parse_target_language(settings.target.as_deref())
+        .map_err(error::CompileError::codegen)?;
 
     // Generate code
     let generate_start = Instant::now();
@@ -104,47 +490,50 @@ fn compile_intent(input: &Path, output: &Path, target: Option<&str>, verbose: bo
         println!("  {} Generating {} code...", "→".blue(), target_lang);
     }
 
-    // Create output directory
-    fs::create_dir_all(output)?;
-
-    let generator = create_generator(target_lang);
-    let result = generator.generate(&ast, output)?;
+    let program = ir::Program::lower(&ast);
+    let generator = create_generator(target_lang, settings.options.clone());
+    let result = generator.generate(&program, &settings.output)?;
     let generate_time = generate_start.elapsed();
 
     if verbose {
         println!("    {} Generated {} files ({} lines) in {}ms", 
             "✓".green(),
-            result.files_created.len(),
-            result.lines_generated,
+            result.files.len(),
+            result.lines_generated(),
             generate_time.as_millis()
         );
-        
-        for file in &result.files_created {
-            println!("      {} {}", "→".blue(), file);
-        }
     }
 
-    // Print warnings from generation
+    // Report warnings from generation
     for warning in &result.warnings {
-        eprintln!("{}: {}", "warning".yellow().bold(), warning);
+        reporter.codegen_warning(warning);
     }
 
-    let total_time = total_start.elapsed();
-    println!("{} Compilation complete!", "✓".green().bold());
-    println!("  Output: {}", output.display());
-    println!("  Build time: {}ms", total_time.as_millis());
+    Ok(result)
+}
 
-    Ok(())
+/// Read a JSON AST written by `intentc ast`
+fn load_ast(path: &Path, reporter: &mut Reporter) -> CompileResult<ast::IntentFile> {
+    // Errors point into the AST file; once loaded, locations refer to the original source instead
+    let content = read_sources(&[path.to_path_buf()], reporter)?;
+    let ast = serde_json::from_str(&content).map_err(|e| {
+        // Drop the position serde_json appends; the location carries it
+        let message = e.to_string();
+        let message = message.split(" at line ").next().unwrap_or_default();
+        CompileError::parse(format!("invalid AST: {}", message), e.line(), e.column())
+    })?;
+    reporter.set_sources(SourceMap::default());
+    Ok(ast)
 }
 
-/// Check an intent file without generating code
-fn check_intent(input: &Path, verbose: bool) -> CompileResult<()> {
+/// Check intent files without generating code
+fn check_intent(config: &ProjectConfig, inputs: &[PathBuf], reporter: &mut Reporter, verbose: bool) -> CompileResult<()> {
     if verbose {
-        println!("{} {}", "Checking".green().bold(), input.display());
+        println!("{} {}", "Checking".green().bold(), display_inputs(inputs));
     }
 
-    // Read source file
-    let source = fs::read_to_string(input)?;
+    // Read source files
+    let source = read_sources(inputs, reporter)?;
 
     // Parse
     let mut ast = parser::parse_intent(&source)?;
@@ -162,18 +551,182 @@ fn check_intent(input: &Path, verbose: bool) -> CompileResult<()> {
     }
 
     // Validate
-    let ctx = validator::validate(&ast)?;
+    let (validation, warnings) = validator::validate(&ast);
+    for warning in &warnings {
+        reporter.warning(warning);
+    }
+    validation?;
+    run_lints(config, &ast, &source, reporter)?;
+
+    if reporter.is_human() {
+        println!("{} No errors found!", "✓".green().bold());
+    }
+
+    Ok(())
+}
+
+/// Rewrite intent files in canonical style. With `check`, only list the files that would
+/// change; returns false if there are any.
+fn format_files(inputs: &[PathBuf], check: bool, reporter: &mut Reporter) -> CompileResult<bool> {
+    let mut changed = 0;
+    for input in inputs {
+        let source = read_sources(std::slice::from_ref(input), reporter)?;
+        let formatted = fmt::format_source(&source)?;
+        if formatted == source {
+            continue;
+        }
+        changed += 1;
+        if check {
+            println!("  {} {}", "unformatted".yellow(), input.display());
+        } else {
+            fs::write(input, formatted)?;
+            println!("  {} {}", "formatted".green(), input.display());
+        }
+    }
+
+    if check && changed > 0 {
+        println!("{} {} of {} file(s) need formatting", "✗".red().bold(), changed, inputs.len());
+        return Ok(false);
+    }
+    let verb = if check { "already formatted" } else { "formatted" };
+    println!("{} {} file(s) {}", "✓".green().bold(), inputs.len(), verb);
+    Ok(true)
+}
 
-    // Print warnings
-    for warning in &ctx.warnings {
-        eprintln!("{}", warning.format_colored());
+/// Run the lints configured in intent.toml, reporting their warnings
+fn run_lints(config: &ProjectConfig, ast: &ast::IntentFile, source: &str, reporter: &mut Reporter) -> CompileResult<()> {
+    let (result, warnings) = lint::run_lints(ast, source, &config.lints);
+    for warning in &warnings {
+        reporter.warning(warning);
     }
+    result
+}
 
-    println!("{} No errors found!", "✓".green().bold());
+/// Validate an intent file and report lint findings only
+fn lint_file(input: &Path, reporter: &mut Reporter) -> CompileResult<()> {
+    let source = read_sources(&[input.to_path_buf()], reporter)?;
+    let mut ast = parser::parse_intent(&source)?;
+    preprocessor::inject_auth_actions(&mut ast);
 
+    // Lints assume a well-formed file, so validation errors still fail the run
+    let (validation, _) = validator::validate(&ast);
+    validation?;
+    run_lints(&config::load_config(input)?, &ast, &source, reporter)?;
+
+    if reporter.is_human() {
+        println!("{} Lint complete", "✓".green().bold());
+    }
     Ok(())
 }
 
+/// Report security findings. Returns false if any reportable finding reaches `fail_on`.
+fn audit_file(input: &Path, fail_on: audit::Severity, reporter: &mut Reporter) -> CompileResult<bool> {
+    let source = read_sources(&[input.to_path_buf()], reporter)?;
+    let ast = parser::parse_intent(&source)?;
+
+    // Validate the file as compiled, but audit it as written: injected auth actions are safe by construction
+    let mut compiled = ast.clone();
+    preprocessor::inject_auth_actions(&mut compiled);
+    let (validation, _) = validator::validate(&compiled);
+    validation?;
+
+    let findings: Vec<_> = audit::audit(&ast)
+        .into_iter()
+        .filter(|f| !reporter.is_allowed(f.code))
+        .collect();
+    for finding in &findings {
+        reporter.finding(finding);
+    }
+
+    let failing = findings.iter().filter(|f| f.severity >= fail_on).count();
+    if reporter.is_human() {
+        if failing > 0 {
+            println!("{} {} finding(s), {} at {} or above", "✗".red().bold(), findings.len(), failing, fail_on);
+        } else {
+            println!("{} Audit complete: {} finding(s)", "✓".green().bold(), findings.len());
+        }
+    }
+    Ok(failing == 0)
+}
+
+/// Parse, validate and lower intent files as `compile` would
+fn load_program(inputs: &[PathBuf], reporter: &mut Reporter) -> CompileResult<ir::Program> {
+    let source = read_sources(inputs, reporter)?;
+    let mut ast = parser::parse_intent(&source)?;
+    preprocessor::inject_auth_actions(&mut ast);
+    let (validation, _) = validator::validate(&ast);
+    validation?;
+    Ok(ir::Program::lower(&ast))
+}
+
+/// Print the API changes from `old` to `new`. Returns false if any of them is breaking.
+fn compat_files(old: &Path, new: &Path, reporter: &mut Reporter) -> CompileResult<bool> {
+    let old = load_program(&[old.to_path_buf()], reporter)?;
+    let new = load_program(&[new.to_path_buf()], reporter)?;
+    let changes = compat::compare(&old, &new);
+
+    for change in &changes {
+        let impact = match change.impact {
+            compat::Impact::Breaking => change.impact.to_string().red().bold(),
+            compat::Impact::NonBreaking => change.impact.to_string().green(),
+        };
+        println!("{:<12} {}: {}", impact, change.action.bold(), change.message);
+    }
+
+    let breaking = changes.iter().filter(|c| c.impact == compat::Impact::Breaking).count();
+    if breaking > 0 {
+        println!("{} {} breaking change(s), {} non-breaking", "✗".red().bold(), breaking, changes.len() - breaking);
+    } else {
+        println!("{} No breaking changes ({} non-breaking)", "✓".green().bold(), changes.len());
+    }
+    Ok(breaking == 0)
+}
+
+/// Write an exported document to `output`, or stdout without one
+fn write_document(document: &impl serde::Serialize, output: Option<&Path>, format: Option<DocumentFormat>) -> CompileResult<()> {
+    let is_json = output.is_some_and(|o| o.extension().is_some_and(|e| e == "json"));
+    let format = format.unwrap_or(if is_json { DocumentFormat::Json } else { DocumentFormat::Yaml });
+    let content = match format {
+        DocumentFormat::Json => serde_json::to_string_pretty(document).map(|json| json + "\n").map_err(|e| e.to_string()),
+        DocumentFormat::Yaml => serde_yaml_ng::to_string(document).map_err(|e| e.to_string()),
+    }
+    .map_err(|e| CompileError::codegen(format!("failed to serialize document: {}", e)))?;
+
+    match output {
+        Some(path) => fs::write(path, content)?,
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Print every lint with its code and default level
+fn list_lints() {
+    for lint in lint::LINTS {
+        let level = format!("{:?}", lint.default_level).to_lowercase();
+        println!("{:<22} {}  {:<5}  {}", lint.name.bold(), lint.code, level, lint.description);
+    }
+}
+
+/// Print the long-form explanation of a diagnostic code
+fn explain_code(code: &str) -> ExitCode {
+    let Some(entry) = codes::lookup(code) else {
+        eprintln!("{}: unknown diagnostic code '{}'", "error".red().bold(), code);
+        return ExitCode::FAILURE;
+    };
+
+    println!("{}: {}\n", entry.code.bold(), entry.title);
+    println!("{}\n", entry.explanation);
+    println!("{}\n", "Erroneous example:".red().bold());
+    for line in entry.bad.lines() {
+        println!("    {}", line);
+    }
+    println!("\n{}\n", "Fixed:".green().bold());
+    for line in entry.fixed.lines() {
+        println!("    {}", line);
+    }
+    ExitCode::SUCCESS
+}
+
 /// Initialize a new intent project
 fn init_project(name: &str, include_example: bool, verbose: bool) -> CompileResult<()> {
     if verbose {
@@ -195,7 +748,7 @@ An Intent Compiler project.
 ## Getting Started
 
 1. Edit `src/app.intent` to define your application
-2. Run `intentc compile -i src/app.intent -o output`
+2. Run `intentc compile` (settings are read from `intent.toml`)
 3. Start the generated API: `cd output && python main.py`
 
 ## Learn More
@@ -258,6 +811,35 @@ rule LogNewUser:
         fs::write(project_dir.join("src/app.intent"), example)?;
     }
 
+    // Create intent.toml, keeping one that is already there
+    let config_path = project_dir.join(config::CONFIG_FILE);
+    if config_path.exists() {
+        if verbose {
+            println!("  {} Keeping existing {}", "→".blue(), config::CONFIG_FILE);
+        }
+    } else {
+        let manifest = format!(r#"[project]
+name = "{}"
+inputs = ["src/app.intent"]
+output = "output"
+target = "python"
+
+[database]
+# sqlite, postgresql or mysql; set url to override the local default
+dialect = "sqlite"
+
+[features]
+tests = true
+migrations = true
+auto_index = false
+generation_gap = false
+
+[lints]
+# unused_entity = "allow"
+"#, project_dir.file_name().map(|n| n.to_string_lossy()).unwrap_or_default());
+        fs::write(&config_path, manifest)?;
+    }
+
     // Create .gitignore
     let gitignore = r#"# Generated code
 output/
@@ -292,12 +874,10 @@ Thumbs.db
     println!("  ");
     println!("  Next steps:");
     println!("    cd {}", name);
-    if include_example {
-        println!("    intentc compile -i src/app.intent -o output");
-    } else {
-        println!("    # Create your .intent files in src/");
-        println!("    intentc compile -i src/app.intent -o output");
+    if !include_example {
+        println!("    # Create src/app.intent");
     }
+    println!("    intentc compile");
 
     Ok(())
 }
@@ -67,9 +67,22 @@ fn parse_entity(pair: pest::iterators::Pair<Rule>, is_auth: bool) -> CompileResu
     let mut name = String::new();
     let mut fields = Vec::new();
     let mut policies = Vec::new();
+    let mut renamed_from_name = None;
+    let mut indexes = Vec::new();
 
     for inner in pair.into_inner() {
         match inner.as_rule() {
+            Rule::entity_decorators => {
+                for decorator in inner.into_inner().flat_map(|d| d.into_inner()) {
+                    match decorator.as_rule() {
+                        Rule::renamed_from_decorator => renamed_from_name = Some(renamed_from(decorator)),
+                        Rule::index_decorator => {
+                            indexes.push(decorator.into_inner().map(|c| c.as_str().to_string()).collect());
+                        }
+                        _ => {}
+                    }
+                }
+            }
             Rule::entity_name => name = inner.as_str().to_string(),
             Rule::entity_fields => {
                 for item_wrapper in inner.into_inner() {
@@ -100,7 +113,15 @@ fn parse_entity(pair: pest::iterators::Pair<Rule>, is_auth: bool) -> CompileResu
         }
     }
 
-    Ok(Entity { name, fields, policies, is_auth, location })
+    Ok(Entity { name, fields, policies, is_auth, indexes, renamed_from: renamed_from_name, location })
+}
+
+/// The quoted name of a `@renamed_from("...")` decorator
+fn renamed_from(pair: pest::iterators::Pair<Rule>) -> String {
+    pair.into_inner()
+        .find(|p| p.as_rule() == Rule::string_literal)
+        .map(|p| p.as_str().trim_matches('"').to_string())
+        .unwrap_or_default()
 }
 
 /// Parse field definition
@@ -210,6 +231,7 @@ fn parse_decorator(pair: pest::iterators::Pair<Rule>) -> CompileResult<Option<De
                             "optional" => Decorator::Optional,
                             "auto" => Decorator::Auto,
                             "index" => Decorator::Index,
+                            "expose" => Decorator::Expose,
                             _ => return Ok(None),
                         }));
                     }
@@ -221,10 +243,8 @@ fn parse_decorator(pair: pest::iterators::Pair<Rule>) -> CompileResult<Option<De
                             if auth_inner.as_rule() == Rule::auth_target {
                                 for target_inner in auth_inner.into_inner() {
                                     match target_inner.as_rule() {
-                                        Rule::type_name | Rule::identifier => {
-                                            if name.is_none() {
-                                                name = Some(target_inner.as_str().to_string());
-                                            }
+                                        Rule::type_name | Rule::identifier if name.is_none() => {
+                                            name = Some(target_inner.as_str().to_string());
                                         }
                                         Rule::auth_args => {
                                             for arg_inner in target_inner.into_inner() {
@@ -335,6 +355,7 @@ fn parse_decorator(pair: pest::iterators::Pair<Rule>) -> CompileResult<Option<De
                             }
                         }
                     }
+                    Rule::renamed_from_decorator => return Ok(Some(Decorator::RenamedFrom(renamed_from(dec_inner)))),
                     _ => {}
                 }
             }
@@ -358,10 +379,9 @@ fn parse_action(pair: pest::iterators::Pair<Rule>) -> CompileResult<Action> {
                 for dec_wrapper in inner.into_inner() {
                     if dec_wrapper.as_rule() == Rule::pre_action_decorator {
                         for dec in dec_wrapper.into_inner() {
-                            if dec.as_rule() == Rule::decorator {
-                                if let Some(d) = parse_decorator(dec)? {
-                                    decorators.push(d);
-                                }
+                            if dec.as_rule() == Rule::decorator
+                                && let Some(d) = parse_decorator(dec)? {
+                                decorators.push(d);
                             }
                         }
                     }
@@ -565,7 +585,7 @@ fn parse_derive_expr(pair: pest::iterators::Pair<Rule>) -> CompileResult<DeriveV
                 return Ok(DeriveValue::FieldAccess { path });
             }
             Rule::literal => {
-                for lit_inner in inner.into_inner() {
+                if let Some(lit_inner) = inner.into_inner().next() {
                     return Ok(DeriveValue::Literal(parse_literal_value(lit_inner)?));
                 }
             }
@@ -707,7 +727,7 @@ fn parse_field_ref(pair: pest::iterators::Pair<Rule>) -> CompileResult<FieldRefe
                 return Ok(FieldReference::InputField(inner.as_str().to_string()));
             }
             Rule::literal => {
-                for lit_inner in inner.into_inner() {
+                if let Some(lit_inner) = inner.into_inner().next() {
                     return Ok(FieldReference::Literal(parse_literal_value(lit_inner)?));
                 }
             }
@@ -734,7 +754,7 @@ fn parse_function_arg(pair: pest::iterators::Pair<Rule>) -> CompileResult<crate:
                 return Ok(FunctionArg::FieldAccess { path });
             }
             Rule::literal => {
-                for lit_inner in inner.into_inner() {
+                if let Some(lit_inner) = inner.into_inner().next() {
                     return Ok(FunctionArg::Literal(parse_literal_value(lit_inner)?));
                 }
             }
@@ -769,9 +789,11 @@ fn parse_literal_value(pair: pest::iterators::Pair<Rule>) -> CompileResult<Liter
 fn parse_output_section(pair: pest::iterators::Pair<Rule>) -> CompileResult<OutputSection> {
     let mut entity = String::new();
     let mut fields = Vec::new();
+    let mut location = get_location(&pair);
 
     for inner in pair.into_inner() {
         if inner.as_rule() == Rule::type_projection {
+            location = get_location(&inner);
             for proj_inner in inner.into_inner() {
                 match proj_inner.as_rule() {
                     Rule::type_name => entity = proj_inner.as_str().to_string(),
@@ -788,7 +810,7 @@ fn parse_output_section(pair: pest::iterators::Pair<Rule>) -> CompileResult<Outp
         }
     }
 
-    Ok(OutputSection { entity, fields })
+    Ok(OutputSection { entity, fields, location })
 }
 
 /// Parse rule definition
@@ -956,7 +978,7 @@ fn parse_comparison(pair: pest::iterators::Pair<Rule>) -> CompileResult<Expressi
 fn parse_primary(pair: pest::iterators::Pair<Rule>) -> CompileResult<Expression> {
     match pair.as_rule() {
         Rule::primary => {
-            for inner in pair.into_inner() {
+            if let Some(inner) = pair.into_inner().next() {
                 return parse_primary(inner);
             }
             Err(CompileError::parse("Empty primary", 0, 0))
@@ -983,7 +1005,7 @@ fn parse_primary(pair: pest::iterators::Pair<Rule>) -> CompileResult<Expression>
             Ok(Expression::FieldAccess { entity, field })
         }
         Rule::literal => {
-            for inner in pair.into_inner() {
+            if let Some(inner) = pair.into_inner().next() {
                 return parse_literal(inner);
             }
             Err(CompileError::parse("Empty literal", 0, 0))
@@ -1104,6 +1126,23 @@ mod tests {
         assert!(matches!(file.entities[0].fields[2].field_type, FieldType::List(_)));
     }
 
+    #[test]
+    fn test_parse_renamed_from() {
+        let source = "@renamed_from(\"Person\")\nentity User:\n    id: uuid @primary\n    full_name: string @renamed_from(\"name\")\n";
+        let result = parse_intent(source);
+        assert!(result.is_ok(), "Parse failed: {:?}", result.err());
+        let file = result.unwrap();
+        assert_eq!(file.entities[0].renamed_from.as_deref(), Some("Person"));
+        assert_eq!(file.entities[0].fields[1].decorators, vec![Decorator::RenamedFrom("name".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_composite_index() {
+        let source = "@renamed_from(\"Basket\")\n@index(user_id, product_id)\nentity CartItem:\n    id: uuid @primary\n    user_id: uuid\n    product_id: uuid\n";
+        let file = parse_intent(source).unwrap();
+        assert_eq!(file.entities[0].indexes, vec![vec!["user_id".to_string(), "product_id".to_string()]]);
+    }
+
     #[test]
     fn test_parse_v01_action() {
         let source = r#"@api POST /signup
@@ -1121,4 +1160,13 @@ action signup:
         assert!(file.actions[0].input.is_some());
         assert!(file.actions[0].output.is_some());
     }
+
+    #[test]
+    fn test_ast_round_trips_through_json() {
+        let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/app.intent")).unwrap();
+        let file = parse_intent(&source).unwrap();
+        let json = serde_json::to_string(&file).unwrap();
+        let loaded: IntentFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+    }
 }
@@ -100,7 +100,8 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
             }),
             output: Some(OutputSection { 
                 entity: auth_entity_name.clone(), 
-                fields: vec!["id".to_string(), "email".to_string()] 
+                fields: vec!["id".to_string(), "email".to_string()],
+                location: SourceLocation::default(),
             }),
             location: SourceLocation::default(),
         });
@@ -169,7 +170,8 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
             }),
             output: Some(OutputSection { 
                 entity: auth_entity_name.clone(), 
-                fields: vec!["id".to_string(), "token".to_string()] 
+                fields: vec!["id".to_string(), "token".to_string()],
+                location: SourceLocation::default(),
             }),
             location: SourceLocation::default(),
         });
@@ -177,6 +179,11 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
 
     // 3. Get Me
     if !file.actions.iter().any(|a| a.name == "get_me") {
+        let mut me_fields = vec!["id".to_string(), "email".to_string()];
+        if auth_entity.as_ref().is_some_and(|ent| ent.fields.iter().any(|f| f.name == "role")) {
+            me_fields.push("role".to_string());
+        }
+
         file.actions.push(Action {
             name: "get_me".to_string(),
             decorators: vec![
@@ -187,7 +194,8 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
             process: None,
             output: Some(OutputSection { 
                 entity: auth_entity_name.clone(), 
-                fields: vec!["id".to_string(), "email".to_string(), "role".to_string()] 
+                fields: me_fields,
+                location: SourceLocation::default(),
             }),
             location: SourceLocation::default(),
         });
@@ -203,7 +211,7 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
             ],
             input: None,
             process: Some(ProcessSection { steps: vec![] }),
-            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec![] }),
+            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec![], location: SourceLocation::default() }),
             location: SourceLocation::default(),
         });
     }
@@ -234,7 +242,7 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
                     }),
                 ]
             }),
-            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec!["token".to_string()] }),
+            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec!["token".to_string()], location: SourceLocation::default() }),
             location: SourceLocation::default(),
         });
     }
@@ -257,7 +265,7 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
                 ]
             }),
             process: Some(ProcessSection { steps: vec![] }),
-            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec![] }),
+            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec![], location: SourceLocation::default() }),
             location: SourceLocation::default(),
         });
     }
@@ -286,7 +294,7 @@ pub fn inject_auth_actions(file: &mut IntentFile) {
                 ]
             }),
             process: Some(ProcessSection { steps: vec![] }),
-            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec![] }),
+            output: Some(OutputSection { entity: auth_entity_name.clone(), fields: vec![], location: SourceLocation::default() }),
             location: SourceLocation::default(),
         });
     }
@@ -0,0 +1,352 @@
+// Intent Compiler - Diagnostic Reporter
+// Emits errors and warnings as colored text, JSON or SARIF
+
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::audit::{Finding, Severity};
+use crate::cli::MessageFormat;
+use crate::codes;
+use crate::ast::SourceLocation;
+use crate::error::{CompileError, SourceMap, Warning};
+
+/// Code attached to free-text warnings from code generators
+const CODEGEN_WARNING: &str = "W0901";
+
+/// A diagnostic in machine-readable form
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: &'static str,
+    pub code: &'static str,
+    pub message: String,
+    pub hint: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub span: Option<Span>,
+}
+
+/// Byte offsets of a diagnostic in the source file
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Routes diagnostics to the terminal, or buffers them for a single JSON/SARIF document
+pub struct Reporter {
+    format: MessageFormat,
+    /// Input files, for attributing positions to the file they came from
+    sources: SourceMap,
+    /// Warning codes suppressed with --allow
+    allow: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Reporter {
+    pub fn new(format: MessageFormat, allow: Vec<String>) -> Self {
+        Self {
+            format,
+            sources: SourceMap::default(),
+            allow,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Set the input files that diagnostic positions refer to
+    pub fn set_sources(&mut self, sources: SourceMap) {
+        self.sources = sources;
+    }
+
+    /// Build a diagnostic, mapping its position into the input file it belongs to
+    fn diagnostic(
+        &self,
+        severity: &'static str,
+        code: &'static str,
+        message: String,
+        hint: Option<String>,
+        location: Option<&SourceLocation>,
+    ) -> Diagnostic {
+        let Some(location) = location else {
+            return Diagnostic { severity, code, message, hint, file: None, line: None, column: None, span: None };
+        };
+        let (file, location) = self.sources.locate(location);
+        // Some parse errors have no position and are reported at 0:0
+        let positioned = location.line > 0;
+        Diagnostic {
+            severity,
+            code,
+            message,
+            hint,
+            file: file.map(str::to_string),
+            line: Some(location.line).filter(|_| positioned),
+            column: Some(location.column).filter(|_| positioned),
+            span: location.span.map(|(start, end)| Span { start, end }),
+        }
+    }
+
+    /// Whether progress and summary lines should be printed
+    pub fn is_human(&self) -> bool {
+        self.format == MessageFormat::Human
+    }
+
+    /// Whether a code was suppressed with --allow
+    pub fn is_allowed(&self, code: &str) -> bool {
+        self.allow.iter().any(|allowed| allowed.eq_ignore_ascii_case(code))
+    }
+
+    pub fn error(&mut self, error: &CompileError) {
+        if self.is_human() {
+            eprintln!("{}", error.format_colored(None, &self.sources));
+            return;
+        }
+        if let CompileError::MultipleErrors(errors) = error {
+            for error in errors {
+                self.error(error);
+            }
+            return;
+        }
+
+        let (code, message, hint, location) = match error {
+            CompileError::ParseError { code, message, location, .. } => (*code, message.clone(), None, Some(location)),
+            CompileError::ValidationError { code, message, location, hint } => {
+                (*code, message.clone(), hint.clone(), Some(location))
+            }
+            CompileError::CodeGenError { code, message } | CompileError::ConfigError { code, message } => {
+                (*code, message.clone(), None, None)
+            }
+            CompileError::IoError(e) => ("E0902", e.to_string(), None, None),
+            CompileError::MultipleErrors(_) => unreachable!("flattened above"),
+        };
+        let diagnostic = self.diagnostic("error", code, message, hint, location);
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn warning(&mut self, warning: &Warning) {
+        if self.is_allowed(warning.code) {
+            return;
+        }
+        if self.is_human() {
+            eprintln!("{}", warning.format_colored(&self.sources));
+            return;
+        }
+        let diagnostic = self.diagnostic(
+            "warning",
+            warning.code,
+            warning.message.clone(),
+            warning.hint.clone(),
+            Some(&warning.location),
+        );
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Report a security audit finding. Machine formats map severities to SARIF levels.
+    pub fn finding(&mut self, finding: &Finding) {
+        if self.is_allowed(finding.code) {
+            return;
+        }
+        if self.is_human() {
+            eprintln!("{}", finding.format_colored(&self.sources));
+            return;
+        }
+        let severity = match finding.severity {
+            Severity::High => "error",
+            Severity::Medium => "warning",
+            Severity::Low => "note",
+        };
+        let diagnostic = self.diagnostic(
+            severity,
+            finding.code,
+            finding.message.clone(),
+            Some(finding.hint.clone()),
+            finding.location.as_ref(),
+        );
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Report a warning from `GenerationResult.warnings`
+    pub fn codegen_warning(&mut self, message: &str) {
+        if self.is_allowed(CODEGEN_WARNING) {
+            return;
+        }
+        if self.is_human() {
+            eprintln!("{}: {}", format!("warning[{}]", CODEGEN_WARNING).yellow().bold(), message);
+            return;
+        }
+        let diagnostic = self.diagnostic("warning", CODEGEN_WARNING, message.to_string(), None, None);
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Print the buffered document for machine-readable formats
+    pub fn finish(self) {
+        match self.format {
+            MessageFormat::Human => {}
+            MessageFormat::Json => println!("{}", render_json(&self.diagnostics)),
+            MessageFormat::Sarif => println!("{}", render_sarif(&self.diagnostics)),
+        }
+    }
+}
+
+fn count(diagnostics: &[Diagnostic], severity: &str) -> usize {
+    diagnostics.iter().filter(|d| d.severity == severity).count()
+}
+
+/// Render diagnostics as a JSON document with a summary
+pub fn render_json(diagnostics: &[Diagnostic]) -> String {
+    let document = json!({
+        "diagnostics": diagnostics,
+        "errors": count(diagnostics, "error"),
+        "warnings": count(diagnostics, "warning"),
+        "notes": count(diagnostics, "note"),
+    });
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+/// Render diagnostics as a SARIF 2.1.0 log
+pub fn render_sarif(diagnostics: &[Diagnostic]) -> String {
+    let mut rule_ids: Vec<&str> = Vec::new();
+    for d in diagnostics {
+        if !rule_ids.contains(&d.code) {
+            rule_ids.push(d.code);
+        }
+    }
+
+    let rules: Vec<_> = rule_ids
+        .iter()
+        .map(|id| match codes::lookup(id) {
+            Some(entry) => json!({
+                "id": id,
+                "name": entry.title,
+                "shortDescription": { "text": entry.title },
+                "fullDescription": { "text": entry.explanation },
+            }),
+            None => json!({ "id": id }),
+        })
+        .collect();
+
+    let results: Vec<_> = diagnostics
+        .iter()
+        .map(|d| {
+            let mut result = json!({
+                "ruleId": d.code,
+                "ruleIndex": rule_ids.iter().position(|id| *id == d.code),
+                "level": d.severity,
+                "message": { "text": d.message },
+            });
+            if let Some(file) = &d.file {
+                let mut region = serde_json::Map::new();
+                if let (Some(line), Some(column)) = (d.line, d.column) {
+                    region.insert("startLine".into(), json!(line));
+                    region.insert("startColumn".into(), json!(column));
+                }
+                if let Some(span) = d.span {
+                    region.insert("charOffset".into(), json!(span.start));
+                    region.insert("charLength".into(), json!(span.end.saturating_sub(span.start)));
+                }
+                let mut physical = json!({ "artifactLocation": { "uri": file } });
+                if !region.is_empty() {
+                    physical["region"] = serde_json::Value::Object(region);
+                }
+                result["locations"] = json!([{ "physicalLocation": physical }]);
+            }
+            if let Some(hint) = &d.hint {
+                result["properties"] = json!({ "hint": hint });
+            }
+            result
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "intentc",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reporter(format: MessageFormat) -> Reporter {
+        let mut reporter = Reporter::new(format, vec!["W0101".to_string()]);
+        let mut sources = SourceMap::default();
+        sources.push("app.intent", &"\n".repeat(10), &mut String::new());
+        reporter.set_sources(sources);
+        reporter
+    }
+
+    #[test]
+    fn test_json_flattens_errors_and_keeps_locations() {
+        let mut r = reporter(MessageFormat::Json);
+        r.error(&CompileError::MultipleErrors(vec![
+            CompileError::validation_with_hint(
+                "E0203",
+                "Unknown entity in select: Usr",
+                SourceLocation::with_span(4, 9, 40, 80),
+                "Did you mean 'User'?",
+            ),
+            CompileError::parse("Empty expression", 0, 0),
+        ]));
+        r.codegen_warning("No primary key");
+
+        let value: serde_json::Value = serde_json::from_str(&render_json(&r.diagnostics)).unwrap();
+        assert_eq!(value["errors"], 2);
+        assert_eq!(value["warnings"], 1);
+        let first = &value["diagnostics"][0];
+        assert_eq!(first["code"], "E0203");
+        assert_eq!(first["line"], 4);
+        assert_eq!(first["span"]["start"], 40);
+        assert_eq!(first["hint"], "Did you mean 'User'?");
+        assert!(value["diagnostics"][1]["line"].is_null());
+    }
+
+    #[test]
+    fn test_allowed_warnings_are_dropped() {
+        let mut r = reporter(MessageFormat::Json);
+        r.warning(&Warning::with_hint("W0101", "no primary", SourceLocation::new(1, 1), "add one"));
+        assert!(r.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_sarif_results_reference_rules() {
+        let mut r = reporter(MessageFormat::Sarif);
+        r.error(&CompileError::validation("E0220", "Unknown field 'emial'", SourceLocation::new(7, 1)));
+
+        let log: serde_json::Value = serde_json::from_str(&render_sarif(&r.diagnostics)).unwrap();
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "E0220");
+        let result = &run["results"][0];
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["ruleIndex"], 0);
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "app.intent");
+        assert_eq!(location["region"]["startLine"], 7);
+    }
+
+    #[test]
+    fn test_positions_map_to_their_input_file() {
+        let mut sources = SourceMap::default();
+        let mut unit = String::new();
+        sources.push("a.intent", "entity A:\n    id: uuid @primary", &mut unit);
+        sources.push("b.intent", "entity B:\n    id: uuid @primary\n", &mut unit);
+        let mut r = Reporter::new(MessageFormat::Json, Vec::new());
+        r.set_sources(sources);
+
+        let offset = unit.find("entity B").unwrap();
+        r.error(&CompileError::validation("E0101", "dup", SourceLocation::with_span(4, 5, offset + 10, offset + 12)));
+        let d = &r.diagnostics[0];
+        assert_eq!((d.file.as_deref(), d.line), (Some("b.intent"), Some(2)));
+        assert_eq!(d.span.map(|s| s.start), Some(10));
+    }
+}
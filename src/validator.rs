@@ -4,14 +4,13 @@
 use std::collections::{HashMap, HashSet};
 
 use crate::ast::*;
-use crate::error::{CompileError, CompileResult, Warning};
+use crate::error::{CompileError, CompileResult, Diagnostics, Warning};
 
 /// Validation context holding symbol tables
 pub struct ValidationContext {
     pub entities: HashMap<String, Entity>,
     pub actions: HashMap<String, Action>,
     pub policies: HashMap<String, Policy>,
-    pub warnings: Vec<Warning>,
     /// The designated auth entity name, if one is defined
     pub auth_entity: Option<String>,
 }
@@ -22,13 +21,29 @@ impl ValidationContext {
             entities: HashMap::new(),
             actions: HashMap::new(),
             policies: HashMap::new(),
-            warnings: Vec::new(),
             auth_entity: None,
         }
     }
 
-    pub fn add_warning(&mut self, warning: Warning) {
-        self.warnings.push(warning);
+    /// Symbol tables of a file that has already been validated, keeping the first of any duplicates
+    pub fn from_file(file: &IntentFile) -> Self {
+        let mut ctx = Self::new();
+        for entity in file.entities.iter().rev() {
+            ctx.entities.insert(entity.name.clone(), entity.clone());
+        }
+        for action in file.actions.iter().rev() {
+            ctx.actions.insert(action.name.clone(), action.clone());
+        }
+        for policy in file.policies.iter().rev() {
+            ctx.policies.insert(policy.name.clone(), policy.clone());
+        }
+        for entity in &file.entities {
+            for policy in &entity.policies {
+                ctx.policies.entry(format!("{}.{}", entity.name, policy.name)).or_insert_with(|| policy.clone());
+            }
+        }
+        ctx.auth_entity = file.entities.iter().find(|e| e.is_auth).map(|e| e.name.clone());
+        ctx
     }
 }
 
@@ -38,15 +53,17 @@ impl Default for ValidationContext {
     }
 }
 
-/// Validate an intent file
-pub fn validate(file: &IntentFile) -> CompileResult<ValidationContext> {
+/// Validate an intent file, reporting every error found in a single pass.
+/// Warnings are returned alongside the result so they survive a failed validation.
+pub fn validate(file: &IntentFile) -> (CompileResult<ValidationContext>, Vec<Warning>) {
     let mut ctx = ValidationContext::new();
-    let mut errors: Vec<CompileError> = Vec::new();
+    let mut diags = Diagnostics::new();
 
     // First pass: collect all entity and action names
     for entity in &file.entities {
         if ctx.entities.contains_key(&entity.name) {
-            errors.push(CompileError::validation(
+            diags.error(CompileError::validation(
+                "E0101",
                 format!("Duplicate entity name: {}", entity.name),
                 entity.location.clone(),
             ));
@@ -56,10 +73,11 @@ pub fn validate(file: &IntentFile) -> CompileResult<ValidationContext> {
 
         // Check for auth entity
         if entity.is_auth {
-            if ctx.auth_entity.is_some() {
-                errors.push(CompileError::validation(
+            if let Some(existing) = &ctx.auth_entity {
+                diags.error(CompileError::validation(
+                    "E0102",
                     format!("Multiple auth entities defined: '{}' and '{}'. Only one auth entity is allowed.",
-                        ctx.auth_entity.as_ref().unwrap(), entity.name),
+                        existing, entity.name),
                     entity.location.clone(),
                 ));
             } else {
@@ -70,7 +88,8 @@ pub fn validate(file: &IntentFile) -> CompileResult<ValidationContext> {
 
     for action in &file.actions {
         if ctx.actions.contains_key(&action.name) {
-            errors.push(CompileError::validation(
+            diags.error(CompileError::validation(
+                "E0201",
                 format!("Duplicate action name: {}", action.name),
                 action.location.clone(),
             ));
@@ -83,7 +102,8 @@ pub fn validate(file: &IntentFile) -> CompileResult<ValidationContext> {
     // Collect policies (global and entity-scoped)
     for policy in &file.policies {
         if ctx.policies.contains_key(&policy.name) {
-             errors.push(CompileError::validation(
+             diags.error(CompileError::validation(
+                "E0301",
                 format!("Duplicate policy name: {}", policy.name),
                 policy.location.clone(),
             ));
@@ -95,80 +115,67 @@ pub fn validate(file: &IntentFile) -> CompileResult<ValidationContext> {
     for entity in &file.entities {
         for policy in &entity.policies {
             let full_name = format!("{}.{}", entity.name, policy.name);
-            if ctx.policies.contains_key(&full_name) {
-                 errors.push(CompileError::validation(
+            if let std::collections::hash_map::Entry::Vacant(slot) = ctx.policies.entry(full_name.clone()) {
+                slot.insert(policy.clone());
+            } else {
+                 diags.error(CompileError::validation(
+                    "E0301",
                     format!("Duplicate policy name: {}", full_name),
                     policy.location.clone(),
                 ));
-            } else {
-                ctx.policies.insert(full_name, policy.clone());
             }
         }
     }
 
     // Second pass: validate each construct
     for entity in &file.entities {
-        if let Err(e) = validate_entity(entity, &mut ctx) {
-            errors.push(e);
-        }
+        validate_entity(entity, &ctx, &mut diags);
     }
 
     for action in &file.actions {
-        if let Err(e) = validate_action(action, &ctx) {
-            errors.push(e);
-        }
+        validate_action(action, &ctx, &mut diags);
     }
 
     for rule in &file.rules {
-        if let Err(e) = validate_rule(rule, &ctx) {
-            errors.push(e);
-        }
+        validate_rule(rule, &ctx, &mut diags);
     }
 
     // Validate global policies
     for policy in &file.policies {
-        if let Err(e) = validate_policy(policy, &ctx) {
-            errors.push(e);
-        }
+        validate_policy(policy, &ctx, &mut diags);
     }
 
     // Validate entity-scoped policies
     for entity in &file.entities {
         for policy in &entity.policies {
-            if let Err(e) = validate_policy(policy, &ctx) {
-                errors.push(e);
-            }
+            validate_policy(policy, &ctx, &mut diags);
         }
     }
 
-    if errors.is_empty() {
-        Ok(ctx)
-    } else if errors.len() == 1 {
-        Err(errors.remove(0))
-    } else {
-        Err(CompileError::MultipleErrors(errors))
-    }
+    let warnings = diags.take_warnings();
+    (diags.into_result(ctx), warnings)
 }
 
 /// Validate an entity definition
-fn validate_entity(entity: &Entity, ctx: &mut ValidationContext) -> CompileResult<()> {
+fn validate_entity(entity: &Entity, ctx: &ValidationContext, diags: &mut Diagnostics) {
     let mut field_names = HashSet::new();
     let mut has_primary = false;
 
     for field in &entity.fields {
         // Check for duplicate field names
-        if field_names.contains(&field.name) {
-            return Err(CompileError::validation(
+        if !field_names.insert(&field.name) {
+            diags.error(CompileError::validation(
+                "E0103",
                 format!("Duplicate field name '{}' in entity '{}'", field.name, entity.name),
                 field.location.clone(),
             ));
         }
-        field_names.insert(&field.name);
 
         // Check for primary key
         if field.decorators.contains(&Decorator::Primary) {
             if has_primary {
-                return Err(CompileError::validation(
+                diags.error(CompileError::validation(
+                    "E0104",
                     format!("Entity '{}' has multiple @primary fields", entity.name),
                     field.location.clone(),
                 ));
@@ -177,25 +184,31 @@ fn validate_entity(entity: &Entity, ctx: &mut ValidationContext) -> CompileResul
         }
 
         // Validate field type
-        validate_field_type(&field.field_type, ctx, &field.location)?;
+        diags.check(validate_field_type(&field.field_type, ctx, &field.location));
 
         // Validate decorator combinations
-        validate_decorators(&field.decorators, &field.location)?;
+        diags.check(validate_decorators(&field.decorators, &field.location));
     }
 
-    // Warn if no primary key
-    if !has_primary {
-        ctx.add_warning(Warning::with_hint(
-            format!("Entity '{}' has no @primary field", entity.name),
-            entity.location.clone(),
-            "Consider adding @primary to an id field",
-        ));
+    // Composite indexes cover fields of the entity
+    for columns in &entity.indexes {
+        for column in columns {
+            if !field_names.contains(column) {
+                diags.error(CompileError::validation_with_hint(
+                    "E0207",
+                    format!("Unknown field '{}' in index of entity '{}'", column, entity.name),
+                    entity.location.clone(),
+                    suggestion_hint(column, entity.fields.iter().map(|f| &f.name), "fields"),
+                ));
+            }
+        }
     }
 
     // Enforce auth entity requirements
     if entity.is_auth {
         if !field_names.contains(&"email".to_string()) {
-            return Err(CompileError::validation(
+            diags.error(CompileError::validation(
+                "E0105",
                 format!("Auth entity '{}' must have an 'email' field", entity.name),
                 entity.location.clone(),
             ));
@@ -207,14 +220,13 @@ fn validate_entity(entity: &Entity, ctx: &mut ValidationContext) -> CompileResul
                            field_names.contains(&"password_hash".to_string());
         
         if !has_password {
-             return Err(CompileError::validation(
+             diags.error(CompileError::validation(
+                "E0106",
                 format!("Auth entity '{}' must have a 'password' or 'password_hash' field", entity.name),
                 entity.location.clone(),
             ));
         }
     }
-
-    Ok(())
 }
 
 /// Validate a field type
@@ -224,14 +236,13 @@ fn validate_field_type(
     location: &SourceLocation,
 ) -> CompileResult<()> {
     match field_type {
-        FieldType::Reference(name) | FieldType::Ref(name) => {
-            if !ctx.entities.contains_key(name) {
-                return Err(CompileError::validation_with_hint(
-                    format!("Unknown entity reference: {}", name),
-                    location.clone(),
-                    format!("Available entities: {:?}", ctx.entities.keys().collect::<Vec<_>>()),
-                ));
-            }
+        FieldType::Reference(name) | FieldType::Ref(name) if !ctx.entities.contains_key(name) => {
+            return Err(CompileError::validation_with_hint(
+                "E0107",
+                format!("Unknown entity reference: {}", name),
+                location.clone(),
+                suggestion_hint(name, ctx.entities.keys(), "entities"),
+            ));
         }
         FieldType::Array(inner) | FieldType::List(inner) => {
             validate_field_type(inner, ctx, location)?;
@@ -242,6 +253,7 @@ fn validate_field_type(
         FieldType::Enum(values) => {
             if values.is_empty() {
                 return Err(CompileError::validation(
+                    "E0108",
                     "Enum type must have at least one value",
                     location.clone(),
                 ));
@@ -249,6 +261,7 @@ fn validate_field_type(
             let unique: HashSet<_> = values.iter().collect();
             if unique.len() != values.len() {
                 return Err(CompileError::validation(
+                    "E0109",
                     "Enum type has duplicate values",
                     location.clone(),
                 ));
@@ -274,6 +287,7 @@ fn validate_decorators(decorators: &[Decorator], location: &SourceLocation) -> C
 
     if has_primary && has_optional {
         return Err(CompileError::validation(
+            "E0110",
             "Field cannot be both @primary and @optional",
             location.clone(),
         ));
@@ -283,110 +297,125 @@ fn validate_decorators(decorators: &[Decorator], location: &SourceLocation) -> C
 }
 
 /// Validate an action definition
-fn validate_action(action: &Action, ctx: &ValidationContext) -> CompileResult<()> {
-    let mut has_api = false;
+fn validate_action(action: &Action, ctx: &ValidationContext, diags: &mut Diagnostics) {
     let mut param_names = HashSet::new();
 
     // Validate input fields ( structured syntax)
     if let Some(input) = &action.input {
         for param in &input.fields {
-            if param_names.contains(&param.name) {
-                return Err(CompileError::validation(
+            if !param_names.insert(&param.name) {
+                diags.error(CompileError::validation(
+                    "E0202",
                     format!("Duplicate parameter '{}' in action '{}'", param.name, action.name),
                     param.location.clone(),
                 ));
             }
-            param_names.insert(&param.name);
 
-            validate_field_type(&param.param_type, ctx, &param.location)?;
+            diags.check(validate_field_type(&param.param_type, ctx, &param.location));
         }
     }
 
     // Validate output section
-    if let Some(output) = &action.output {
-        if !ctx.entities.contains_key(&output.entity) {
-            return Err(CompileError::validation_with_hint(
-                format!("Unknown output type: {}", output.entity),
-                action.location.clone(),
-                "Output type must be a defined entity",
-            ));
-        }
+    if let Some(output) = &action.output
+        && !ctx.entities.contains_key(&output.entity) {
+        diags.error(CompileError::validation_with_hint(
+            "E0206",
+            format!("Unknown output type: {}", output.entity),
+            output.location.clone(),
+            format!(
+                "Output type must be a defined entity. {}",
+                suggestion_hint(&output.entity, ctx.entities.keys(), "entities")
+            ),
+        ));
     }
 
     // Validate process section
-    if let Some(process) = &action.process {
-        let input_fields: HashSet<String> = param_names.iter().map(|s| s.to_string()).collect();
-        validate_process(process, ctx, &action.location, &input_fields)?;
+    let scope = action.process.as_ref().map(|process| {
+        let inputs = action.input.as_ref().map(|i| i.fields.as_slice()).unwrap_or_default();
+        validate_process(process, ctx, inputs, diags)
+    });
+
+    // Validate output projection
+    if let Some(output) = &action.output {
+        validate_output(output, action, ctx, scope.as_ref(), diags);
     }
 
     // Validate decorators
     for decorator in &action.decorators {
-        match decorator {
-            Decorator::Api { method: _, path } => {
-                has_api = true;
-                validate_api_path(path, &param_names, &action.location)?;
-            }
-            Decorator::Auth { name, args } => {
-                if let Some(name) = name {
-                    let first_char = name.chars().next().unwrap_or(' ');
-                    if first_char.is_uppercase() {
-                        if !ctx.entities.contains_key(name) {
-                            return Err(CompileError::validation_with_hint(
-                                format!("Unknown entity in @auth: {}", name),
-                                action.location.clone(),
-                                format!("Available entities: {:?}", ctx.entities.keys().collect::<Vec<_>>()),
-                            ));
-                        }
-                    } else {
-                        if !ctx.actions.contains_key(name) {
-                            return Err(CompileError::validation_with_hint(
-                                format!("Unknown action in @auth: {}", name),
-                                action.location.clone(),
-                                format!("Available actions: {:?}", ctx.actions.keys().collect::<Vec<_>>()),
-                            ));
-                        }
-                    }
+        diags.check(validate_action_decorator(decorator, action, ctx, &param_names));
+    }
 
-                    // Validate arguments
-                    for arg in args {
-                        if !param_names.contains(arg) {
-                            return Err(CompileError::validation_with_hint(
-                                format!("Unknown argument '{}' in @auth", arg),
-                                action.location.clone(),
-                                format!("Available parameters: {:?}", param_names.iter().collect::<Vec<_>>()),
-                            ));
-                        }
+    // Actions without @api are internal and don't generate routes
+}
+
+/// Validate a single pre-action decorator
+fn validate_action_decorator(
+    decorator: &Decorator,
+    action: &Action,
+    ctx: &ValidationContext,
+    param_names: &HashSet<&String>,
+) -> CompileResult<()> {
+    match decorator {
+        Decorator::Api { method: _, path } => {
+            validate_api_path(path, param_names, &action.location)?;
+        }
+        Decorator::Auth { name, args } => {
+            if let Some(name) = name {
+                let first_char = name.chars().next().unwrap_or(' ');
+                if first_char.is_uppercase() {
+                    if !ctx.entities.contains_key(name) {
+                        return Err(CompileError::validation_with_hint(
+                            "E0224",
+                            format!("Unknown entity in @auth: {}", name),
+                            action.location.clone(),
+                            suggestion_hint(name, ctx.entities.keys(), "entities"),
+                        ));
                     }
                 } else {
-                    // @auth without arguments requires an auth entity to be defined
-                    if ctx.auth_entity.is_none() {
+                    if !ctx.actions.contains_key(name) {
                         return Err(CompileError::validation_with_hint(
-                            "@auth decorator used without arguments, but no auth entity is defined".to_string(),
+                            "E0225",
+                            format!("Unknown action in @auth: {}", name),
                             action.location.clone(),
-                            "Define an auth entity using 'auth entity EntityName:' or specify an entity/action in @auth(Name)",
+                            suggestion_hint(name, ctx.actions.keys(), "actions"),
                         ));
                     }
                 }
-            }
-            Decorator::Policy(name) => {
-                // Check if policy exists
-                if !ctx.policies.contains_key(name) {
-                     return Err(CompileError::validation_with_hint(
-                        format!("Unknown policy: {}", name),
+
+                // Validate arguments
+                for arg in args {
+                    if !param_names.contains(arg) {
+                        return Err(CompileError::validation_with_hint(
+                            "E0226",
+                            format!("Unknown argument '{}' in @auth", arg),
+                            action.location.clone(),
+                            suggestion_hint(arg, param_names.iter(), "parameters"),
+                        ));
+                    }
+                }
+            } else {
+                // @auth without arguments requires an auth entity to be defined
+                if ctx.auth_entity.is_none() {
+                    return Err(CompileError::validation_with_hint(
+                        "E0227",
+                        "@auth decorator used without arguments, but no auth entity is defined".to_string(),
                         action.location.clone(),
-                        format!("Available policies: {:?}", ctx.policies.keys().collect::<Vec<_>>()),
+                        "Define an auth entity using 'auth entity EntityName:' or specify an entity/action in @auth(Name)",
                     ));
                 }
             }
-            _ => {}
         }
+        // Check if policy exists
+        Decorator::Policy(name) if !ctx.policies.contains_key(name) => {
+            return Err(CompileError::validation_with_hint(
+                "E0228",
+                format!("Unknown policy: {}", name),
+                action.location.clone(),
+                suggestion_hint(name, ctx.policies.keys(), "policies"),
+            ));
+        }
+        _ => {}
     }
-
-    if !has_api {
-        // Actions without @api are internal and don't generate routes
-        // For  we just allow them
-    }
-
     Ok(())
 }
 
@@ -407,6 +436,7 @@ fn validate_api_path(
     for path_param in path_params {
         if !param_names.iter().any(|p| p.as_str() == path_param) {
             return Err(CompileError::validation_with_hint(
+                "E0223",
                 format!("Path parameter '{{{}}}' not found in action parameters", path_param),
                 location.clone(),
                 format!("Add '{}: type' to action parameters", path_param),
@@ -418,14 +448,12 @@ fn validate_api_path(
 }
 
 /// Validate a rule definition
-fn validate_rule(rule: &crate::ast::Rule, ctx: &ValidationContext) -> CompileResult<()> {
+fn validate_rule(rule: &crate::ast::Rule, ctx: &ValidationContext, diags: &mut Diagnostics) {
     // Validate the condition expression
-    validate_expression(&rule.condition, ctx, &rule.location)?;
+    diags.check(validate_expression(&rule.condition, ctx, &rule.location));
 
     // Validate the consequence
-    validate_consequence(&rule.consequence, ctx, &rule.location)?;
-
-    Ok(())
+    diags.check(validate_consequence(&rule.consequence, ctx, &rule.location));
 }
 
 /// Validate an expression
@@ -450,20 +478,17 @@ fn validate_expression(
             if entity == "subject" {
                 // 'subject' refers to the auth entity or the specified subject in the policy
                 // For now, we mainly check if it's the auth entity
-                if let Some(auth_entity_name) = &ctx.auth_entity {
-                    if let Some(ent) = ctx.entities.get(auth_entity_name) {
-                        if !ent.fields.iter().any(|f| &f.name == field) {
-                            return Err(CompileError::validation_with_hint(
-                                format!("Field '{}' not found in auth entity '{}' (referenced via 'subject')", field, auth_entity_name),
-                                location.clone(),
-                                format!(
-                                    "Available fields: {:?}",
-                                    ent.fields.iter().map(|f| &f.name).collect::<Vec<_>>()
-                                ),
-                            ));
-                        }
-                        return Ok(());
+                if let Some(auth_entity_name) = &ctx.auth_entity
+                    && let Some(ent) = ctx.entities.get(auth_entity_name) {
+                    if !ent.fields.iter().any(|f| &f.name == field) {
+                        return Err(CompileError::validation_with_hint(
+                            "E0303",
+                            format!("Field '{}' not found in auth entity '{}' (referenced via 'subject')", field, auth_entity_name),
+                            location.clone(),
+                            suggestion_hint(field, ent.fields.iter().map(|f| &f.name), "fields"),
+                        ));
                     }
+                    return Ok(());
                 }
                 
                 // If it's a specific entity subject, validate that later or just allow for now
@@ -475,18 +500,18 @@ fn validate_expression(
                 // Check field exists
                 if !ent.fields.iter().any(|f| &f.name == field) {
                     return Err(CompileError::validation_with_hint(
+                        "E0303",
                         format!("Field '{}' not found in entity '{}'", field, entity),
                         location.clone(),
-                        format!(
-                            "Available fields: {:?}",
-                            ent.fields.iter().map(|f| &f.name).collect::<Vec<_>>()
-                        ),
+                        suggestion_hint(field, ent.fields.iter().map(|f| &f.name), "fields"),
                     ));
                 }
             } else {
-                return Err(CompileError::validation(
+                return Err(CompileError::validation_with_hint(
+                    "E0304",
                     format!("Unknown entity: {}", entity),
                     location.clone(),
+                    suggestion_hint(entity, ctx.entities.keys(), "entities"),
                 ));
             }
         }
@@ -507,9 +532,10 @@ fn validate_consequence(
             // Check action exists (skip built-in actions)
             if !ctx.actions.contains_key(action) {
                 return Err(CompileError::validation_with_hint(
+                    "E0305",
                     format!("Unknown action: {}", action),
                     location.clone(),
-                    format!("Available actions: {:?}", ctx.actions.keys().collect::<Vec<_>>()),
+                    suggestion_hint(action, ctx.actions.keys(), "actions"),
                 ));
             }
 
@@ -521,6 +547,7 @@ fn validate_consequence(
         Consequence::Reject(message) | Consequence::Log(message) => {
             if message.is_empty() {
                 return Err(CompileError::validation(
+                    "E0306",
                     "Empty message in reject/log",
                     location.clone(),
                 ));
@@ -531,147 +558,658 @@ fn validate_consequence(
 }
 
 /// Validate a policy definition
-fn validate_policy(policy: &Policy, ctx: &ValidationContext) -> CompileResult<()> {
+fn validate_policy(policy: &Policy, ctx: &ValidationContext, diags: &mut Diagnostics) {
     if policy.subject != "@auth" && !ctx.entities.contains_key(&policy.subject) {
-        return Err(CompileError::validation_with_hint(
+        diags.error(CompileError::validation_with_hint(
+            "E0302",
             format!("Unknown subject in policy '{}': {}", policy.name, policy.subject),
             policy.location.clone(),
-            format!("Subject must be '@auth' or a defined entity name. Available entities: {:?}", ctx.entities.keys().collect::<Vec<_>>()),
+            format!(
+                "Subject must be '@auth' or a defined entity name. {}",
+                suggestion_hint(&policy.subject, ctx.entities.keys(), "entities")
+            ),
         ));
     }
-    validate_expression(&policy.require, ctx, &policy.location)?;
-    Ok(())
+    diags.check(validate_expression(&policy.require, ctx, &policy.location));
+}
+
+/// Inferred type of a value inside a process section
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueType {
+    /// A scalar or collection value of a known field type
+    Field(FieldType),
+    /// A record of the named entity (select results, current_user)
+    Entity(String),
+    /// A value whose type cannot be inferred (e.g. custom compute functions)
+    Unknown,
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueType::Field(field_type) => write!(f, "{}", field_type),
+            ValueType::Entity(name) => write!(f, "{} record", name),
+            ValueType::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// System capabilities available to `system.<namespace>.<capability>(...)` and their result type
+pub const SYSTEM_CAPABILITIES: &[(&str, &str, Option<FieldType>)] = &[
+    ("jwt", "create", Some(FieldType::String)),
+    ("jwt", "verify", None),
+];
+
+/// Symbols visible to process steps: declared inputs and variables derived so far
+struct ProcessScope {
+    inputs: HashMap<String, FieldType>,
+    variables: HashMap<String, ValueType>,
+}
+
+impl ProcessScope {
+    fn lookup(&self, name: &str) -> Option<ValueType> {
+        self.variables
+            .get(name)
+            .cloned()
+            .or_else(|| self.inputs.get(name).map(|t| ValueType::Field(t.clone())))
+    }
+}
+
+/// Types of the variables an action's process derives, in step order, inferred exactly as
+/// validation infers them. Values that don't resolve are `ValueType::Unknown`
+pub fn derived_types(action: &Action, ctx: &ValidationContext) -> Vec<(String, ValueType)> {
+    let Some(process) = &action.process else {
+        return Vec::new();
+    };
+    let inputs = action.input.as_ref().map(|i| i.fields.as_slice()).unwrap_or_default();
+    let scope = validate_process(process, ctx, inputs, &mut Diagnostics::new());
+    process
+        .steps
+        .iter()
+        .filter_map(|step| match step {
+            ProcessStep::Derive(d) => Some((d.name.clone(), scope.variables[&d.name].clone())),
+            _ => None,
+        })
+        .collect()
 }
 
-/// Validate process section
+/// Validate process section, type checking every step
 fn validate_process(
     process: &ProcessSection,
     ctx: &ValidationContext,
-    _location: &SourceLocation,
-    input_fields: &HashSet<String>,
-) -> CompileResult<()> {
-    let mut scope = input_fields.clone();
+    inputs: &[ActionParam],
+    diags: &mut Diagnostics,
+) -> ProcessScope {
+    let mut scope = ProcessScope {
+        inputs: inputs.iter().map(|p| (p.name.clone(), p.param_type.clone())).collect(),
+        variables: HashMap::new(),
+    };
     // Implicit variables
-    scope.insert("current_user".to_string()); // Assuming available context
+    let current_user = match &ctx.auth_entity {
+        Some(name) => ValueType::Entity(name.clone()),
+        None => ValueType::Unknown,
+    };
+    scope.variables.insert("current_user".to_string(), current_user);
 
     for step in &process.steps {
         match step {
             ProcessStep::Derive(d) => {
-                validate_derive(d, ctx, &scope)?;
-                scope.insert(d.name.clone());
+                // A failed derive stays in scope with its best-known type so later steps don't cascade
+                let value_type = diags
+                    .check(infer_value(&d.value, ctx, &scope, &d.location))
+                    .unwrap_or_else(|| match &d.value {
+                        DeriveValue::Select { entity, .. } if ctx.entities.contains_key(entity) => {
+                            ValueType::Entity(entity.clone())
+                        }
+                        _ => ValueType::Unknown,
+                    });
+                scope.variables.insert(d.name.clone(), value_type);
+            }
+            ProcessStep::Mutate(m) => validate_mutate(m, ctx, &scope, diags),
+            ProcessStep::Delete(d) => {
+                diags.check(validate_delete(d, ctx, &scope));
             }
-            ProcessStep::Mutate(m) => validate_mutate(m, ctx, &scope)?,
-            ProcessStep::Delete(d) => validate_delete(d, ctx, &scope)?,
         }
     }
-    Ok(())
+    scope
+}
+
+/// Validate output projection: every field is an entity field or a derived variable,
+/// secret fields stay private, and the process actually produces the projected entity
+fn validate_output(
+    output: &OutputSection,
+    action: &Action,
+    ctx: &ValidationContext,
+    scope: Option<&ProcessScope>,
+    diags: &mut Diagnostics,
+) {
+    let Some(entity) = ctx.entities.get(&output.entity) else {
+        return;
+    };
+    let derived: HashSet<&String> = action
+        .process
+        .iter()
+        .flat_map(|p| &p.steps)
+        .filter_map(|step| match step {
+            ProcessStep::Derive(d) => Some(&d.name),
+            _ => None,
+        })
+        .collect();
+
+    let mut projects_entity_fields = false;
+    for name in &output.fields {
+        // Derived variables shadow entity fields, matching the generated services
+        if derived.contains(name) {
+            continue;
+        }
+        let Some(field) = entity.fields.iter().find(|f| &f.name == name) else {
+            diags.error(CompileError::validation_with_hint(
+                "E0220",
+                format!("Unknown field '{}' in output of action '{}'", name, action.name),
+                output.location.clone(),
+                format!(
+                    "Output fields must be fields of '{}' or derived variables. {}",
+                    output.entity,
+                    suggestion_hint(name, entity.fields.iter().map(|f| &f.name).chain(derived.iter().copied()), "fields")
+                ),
+            ));
+            continue;
+        };
+        if is_secret_field(field) {
+            diags.error(CompileError::validation_with_hint(
+                "E0221",
+                format!("Action '{}' exposes secret field '{}.{}' in its output", action.name, output.entity, name),
+                output.location.clone(),
+                "Remove it from the output, or mark the field with @expose if it is meant to be public",
+            ));
+        }
+        projects_entity_fields = true;
+    }
+
+    // Actions without a process fall back to the generated CRUD lookups
+    if let (Some(process), Some(scope)) = (&action.process, scope)
+        && projects_entity_fields
+        && !yields_entity(process, scope, &output.entity) {
+        diags.error(CompileError::validation_with_hint(
+            "E0222",
+            format!(
+                "Action '{}' projects fields of '{}' but its process never produces a {} record",
+                action.name, output.entity, output.entity
+            ),
+            output.location.clone(),
+            format!("Add a `select {}`, `mutate {}` or `delete {}` step", output.entity, output.entity, output.entity),
+        ));
+    }
+}
+
+/// Fields holding credentials that must not leave the service unless marked @expose
+fn is_secret_field(field: &Field) -> bool {
+    !field.decorators.contains(&Decorator::Expose) && is_secret_name(&field.name)
+}
+
+/// Whether a field or parameter name looks like a credential
+pub fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("password") || name.contains("secret") || name.ends_with("_hash")
 }
 
-fn validate_derive(derive: &DeriveStatement, ctx: &ValidationContext, scope: &HashSet<String>) -> CompileResult<()> {
-    // Basic validation for derive expressions
-    match &derive.value {
+/// Whether a process step or derived variable may produce a record of the given entity
+fn yields_entity(process: &ProcessSection, scope: &ProcessScope, entity: &str) -> bool {
+    process.steps.iter().any(|step| match step {
+        ProcessStep::Mutate(m) => m.entity == entity,
+        ProcessStep::Delete(d) => d.entity == entity,
+        // Variables of unknown type (custom computes, failed derives) get the benefit of the doubt
+        ProcessStep::Derive(d) => match scope.variables.get(&d.name) {
+            Some(ValueType::Entity(name)) => name == entity,
+            Some(ValueType::Unknown) => true,
+            _ => false,
+        },
+    }) || matches!(scope.variables.get("current_user"), Some(ValueType::Entity(name)) if name == entity)
+}
+
+/// Infer the type of a derive or setter value, validating every reference it makes
+fn infer_value(
+    value: &DeriveValue,
+    ctx: &ValidationContext,
+    scope: &ProcessScope,
+    location: &SourceLocation,
+) -> CompileResult<ValueType> {
+    match value {
+        DeriveValue::Literal(lit) => Ok(literal_type(lit)),
+        DeriveValue::Identifier(id) => resolve_identifier(id, scope, location),
+        DeriveValue::FieldAccess { path } => resolve_path(path, ctx, scope, location),
         DeriveValue::Select { entity, predicate } => {
-            if !ctx.entities.contains_key(entity) {
-                 return Err(CompileError::validation(
+            let entity_def = ctx.entities.get(entity).ok_or_else(|| {
+                CompileError::validation_with_hint(
+                    "E0203",
                     format!("Unknown entity in select: {}", entity),
-                    derive.location.clone(),
+                    location.clone(),
+                    suggestion_hint(entity, ctx.entities.keys(), "entities"),
+                )
+            })?;
+            validate_predicate(predicate, entity_def, ctx, scope, location)?;
+            Ok(ValueType::Entity(entity.clone()))
+        }
+        DeriveValue::Compute { function, args } => {
+            for arg in args {
+                infer_function_arg(arg, ctx, scope, location)?;
+            }
+            let (arity, result) = match function.as_str() {
+                "hash" | "slugify" => (Some(1), ValueType::Field(FieldType::String)),
+                "verify_hash" => (Some(2), ValueType::Field(FieldType::Boolean)),
+                _ => (None, ValueType::Unknown),
+            };
+            if let Some(expected) = arity
+                && args.len() != expected {
+                return Err(CompileError::validation(
+                    "E0216",
+                    format!("{} expects {} argument(s), found {}", function, expected, args.len()),
+                    location.clone(),
                 ));
             }
-            validate_predicate(predicate, ctx, scope, &derive.location)?;
-        }
-        DeriveValue::Compute { function: _, args } => {
-             for arg in args {
-                 validate_function_arg(arg, scope, &derive.location)?;
-             }
+            Ok(result)
         }
-        DeriveValue::SystemCall { namespace: _, capability: _, args } => {
-             for arg in args {
-                 validate_function_arg(arg, scope, &derive.location)?;
-             }
+        DeriveValue::SystemCall { namespace, capability, args } => {
+            let (_, _, result) = SYSTEM_CAPABILITIES
+                .iter()
+                .find(|(ns, cap, _)| ns == namespace && cap == capability)
+                .ok_or_else(|| {
+                    CompileError::validation_with_hint(
+                        "E0217",
+                        format!("Unknown system capability: system.{}.{}", namespace, capability),
+                        location.clone(),
+                        suggestion_hint(
+                            &format!("system.{}.{}", namespace, capability),
+                            SYSTEM_CAPABILITIES.iter().map(|(ns, cap, _)| format!("system.{}.{}", ns, cap)),
+                            "capabilities",
+                        ),
+                    )
+                })?;
+            for arg in args {
+                infer_function_arg(arg, ctx, scope, location)?;
+            }
+            Ok(result.clone().map(ValueType::Field).unwrap_or(ValueType::Unknown))
         }
-        DeriveValue::Identifier(id) => {
-             // For simple identifier, check if it's in scope (unless dotted)
-             if !id.contains('.') && !scope.contains(id) {
-                  // Not a hard error in previous versions, but good to have
-             }
+    }
+}
+
+fn infer_function_arg(
+    arg: &FunctionArg,
+    ctx: &ValidationContext,
+    scope: &ProcessScope,
+    location: &SourceLocation,
+) -> CompileResult<ValueType> {
+    match arg {
+        FunctionArg::TypeName(name) => {
+            if !ctx.entities.contains_key(name) {
+                return Err(CompileError::validation_with_hint(
+                    "E0107",
+                    format!("Unknown entity: {}", name),
+                    location.clone(),
+                    suggestion_hint(name, ctx.entities.keys(), "entities"),
+                ));
+            }
+            Ok(ValueType::Unknown)
         }
-        _ => {}
+        FunctionArg::Identifier(id) => resolve_identifier(id, scope, location),
+        FunctionArg::FieldAccess { path } => resolve_path(path, ctx, scope, location),
+        FunctionArg::Literal(lit) => Ok(literal_type(lit)),
     }
-    Ok(())
 }
 
-fn validate_mutate(mutate: &MutateBlock, ctx: &ValidationContext, scope: &HashSet<String>) -> CompileResult<()> {
+fn literal_type(lit: &LiteralValue) -> ValueType {
+    ValueType::Field(match lit {
+        LiteralValue::String(_) => FieldType::String,
+        LiteralValue::Number(_) => FieldType::Number,
+        LiteralValue::Boolean(_) => FieldType::Boolean,
+    })
+}
+
+fn resolve_identifier(name: &str, scope: &ProcessScope, location: &SourceLocation) -> CompileResult<ValueType> {
+    scope.lookup(name).ok_or_else(|| {
+        CompileError::validation_with_hint(
+            "E0209",
+            format!("Unknown identifier '{}' in process", name),
+            location.clone(),
+            format!(
+                "Declare it in the input section or derive it before use. {}",
+                suggestion_hint(name, scope.inputs.keys().chain(scope.variables.keys()), "names")
+            ),
+        )
+    })
+}
+
+/// Resolve a dotted path such as `input.email` or `user.password_hash`
+fn resolve_path(
+    path: &[String],
+    ctx: &ValidationContext,
+    scope: &ProcessScope,
+    location: &SourceLocation,
+) -> CompileResult<ValueType> {
+    let Some((head, rest)) = path.split_first() else {
+        return Ok(ValueType::Unknown);
+    };
+
+    let (mut current, rest) = if head == "input" {
+        let Some((name, rest)) = rest.split_first() else {
+            return Err(CompileError::validation(
+                "E0208",
+                "Expected a field name after 'input.'",
+                location.clone(),
+            ));
+        };
+        let field_type = scope.inputs.get(name).ok_or_else(|| {
+            CompileError::validation_with_hint(
+                "E0208",
+                format!("Unknown input field: input.{}", name),
+                location.clone(),
+                suggestion_hint(name, scope.inputs.keys(), "inputs"),
+            )
+        })?;
+        (ValueType::Field(field_type.clone()), rest)
+    } else {
+        (resolve_identifier(head, scope, location)?, rest)
+    };
+
+    for segment in rest {
+        current = match current {
+            ValueType::Entity(entity) => {
+                let field = find_entity_field(ctx, &entity, segment, location)?;
+                ValueType::Field(field.field_type.clone())
+            }
+            ValueType::Unknown => ValueType::Unknown,
+            ValueType::Field(field_type) => {
+                return Err(CompileError::validation(
+                    "E0218",
+                    format!("Cannot access field '{}' on a value of type {}", segment, field_type),
+                    location.clone(),
+                ));
+            }
+        };
+    }
+    Ok(current)
+}
+
+fn find_entity_field<'a>(
+    ctx: &'a ValidationContext,
+    entity: &str,
+    field: &str,
+    location: &SourceLocation,
+) -> CompileResult<&'a Field> {
+    let entity_def = ctx.entities.get(entity).ok_or_else(|| {
+        CompileError::validation_with_hint(
+            "E0107",
+            format!("Unknown entity: {}", entity),
+            location.clone(),
+            suggestion_hint(entity, ctx.entities.keys(), "entities"),
+        )
+    })?;
+    entity_def.fields.iter().find(|f| f.name == field).ok_or_else(|| {
+        CompileError::validation_with_hint(
+            "E0207",
+            format!("Unknown field '{}' in entity '{}'", field, entity),
+            location.clone(),
+            suggestion_hint(field, entity_def.fields.iter().map(|f| &f.name), "fields"),
+        )
+    })
+}
+
+fn validate_mutate(mutate: &MutateBlock, ctx: &ValidationContext, scope: &ProcessScope, diags: &mut Diagnostics) {
     // Check entity exists
-    let entity = ctx.entities.get(&mutate.entity).ok_or_else(|| {
-        CompileError::validation(
+    let Some(entity) = ctx.entities.get(&mutate.entity) else {
+        diags.error(CompileError::validation_with_hint(
+            "E0204",
             format!("Unknown entity in mutate: {}", mutate.entity),
             mutate.location.clone(),
-        )
-    })?;
+            suggestion_hint(&mutate.entity, ctx.entities.keys(), "entities"),
+        ));
+        return;
+    };
 
     // Validate predicate if present (Update mode)
     if let Some(predicate) = &mutate.predicate {
-        validate_predicate(predicate, ctx, scope, &mutate.location)?;
+        diags.check(validate_predicate(predicate, entity, ctx, scope, &mutate.location));
     }
 
     for setter in &mutate.setters {
-        // Check field exists
-        let field = entity.fields.iter().find(|f| f.name == setter.field).ok_or_else(|| {
-            CompileError::validation_with_hint(
-                format!("Unknown field '{}' in entity '{}'", setter.field, mutate.entity),
-                setter.location.clone(),
-                format!("Available fields: {:?}", entity.fields.iter().map(|f| &f.name).collect::<Vec<_>>()),
-            )
-        })?;
+        diags.check(validate_setter(setter, &mutate.entity, ctx, scope));
+    }
+}
 
-        // Check for read-only fields (@primary, @auto)
-        if field.decorators.contains(&Decorator::Primary) {
-             return Err(CompileError::validation(
-                format!("Cannot mutate primary key field '{}'", setter.field),
-                setter.location.clone(),
-            ));
-        }
-        if field.decorators.contains(&Decorator::Auto) {
-             return Err(CompileError::validation(
-                format!("Cannot mutate auto-generated field '{}'", setter.field),
-                setter.location.clone(),
-            ));
-        }
+fn validate_setter(
+    setter: &MutateSetter,
+    entity: &str,
+    ctx: &ValidationContext,
+    scope: &ProcessScope,
+) -> CompileResult<()> {
+    // Check field exists
+    let field = find_entity_field(ctx, entity, &setter.field, &setter.location)?;
 
-        // Validate value expression (context/scope check)
-        // Simplified check: if it's an identifier, must be in scope
+    // Check for read-only fields (@primary, @auto)
+    if field.decorators.contains(&Decorator::Primary) {
+         return Err(CompileError::validation(
+            "E0214",
+            format!("Cannot mutate primary key field '{}'", setter.field),
+            setter.location.clone(),
+        ));
+    }
+    if field.decorators.contains(&Decorator::Auto) {
+         return Err(CompileError::validation(
+            "E0214",
+            format!("Cannot mutate auto-generated field '{}'", setter.field),
+            setter.location.clone(),
+        ));
     }
 
+    // Check the assigned value against the field type
+    let field_type = ValueType::Field(field.field_type.clone());
+    let value_type = infer_value(&setter.value, ctx, scope, &setter.location)?;
+    if !types_compatible(&field_type, &value_type) {
+        return Err(CompileError::validation(
+            "E0210",
+            format!(
+                "Type mismatch: cannot assign {} to field '{}' of type {}",
+                value_type, setter.field, field_type
+            ),
+            setter.location.clone(),
+        ));
+    }
+    if let DeriveValue::Literal(lit) = &setter.value {
+        check_enum_literal(&field.field_type, lit, &setter.location)?;
+    }
     Ok(())
 }
 
-fn validate_delete(delete: &DeleteStatement, ctx: &ValidationContext, scope: &HashSet<String>) -> CompileResult<()> {
-    if !ctx.entities.contains_key(&delete.entity) {
-         return Err(CompileError::validation(
+fn validate_delete(delete: &DeleteStatement, ctx: &ValidationContext, scope: &ProcessScope) -> CompileResult<()> {
+    let entity = ctx.entities.get(&delete.entity).ok_or_else(|| {
+        CompileError::validation_with_hint(
+            "E0205",
             format!("Unknown entity in delete: {}", delete.entity),
             delete.location.clone(),
+            suggestion_hint(&delete.entity, ctx.entities.keys(), "entities"),
+        )
+    })?;
+    validate_predicate(&delete.predicate, entity, ctx, scope, &delete.location)?;
+    Ok(())
+}
+
+/// Validate a `where` predicate: the left side names a column of the target entity,
+/// the right side must resolve in scope and be comparable with it
+fn validate_predicate(
+    predicate: &Predicate,
+    entity: &Entity,
+    ctx: &ValidationContext,
+    scope: &ProcessScope,
+    location: &SourceLocation,
+) -> CompileResult<()> {
+    let column = match &predicate.field {
+        FieldReference::InputField(name) | FieldReference::DerivedField { field: name, .. } => name,
+        FieldReference::Literal(_) => {
+            return Err(CompileError::validation_with_hint(
+                "E0215",
+                format!("Left side of a predicate must be a field of '{}'", entity.name),
+                location.clone(),
+                "Write predicates as `field == value`",
+            ));
+        }
+    };
+    let column_field = find_entity_field(ctx, &entity.name, column, location)?;
+    let left = ValueType::Field(column_field.field_type.clone());
+
+    let right = match &predicate.value {
+        FieldReference::InputField(name) => resolve_identifier(name, scope, location)?,
+        FieldReference::DerivedField { name, field } => {
+            resolve_path(&[name.clone(), field.clone()], ctx, scope, location)?
+        }
+        FieldReference::Literal(lit) => {
+            check_enum_literal(&column_field.field_type, lit, location)?;
+            literal_type(lit)
+        }
+    };
+
+    if !types_compatible(&left, &right) {
+        return Err(CompileError::validation(
+            "E0211",
+            format!(
+                "Cannot compare {}.{} ({}) with a value of type {}",
+                entity.name, column, left, right
+            ),
+            location.clone(),
+        ));
+    }
+
+    if matches!(predicate.operator, CompareOp::Less | CompareOp::Greater)
+        && !(is_ordered(&left) && is_ordered(&right))
+    {
+        return Err(CompileError::validation_with_hint(
+            "E0212",
+            format!(
+                "Operator '{}' is not supported between {} and {}",
+                predicate.operator, left, right
+            ),
+            location.clone(),
+            "Ordering comparisons require number or datetime operands",
         ));
     }
-    validate_predicate(&delete.predicate, ctx, scope, &delete.location)?;
     Ok(())
 }
 
-fn validate_predicate(_predicate: &Predicate, _ctx: &ValidationContext, _scope: &HashSet<String>, _location: &SourceLocation) -> CompileResult<()> {
-    // Validate field references in predicate
-    // TODO: deeper validation of types
+/// Reject string literals that are not members of an enum field
+fn check_enum_literal(field_type: &FieldType, lit: &LiteralValue, location: &SourceLocation) -> CompileResult<()> {
+    if let (FieldType::Enum(values), LiteralValue::String(value)) = (strip_optional(field_type), lit)
+        && !values.contains(value) {
+        return Err(CompileError::validation_with_hint(
+            "E0213",
+            format!("'{}' is not a value of {}", value, field_type),
+            location.clone(),
+            format!("Allowed values: {:?}", values),
+        ));
+    }
     Ok(())
 }
 
-fn validate_function_arg(arg: &FunctionArg, scope: &HashSet<String>, _location: &SourceLocation) -> CompileResult<()> {
-    match arg {
-        FunctionArg::Identifier(id) => {
-             if !scope.contains(id) {
-                  // Warn or Error?
-             }
+/// Broad type categories used for compatibility checks
+#[derive(PartialEq)]
+enum TypeKind {
+    Text,
+    Number,
+    Boolean,
+    DateTime,
+    Collection,
+}
+
+fn type_kind(field_type: &FieldType) -> TypeKind {
+    match strip_optional(field_type) {
+        FieldType::Number => TypeKind::Number,
+        FieldType::Boolean => TypeKind::Boolean,
+        FieldType::DateTime => TypeKind::DateTime,
+        FieldType::Array(_) | FieldType::List(_) => TypeKind::Collection,
+        _ => TypeKind::Text,
+    }
+}
+
+fn strip_optional(field_type: &FieldType) -> &FieldType {
+    match field_type {
+        FieldType::Optional(inner) => strip_optional(inner),
+        other => other,
+    }
+}
+
+fn types_compatible(a: &ValueType, b: &ValueType) -> bool {
+    match (a, b) {
+        (ValueType::Unknown, _) | (_, ValueType::Unknown) => true,
+        (ValueType::Entity(x), ValueType::Entity(y)) => x == y,
+        (ValueType::Field(x), ValueType::Field(y)) => field_types_compatible(x, y),
+        _ => false,
+    }
+}
+
+fn field_types_compatible(a: &FieldType, b: &FieldType) -> bool {
+    match (strip_optional(a), strip_optional(b)) {
+        (FieldType::Array(x) | FieldType::List(x), FieldType::Array(y) | FieldType::List(y)) => {
+            field_types_compatible(x, y)
+        }
+        (x, y) => {
+            let (x, y) = (type_kind(x), type_kind(y));
+            // Datetimes travel as ISO strings through inputs
+            x == y || matches!((x, y), (TypeKind::DateTime, TypeKind::Text) | (TypeKind::Text, TypeKind::DateTime))
         }
-        _ => {}
     }
-    Ok(())
+}
+
+fn is_ordered(value_type: &ValueType) -> bool {
+    match value_type {
+        ValueType::Unknown => true,
+        ValueType::Field(t) => matches!(type_kind(t), TypeKind::Number | TypeKind::DateTime),
+        ValueType::Entity(_) => false,
+    }
+}
+
+/// Hint listing the available names, led by the closest match to `name` when one is near enough
+fn suggestion_hint<S: AsRef<str>>(name: &str, candidates: impl IntoIterator<Item = S>, kind: &str) -> String {
+    let mut names: Vec<String> = candidates.into_iter().map(|c| c.as_ref().to_string()).collect();
+    names.sort();
+    names.dedup();
+    let available = format!("Available {}: {:?}", kind, names);
+    match closest_match(name, &names) {
+        Some(best) => format!("Did you mean '{}'? {}", best, available),
+        None => available,
+    }
+}
+
+/// Closest candidate by case-insensitive edit distance, allowing roughly one typo per three characters
+fn closest_match<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    let name = name.to_lowercase();
+    candidates
+        .iter()
+        .map(|c| (edit_distance(&name, &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c.as_str())
+}
+
+/// Edit distance counting insertions, deletions, substitutions and adjacent transpositions
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
 }
 
 #[cfg(test)]
@@ -688,7 +1226,7 @@ entity User:
     name: string
 "#;
         let file = parse_intent(source).unwrap();
-        let result = validate(&file);
+        let result = validate(&file).0;
         assert!(result.is_err());
     }
 
@@ -699,7 +1237,7 @@ entity User:
     author: UnknownEntity
 "#;
         let file = parse_intent(source).unwrap();
-        let result = validate(&file);
+        let result = validate(&file).0;
         assert!(result.is_err());
     }
 
@@ -716,7 +1254,7 @@ action create_user:
     output: User(id, name)
 "#;
         let file = parse_intent(source).unwrap();
-        let result = validate(&file);
+        let result = validate(&file).0;
         assert!(result.is_ok());
     }
 
@@ -732,7 +1270,7 @@ action private_action:
     output: User(id)
 "#;
         let file = parse_intent(source).unwrap();
-        let result = validate(&file);
+        let result = validate(&file).0;
         assert!(result.is_err());
         match result.err().unwrap() {
             CompileError::ValidationError { message, .. } => {
@@ -770,7 +1308,7 @@ action get_order:
     output: Order(id)
 "#;
         let file = crate::parser::parse_intent(source).unwrap();
-        let result = validate(&file);
+        let result = validate(&file).0;
         assert!(result.is_ok(), "Error: {:?}", result.err());
     }
 
@@ -787,7 +1325,7 @@ policy AdminOnly:
     require subject.role == "admin"
 "#;
         let file = crate::parser::parse_intent(source).unwrap();
-        let result = validate(&file);
+        let result = validate(&file).0;
         assert!(result.is_err());
         match result.err().unwrap() {
             CompileError::ValidationError { message, .. } => {
@@ -796,4 +1334,355 @@ policy AdminOnly:
             _ => panic!("Expected validation error"),
         }
     }
+
+    const PROCESS_ENTITIES: &str = r#"
+entity Ticket:
+    id: uuid @primary
+    title: string
+    priority: number
+    status: open | closed
+    opened_at: datetime
+"#;
+
+    fn validation_message(source: &str) -> String {
+        let file = parse_intent(&format!("{}{}", PROCESS_ENTITIES, source)).unwrap();
+        match validate(&file).0 {
+            Err(CompileError::ValidationError { message, .. }) => message,
+            Err(other) => panic!("Expected validation error, got {:?}", other),
+            Ok(_) => panic!("Expected validation to fail"),
+        }
+    }
+
+    #[test]
+    fn test_validate_unknown_input_field() {
+        let message = validation_message(r#"
+@api PATCH /tickets/{id}
+action rename_ticket:
+    input:
+        id: uuid
+    process:
+        mutate Ticket where id == input.id:
+            set title = input.title
+    output: Ticket(id)
+"#);
+        assert!(message.contains("Unknown input field: input.title"));
+    }
+
+    #[test]
+    fn test_validate_predicate_unknown_field() {
+        let message = validation_message(r#"
+@api GET /tickets/{id}
+action get_ticket:
+    input:
+        id: uuid
+    process:
+        derive ticket = select Ticket where ticket_id == input.id
+    output: Ticket(id)
+"#);
+        assert!(message.contains("Unknown field 'ticket_id' in entity 'Ticket'"));
+    }
+
+    #[test]
+    fn test_validate_predicate_incomparable_types() {
+        let message = validation_message(r#"
+@api GET /tickets/{id}
+action get_ticket:
+    input:
+        id: uuid
+        urgent: boolean
+    process:
+        derive ticket = select Ticket where priority == input.urgent
+    output: Ticket(id)
+"#);
+        assert!(message.contains("Cannot compare Ticket.priority"));
+    }
+
+    #[test]
+    fn test_validate_predicate_ordering_requires_numbers() {
+        let message = validation_message(r#"
+@api GET /tickets/{id}
+action get_ticket:
+    input:
+        id: uuid
+    process:
+        derive ticket = select Ticket where title > "a"
+    output: Ticket(id)
+"#);
+        assert!(message.contains("Operator '>' is not supported"));
+    }
+
+    #[test]
+    fn test_validate_setter_type_mismatch_from_derived_value() {
+        let message = validation_message(r#"
+@api PATCH /tickets/{id}
+action close_ticket:
+    input:
+        id: uuid
+    process:
+        derive closed = true
+        mutate Ticket where id == input.id:
+            set title = closed
+    output: Ticket(id)
+"#);
+        assert!(message.contains("cannot assign boolean to field 'title' of type string"));
+    }
+
+    #[test]
+    fn test_validate_setter_invalid_enum_literal() {
+        let message = validation_message(r#"
+@api PATCH /tickets/{id}
+action close_ticket:
+    input:
+        id: uuid
+    process:
+        mutate Ticket where id == input.id:
+            set status = "archived"
+    output: Ticket(id)
+"#);
+        assert!(message.contains("'archived' is not a value of"));
+    }
+
+    #[test]
+    fn test_validate_out_of_scope_identifier() {
+        let message = validation_message(r#"
+@api GET /tickets/slug
+action ticket_slug:
+    input:
+        id: uuid
+    process:
+        derive slug = compute slugify(title)
+    output: Ticket(slug)
+"#);
+        assert!(message.contains("Unknown identifier 'title'"));
+    }
+
+    #[test]
+    fn test_validate_typed_process() {
+        let source = format!("{}{}", PROCESS_ENTITIES, r#"
+@api PATCH /tickets/{id}
+action update_ticket:
+    input:
+        id: uuid
+        title: string
+        priority: number
+    process:
+        derive ticket = select Ticket where id == input.id
+        derive slug = compute slugify(input.title)
+        mutate Ticket where id == ticket.id:
+            set title = slug
+            set priority = input.priority
+            set status = "closed"
+    output: Ticket(id, title)
+"#);
+        let file = parse_intent(&source).unwrap();
+        assert!(validate(&file).0.is_ok());
+    }
+
+    #[test]
+    fn test_validate_output_unknown_field() {
+        let message = validation_message(r#"
+@api GET /tickets
+action list_tickets:
+    output: Ticket(id, titel)
+"#);
+        assert!(message.contains("Unknown field 'titel' in output of action 'list_tickets'"));
+    }
+
+    #[test]
+    fn test_validate_output_secret_field() {
+        let source = r#"
+entity Account:
+    id: uuid @primary
+    password_hash: string
+
+@api GET /accounts
+action list_accounts:
+    output: Account(id, password_hash)
+"#;
+        let file = parse_intent(source).unwrap();
+        match validate(&file).0 {
+            Err(CompileError::ValidationError { message, .. }) => {
+                assert!(message.contains("exposes secret field 'Account.password_hash'"));
+            }
+            other => panic!("Expected validation error, got {:?}", other.err()),
+        }
+
+        let exposed = source.replace("password_hash: string", "password_hash: string @expose");
+        let file = parse_intent(&exposed).unwrap();
+        assert!(validate(&file).0.is_ok());
+    }
+
+    #[test]
+    fn test_validate_output_entity_not_produced() {
+        let message = validation_message(r#"
+@api POST /tickets/slug
+action ticket_slug:
+    input:
+        title: string
+    process:
+        derive slug = compute slugify(input.title)
+    output: Ticket(id, slug)
+"#);
+        assert!(message.contains("never produces a Ticket record"));
+    }
+
+    #[test]
+    fn test_validate_output_errors_point_at_projection() {
+        let source = format!(
+            "{}{}",
+            PROCESS_ENTITIES,
+            "\n@api GET /tickets\naction list_tickets:\n    output: Ticket(id, titel)\n"
+        );
+        let line = source.lines().position(|l| l.contains("titel")).unwrap() + 1;
+        match validate(&parse_intent(&source).unwrap()).0 {
+            Err(CompileError::ValidationError { location, .. }) => {
+                assert_eq!((location.line, location.column), (line, 13));
+            }
+            other => panic!("Expected validation error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_validate_output_after_failed_derive() {
+        // The misspelled select is the only error; the output isn't also reported as never produced
+        let message = validation_message(r#"
+@api GET /tickets/{id}
+action get_ticket:
+    input:
+        id: uuid
+    process:
+        derive ticket = select Tiket where id == input.id
+    output: Ticket(id, title)
+"#);
+        assert!(message.contains("Unknown entity in select: Tiket"), "{}", message);
+
+        let message = validation_message(r#"
+@api POST /tickets/slug
+action ticket_slug:
+    input:
+        title: string
+    process:
+        derive ticket = compute slugify(titel)
+    output: Ticket(id)
+"#);
+        assert!(message.contains("Unknown identifier 'titel'"), "{}", message);
+    }
+
+    #[test]
+    fn test_validate_output_current_user() {
+        let source = r#"
+auth entity User:
+    id: uuid @primary
+    email: email @unique
+    password_hash: string
+
+@api POST /me/touch
+@auth
+action touch_me:
+    process:
+        derive slug = compute slugify(current_user.email)
+    output: User(id, email)
+"#;
+        let file = parse_intent(source).unwrap();
+        assert!(validate(&file).0.is_ok(), "{:?}", validate(&file).0.err());
+    }
+
+    #[test]
+    fn test_validate_collects_all_errors_sorted() {
+        let source = r#"
+entity Ticket:
+    id: uuid @primary
+    title: string
+    title: string
+
+@api PATCH /tickets/{id}
+action update_ticket:
+    input:
+        id: uuid
+    process:
+        mutate Ticket where id == input.id:
+            set title = input.name
+            set priority = 1
+    output: Ticket(id, titel)
+"#;
+        let file = parse_intent(source).unwrap();
+        let errors = match validate(&file).0 {
+            Err(CompileError::MultipleErrors(errors)) => errors,
+            other => panic!("Expected multiple errors, got {:?}", other.err()),
+        };
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 4, "{:?}", messages);
+        assert!(messages[0].contains("Duplicate field name 'title'"));
+        assert!(messages.iter().any(|m| m.contains("Unknown input field: input.name")));
+        assert!(messages.iter().any(|m| m.contains("Unknown field 'priority'")));
+        assert!(messages.iter().any(|m| m.contains("Unknown field 'titel'")));
+
+        let lines: Vec<usize> = errors.iter().filter_map(|e| e.location()).map(|l| l.line).collect();
+        let mut sorted = lines.clone();
+        sorted.sort();
+        assert_eq!(lines, sorted);
+    }
+
+    fn validation_hint(source: &str) -> String {
+        let file = parse_intent(&format!("{}{}", PROCESS_ENTITIES, source)).unwrap();
+        match validate(&file).0 {
+            Err(CompileError::ValidationError { hint, .. }) => hint.unwrap_or_default(),
+            other => panic!("Expected a single validation error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+
+    #[test]
+    fn test_suggests_entity_name() {
+        let hint = validation_hint(r#"
+@api GET /tickets/{id}
+action get_ticket:
+    input:
+        id: uuid
+    process:
+        derive ticket = select Tiket where id == input.id
+    output: Ticket(id)
+"#);
+        assert!(hint.starts_with("Did you mean 'Ticket'?"), "{}", hint);
+    }
+
+    #[test]
+    fn test_suggests_field_and_derived_variable() {
+        let hint = validation_hint(r#"
+@api GET /tickets
+action list_tickets:
+    output: Ticket(id, titel)
+"#);
+        assert!(hint.contains("Did you mean 'title'?"), "{}", hint);
+
+        let hint = validation_hint(r#"
+@api POST /tickets/slug
+action ticket_slug:
+    input:
+        title: string
+    process:
+        derive slug = compute slugify(input.title)
+        derive upper = compute slugify(slgu)
+    output: Ticket(slug)
+"#);
+        assert!(hint.contains("Did you mean 'slug'?"), "{}", hint);
+    }
+
+    #[test]
+    fn test_no_suggestion_for_distant_names() {
+        let hint = validation_hint(r#"
+@api GET /tickets
+@policy(Zebra)
+action list_tickets:
+    output: Ticket(id)
+"#);
+        assert!(!hint.contains("Did you mean"), "{}", hint);
+        assert!(hint.starts_with("Available policies"), "{}", hint);
+    }
 }
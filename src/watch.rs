@@ -0,0 +1,148 @@
+// Intent Compiler - Watch Mode
+// Reruns a build whenever one of the files it read changes
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use colored::Colorize;
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use walkdir::WalkDir;
+
+use crate::error::{CompileError, CompileResult};
+
+/// Quiet time before rebuilding, so an editor's burst of writes causes one rebuild
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+fn watch_error(error: impl ToString) -> CompileError {
+    CompileError::IoError(io::Error::other(error.to_string()))
+}
+
+/// Run `build` now and after every change to the files it lists in its argument.
+/// A directory in the list counts as everything under it, and may not exist yet.
+/// A build that fails before finding out which files it reads leaves the list as it was.
+/// Returns only if there is nothing to watch or the watcher fails.
+pub fn watch(mut build: impl FnMut(&mut Vec<PathBuf>)) -> CompileResult<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, sender).map_err(watch_error)?;
+    let mut files = Vec::new();
+    let mut directories = BTreeSet::new();
+    let mut trees = BTreeSet::new();
+    let mut contents = HashMap::new();
+    build(&mut files);
+    if files.is_empty() {
+        return Err(CompileError::config("no files to watch"));
+    }
+
+    loop {
+        // Editors that save by renaming replace the file, so watch the directories holding it
+        let watched: Vec<PathBuf> = files.iter().map(|f| absolute(f)).collect();
+        let wanted: BTreeSet<PathBuf> = watched.iter().filter_map(|f| f.parent().map(Path::to_path_buf)).collect();
+        for directory in wanted.difference(&directories) {
+            debouncer.watcher().watch(directory, RecursiveMode::NonRecursive).map_err(watch_error)?;
+        }
+        for directory in directories.difference(&wanted) {
+            // The directory may be gone already
+            let _ = debouncer.watcher().unwatch(directory);
+        }
+        directories = wanted;
+        let wanted: BTreeSet<PathBuf> = watched.iter().filter(|f| f.is_dir()).cloned().collect();
+        for tree in wanted.difference(&trees) {
+            debouncer.watcher().watch(tree, RecursiveMode::Recursive).map_err(watch_error)?;
+        }
+        for tree in trees.difference(&wanted) {
+            let _ = debouncer.watcher().unwatch(tree);
+        }
+        trees = wanted;
+        for file in &files {
+            contents.entry(file.clone()).or_insert_with(|| snapshot(file));
+        }
+        eprintln!("{} {} (Ctrl-C to stop)", "Watching".cyan().bold(), display(&files));
+
+        // Events also fire for reads, including the build's own, so compare contents
+        let changed = loop {
+            let events = receiver.recv().map_err(watch_error)?.map_err(watch_error)?;
+            if !events.iter().any(|e| watched.iter().any(|w| e.path.starts_with(w))) {
+                continue;
+            }
+            let changed: Vec<PathBuf> = files.iter().filter(|f| contents[*f] != snapshot(f)).cloned().collect();
+            if !changed.is_empty() {
+                break changed;
+            }
+        };
+        eprintln!("{} {}", "Changed".cyan().bold(), display(&changed));
+        // Read before building so that edits made during the build trigger another one
+        contents = files.iter().map(|f| (f.clone(), snapshot(f))).collect();
+        build(&mut files);
+    }
+}
+
+/// `path` with its directory made absolute, as file events report it; the file itself may not exist
+fn absolute(path: &Path) -> PathBuf {
+    let directory = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    match (directory.canonicalize(), path.file_name()) {
+        (Ok(directory), Some(name)) => directory.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A file's contents, or the names and contents of everything under a directory; None if missing
+fn snapshot(path: &Path) -> Option<Vec<u8>> {
+    if !path.is_dir() {
+        return fs::read(path).ok();
+    }
+    let mut snapshot = Vec::new();
+    for entry in WalkDir::new(path).sort_by_file_name().into_iter().flatten() {
+        snapshot.extend(entry.path().to_string_lossy().as_bytes());
+        snapshot.push(0);
+        if entry.file_type().is_file() {
+            snapshot.extend(fs::read(entry.path()).unwrap_or_default());
+            snapshot.push(0);
+        }
+    }
+    Some(snapshot)
+}
+
+fn display(files: &[PathBuf]) -> String {
+    files.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_absolute_matches_event_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.intent");
+        // Event paths are canonical even when the watched path isn't
+        let roundabout = dir.path().join(".").join("app.intent");
+        assert_eq!(absolute(&roundabout), dir.path().canonicalize().unwrap().join("app.intent"));
+        // The file doesn't have to exist, e.g. while an editor replaces it
+        assert_eq!(absolute(&file), absolute(&roundabout));
+    }
+
+    #[test]
+    fn test_directory_changes_with_anything_under_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let templates = dir.path().join("templates");
+        let missing = snapshot(&templates);
+        assert_eq!(missing, None);
+
+        fs::create_dir_all(templates.join("db")).unwrap();
+        let empty = snapshot(&templates);
+        assert_ne!(empty, missing);
+
+        fs::write(templates.join("db/models.py.tera"), "# v1").unwrap();
+        let added = snapshot(&templates);
+        assert_ne!(added, empty);
+
+        fs::write(templates.join("db/models.py.tera"), "# v2").unwrap();
+        assert_ne!(snapshot(&templates), added);
+        assert_eq!(snapshot(&templates), snapshot(&templates));
+    }
+}